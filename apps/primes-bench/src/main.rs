@@ -0,0 +1,189 @@
+//! Benchmark Sweep for the Prime Sieves
+//!
+//! Runs the sequential and multithreaded sieves from `primes-core` over
+//! every combination of `--limits` and `--threads`, repeats each
+//! configuration `--repeat` times, and prints one CSV row per
+//! configuration with the min, mean and standard deviation of the wall
+//! times. This replaces looping over the apps' `--csv` mode in a shell.
+//!
+//! # Usage
+//!
+//! ```bash
+//! primes-bench --limits 1000000,10000000 --threads 1,2,4,8 --repeat 5
+//! ```
+
+use clap::Parser;
+use primes_core::{segmented_sieve_parallel_cached, sieve_into, BasePrimeCache, SieveError, SievePool};
+use std::time::Instant;
+
+/// Sweep the prime sieves across limits and thread counts
+#[derive(Parser, Debug)]
+#[command(name = "primes-bench")]
+#[command(about = "Benchmark the prime sieves across limits and thread counts", long_about = None)]
+struct Args {
+    /// Upper limits to sieve, comma-separated
+    #[arg(short, long, value_delimiter = ',', default_values_t = [1_000_000, 10_000_000])]
+    limits: Vec<u64>,
+
+    /// Thread counts for the multithreaded sieve, comma-separated
+    #[arg(short, long, value_delimiter = ',', default_values_t = [1, 2, 4, 8])]
+    threads: Vec<usize>,
+
+    /// Timed runs per configuration
+    #[arg(short, long, default_value_t = 3)]
+    repeat: usize,
+
+    /// Run the multithreaded sieve on a SievePool reused across repeats
+    #[arg(long, default_value_t = false)]
+    pool: bool,
+}
+
+/// Summary of the repeated timings of one configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TimingStats {
+    min: f64,
+    mean: f64,
+    /// Sample standard deviation (n - 1); 0 for fewer than two samples
+    stddev: f64,
+}
+
+/// Aggregate a set of timings in milliseconds
+fn timing_stats(samples: &[f64]) -> TimingStats {
+    if samples.is_empty() {
+        return TimingStats {
+            min: 0.0,
+            mean: 0.0,
+            stddev: 0.0,
+        };
+    }
+
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let stddev = if samples.len() > 1 {
+        let squares: f64 = samples.iter().map(|t| (t - mean).powi(2)).sum();
+        (squares / (n - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+
+    TimingStats {
+        min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+        mean,
+        stddev,
+    }
+}
+
+/// Run `sieve` `repeat` times, returning its prime count and the timings in ms
+///
+/// Every run fills the same buffer, so after the first one the timings
+/// leave out allocating the result.
+fn time_runs(
+    repeat: usize,
+    mut sieve: impl FnMut(&mut Vec<u64>) -> Result<(), SieveError>,
+) -> Result<(usize, Vec<f64>), SieveError> {
+    let mut primes = Vec::new();
+    let mut samples = Vec::with_capacity(repeat);
+    for _ in 0..repeat.max(1) {
+        let start = Instant::now();
+        sieve(&mut primes)?;
+        samples.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    Ok((primes.len(), samples))
+}
+
+/// One CSV row: algorithm,limit,threads,runs,prime_count,min_ms,mean_ms,stddev_ms
+fn csv_row(algorithm: &str, limit: u64, threads: usize, count: usize, samples: &[f64]) -> String {
+    let stats = timing_stats(samples);
+    format!(
+        "{},{},{},{},{},{:.3},{:.3},{:.3}",
+        algorithm,
+        limit,
+        threads,
+        samples.len(),
+        count,
+        stats.min,
+        stats.mean,
+        stats.stddev
+    )
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    println!("algorithm,limit,threads,runs,prime_count,min_ms,mean_ms,stddev_ms");
+
+    // One pool per thread count, shared by every limit and repeat
+    let pools: Vec<Option<SievePool>> = args
+        .threads
+        .iter()
+        .map(|&threads| args.pool.then(|| SievePool::new(threads)))
+        .collect();
+    // Base primes are found once for the largest √limit, not per run
+    let mut base_cache = BasePrimeCache::new();
+
+    for &limit in &args.limits {
+        let (expected, samples) = time_runs(args.repeat, |out| sieve_into(limit, out)).map_err(|e| e.to_string())?;
+        println!("{}", csv_row("sequential", limit, 1, expected, &samples));
+
+        for (&threads, pool) in args.threads.iter().zip(&pools) {
+            let (algorithm, result) = match pool {
+                Some(pool) => ("pool", time_runs(args.repeat, |out| pool.compute_cached_into(limit, &mut base_cache, out))),
+                None => (
+                    "multithread",
+                    time_runs(args.repeat, |out| {
+                        *out = segmented_sieve_parallel_cached(limit, threads, &mut base_cache)?.0;
+                        Ok(())
+                    }),
+                ),
+            };
+            let (count, samples) = result.map_err(|e| e.to_string())?;
+            if count != expected {
+                return Err(format!(
+                    "{} with {} threads found {} primes up to {}, sequential found {}",
+                    algorithm, threads, count, limit, expected
+                ));
+            }
+            println!("{}", csv_row(algorithm, limit, threads, count, &samples));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_stats() {
+        let stats = timing_stats(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(stats.min, 2.0);
+        assert_eq!(stats.mean, 5.0);
+        // Sum of squared deviations is 32, over n - 1 = 7
+        assert!((stats.stddev - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+
+        let single = timing_stats(&[3.5]);
+        assert_eq!((single.min, single.mean, single.stddev), (3.5, 3.5, 0.0));
+    }
+
+    #[test]
+    fn test_csv_row() {
+        let row = csv_row("multithread", 1000, 4, 168, &[1.0, 2.0, 3.0]);
+        assert_eq!(row, "multithread,1000,4,3,168,1.000,2.000,1.000");
+    }
+
+    #[test]
+    fn test_parse_lists() {
+        let args = Args::parse_from(["primes-bench", "--limits", "100,1000", "-t", "1,3", "--repeat", "2"]);
+        assert_eq!(args.limits, vec![100, 1000]);
+        assert_eq!(args.threads, vec![1, 3]);
+        assert_eq!(args.repeat, 2);
+    }
+}
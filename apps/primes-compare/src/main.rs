@@ -0,0 +1,133 @@
+//! Sequential vs Multithreaded Comparison
+//!
+//! Runs the sequential sieve and the multithreaded segmented sieve from
+//! `primes-core` once each at the same `--limit`, checks that they found
+//! exactly the same primes, and prints both times and the speedup.
+//!
+//! # Usage
+//!
+//! ```bash
+//! primes-compare --limit 10000000 --threads 4
+//! ```
+
+use clap::Parser;
+use primes_core::{resolve_threads, segmented_sieve_parallel, simple_sieve, SieveError};
+use std::time::Instant;
+
+/// Compare the sequential and multithreaded sieves at one limit
+#[derive(Parser, Debug)]
+#[command(name = "primes-compare")]
+#[command(about = "Run the sequential and multithreaded sieves and report the speedup", long_about = None)]
+struct Args {
+    /// Upper limit of the range to search for primes (inclusive), e.g. 10000000, 10M or 1e7; the flag overrides PRIMES_LIMIT
+    #[arg(short, long, env = "PRIMES_LIMIT", default_value_t = 10_000_000, value_parser = primes_core::parse_limit)]
+    limit: u64,
+
+    /// Threads for the multithreaded sieve (default: available parallelism); the flag overrides PRIMES_THREADS
+    #[arg(short, long, env = "PRIMES_THREADS")]
+    threads: Option<usize>,
+}
+
+/// Outcome of running both sieves at one limit
+#[derive(Debug)]
+struct Comparison {
+    threads: usize,
+    prime_count: usize,
+    sequential_ms: f64,
+    multithread_ms: f64,
+    /// Both sieves returned the same primes in the same order
+    matches: bool,
+}
+
+impl Comparison {
+    /// sequential time / multithreaded time (0 if the latter took no time)
+    fn speedup(&self) -> f64 {
+        if self.multithread_ms > 0.0 {
+            self.sequential_ms / self.multithread_ms
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Run `sieve` once, returning its primes and the time it took in ms
+fn timed(sieve: impl FnOnce() -> Result<Vec<u64>, SieveError>) -> Result<(Vec<u64>, f64), SieveError> {
+    let start = Instant::now();
+    let primes = sieve()?;
+    Ok((primes, start.elapsed().as_secs_f64() * 1000.0))
+}
+
+/// Sieve up to `limit` sequentially and with `threads` threads
+fn compare(limit: u64, threads: usize) -> Result<Comparison, SieveError> {
+    let (sequential, sequential_ms) = timed(|| simple_sieve(limit))?;
+    let (multithread, multithread_ms) =
+        timed(|| segmented_sieve_parallel(limit, threads).map(|(primes, _)| primes))?;
+
+    Ok(Comparison {
+        threads,
+        prime_count: sequential.len(),
+        sequential_ms,
+        multithread_ms,
+        matches: sequential == multithread,
+    })
+}
+
+/// The report printed for a comparison
+fn render(comparison: &Comparison, limit: u64) -> String {
+    let row = |name: &str, threads: usize, time_ms: f64| format!("  {:<12} {:>7} {:>12.3}\n", name, threads, time_ms);
+
+    let mut report = format!("Limit: {}   Primes: {}\n\n", limit, comparison.prime_count);
+    report += &format!("  {:<12} {:>7} {:>12}\n", "sieve", "threads", "time_ms");
+    report += &row("sequential", 1, comparison.sequential_ms);
+    report += &row("multithread", comparison.threads, comparison.multithread_ms);
+    report += &format!("\nSpeedup: {:.2}x\n", comparison.speedup());
+    report += &format!("Outputs match: {}", if comparison.matches { "yes" } else { "NO" });
+    report
+}
+
+fn main() {
+    let args = Args::parse();
+    let threads = resolve_threads(args.threads);
+
+    let comparison = match compare(args.limit, threads) {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{}", render(&comparison, args.limit));
+    if !comparison.matches {
+        eprintln!("Error: the multithreaded sieve found different primes");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_matches_with_positive_speedup() {
+        let comparison = compare(1_000_000, 4).unwrap();
+        assert!(comparison.matches);
+        assert_eq!(comparison.prime_count, 78_498);
+        assert!(comparison.speedup() > 0.0, "{:?}", comparison);
+    }
+
+    #[test]
+    fn test_render() {
+        let comparison = Comparison {
+            threads: 4,
+            prime_count: 168,
+            sequential_ms: 3.0,
+            multithread_ms: 1.5,
+            matches: true,
+        };
+        let report = render(&comparison, 1000);
+        assert!(report.starts_with("Limit: 1000   Primes: 168\n"), "{}", report);
+        assert!(report.contains("  multithread        4        1.500\n"), "{}", report);
+        assert!(report.ends_with("Speedup: 2.00x\nOutputs match: yes"), "{}", report);
+    }
+}
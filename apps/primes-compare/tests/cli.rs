@@ -0,0 +1,25 @@
+//! End-to-end checks of the `primes-compare` binary
+
+use assert_cmd::Command;
+
+#[test]
+fn test_reports_match_and_speedup() {
+    let output = Command::cargo_bin("primes-compare")
+        .unwrap()
+        .args(["--limit", "1000000", "--threads", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Primes: 78498"), "{}", stdout);
+    assert!(stdout.contains("Outputs match: yes"), "{}", stdout);
+
+    let speedup: f64 = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Speedup: ")?.strip_suffix('x'))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(speedup > 0.0, "{}", stdout);
+}
@@ -0,0 +1,130 @@
+//! Read and write buffering for a TCP master/worker connection
+//!
+//! Reading a frame takes three `read_exact` calls (header, payload,
+//! checksum), and on a raw socket each is at least one syscall. A
+//! [`Buffered`] channel reads as much as the socket has, up to
+//! `--tcp-buffer-size` bytes, and serves the frame from that; writes
+//! collect in a `BufWriter` until [`write_message`](crate::protocol::write_message)
+//! flushes them at the end of the message.
+//!
+//! One type does both because a TLS stream cannot be split into halves
+//! the way a `TcpStream` can be cloned.
+
+use std::io::{self, BufWriter, Read, Write};
+
+/// A stream with a read buffer and a write buffer of the same size
+pub struct Buffered<T: Write> {
+    writer: BufWriter<T>,
+    read_buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<T: Read + Write> Buffered<T> {
+    /// Buffer `inner` with `capacity` bytes each way (at least one)
+    pub fn new(inner: T, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Buffered {
+            writer: BufWriter::with_capacity(capacity, inner),
+            read_buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl<T: Read + Write> Read for Buffered<T> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.filled {
+            // Whatever the peer is waiting for must go out before blocking
+            self.writer.flush()?;
+            if out.len() >= self.read_buf.len() {
+                return self.writer.get_mut().read(out);
+            }
+            self.filled = self.writer.get_mut().read(&mut self.read_buf)?;
+            self.pos = 0;
+        }
+
+        let n = out.len().min(self.filled - self.pos);
+        out[..n].copy_from_slice(&self.read_buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<T: Read + Write> Write for Buffered<T> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.writer.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{read_message, write_message, Message};
+    use std::collections::VecDeque;
+
+    /// Bytes written come back out as reads, which are counted
+    #[derive(Default)]
+    struct Loopback {
+        queue: VecDeque<u8>,
+        reads: usize,
+        writes: usize,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            self.queue.read(out)
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.queue.extend(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_small_buffer_reassembles_large_frames() {
+        let work = Message::Work {
+            low: 1_001,
+            high: 1_000_000,
+            limit: 1_000_000,
+            base_primes: Some(primes_core::simple_sieve(1000).unwrap()),
+            stream: false,
+        };
+        let mut channel = Buffered::new(Loopback::default(), 7);
+        for _ in 0..3 {
+            write_message(&mut channel, &work).unwrap();
+            assert_eq!(read_message(&mut channel).unwrap(), work);
+        }
+        assert!(channel.writer.get_ref().reads > 3);
+        assert!(channel.writer.get_ref().queue.is_empty());
+    }
+
+    #[test]
+    fn test_one_read_and_write_per_small_frame() {
+        let mut channel = Buffered::new(Loopback::default(), 4096);
+        let result = Message::Result {
+            low: 2,
+            high: 1000,
+            count: 168,
+        };
+        write_message(&mut channel, &result).unwrap();
+        assert_eq!(read_message(&mut channel).unwrap(), result);
+
+        let inner = channel.writer.get_ref();
+        assert_eq!((inner.writes, inner.reads), (1, 1));
+    }
+}
@@ -0,0 +1,773 @@
+//! Distributed Prime Number Calculator using MPI
+//!
+//! This application calculates prime numbers across multiple nodes
+//! using the Message Passing Interface (MPI) for communication.
+//!
+//! # Architecture
+//!
+//! ```text
+//! ┌─────────────────────────────────────────────────────────────────┐
+//! │                        MPI Cluster                              │
+//! ├─────────────────────────────────────────────────────────────────┤
+//! │                                                                 │
+//! │   ┌─────────────┐     ┌─────────────┐     ┌─────────────┐      │
+//! │   │   Rank 0    │     │   Rank 1    │     │   Rank 2    │      │
+//! │   │  (Master)   │     │  (Worker)   │     │  (Worker)   │      │
+//! │   │             │     │             │     │             │      │
+//! │   │ [2, 3.3M]   │     │ [3.3M, 6.6M]│     │ [6.6M, 10M] │      │
+//! │   └──────┬──────┘     └──────┬──────┘     └──────┬──────┘      │
+//! │          │                   │                   │              │
+//! │          └───────────────────┼───────────────────┘              │
+//! │                              │                                  │
+//! │                        MPI_Gather                               │
+//! │                              │                                  │
+//! │                              ▼                                  │
+//! │                     ┌─────────────┐                             │
+//! │                     │   Results   │                             │
+//! │                     └─────────────┘                             │
+//! └─────────────────────────────────────────────────────────────────┘
+//! ```
+//!
+//! # Usage
+//!
+//! ```bash
+//! # With MPI (requires mpirun)
+//! mpirun -np 4 ./primes-mpi --limit 10000000
+//!
+//! # Without MPI (single process fallback)
+//! ./primes-mpi --limit 10000000
+//!
+//! # Show how the range would be split, without sieving
+//! mpirun -np 4 ./primes-mpi --limit 10000000 --dry-run
+//! ```
+//!
+//! # Library
+//!
+//! The binary only parses flags, picks a mode and prints what comes back.
+//! The runs themselves live here, so tests and other tools can start one
+//! from an [`Args`] and read the [`DistributedResult`]:
+//!
+//! ```no_run
+//! use clap::Parser;
+//! use primes_mpi::{run_single_node, Args};
+//!
+//! let args = Args::parse_from(["primes-mpi", "--limit", "1000"]);
+//! let result = run_single_node(&args)?;
+//! assert_eq!(result.total_primes, 168);
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! [`tcp_impl::run_master`] and [`tcp_impl::run_worker`] do the same over
+//! TCP, and `mpi_impl::run_mpi` under `mpirun` with the `mpi` feature.
+
+use clap::Parser;
+use primes_core::{count_primes, count_segment, simple_sieve};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Process exit codes, listed in `--help`
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// A worker failed or disconnected, or the run could not complete
+    pub const WORKER_FAILURE: i32 = 3;
+    /// Bad command line or flag combination
+    pub const INVALID_ARGS: i32 = 4;
+    /// `--verify` found a total that disagrees with the sequential sieve
+    pub const VERIFY_FAILED: i32 = 5;
+
+    pub const HELP: &str = "Exit codes:\n  \
+        0  success\n  \
+        3  worker failure: a worker was lost (even if its chunks were reassigned) or the run failed\n  \
+        4  invalid arguments\n  \
+        5  --verify failed: the distributed total is wrong";
+}
+
+/// Distributed prime calculator using MPI or TCP fallback
+#[derive(Parser, Debug, Clone)]
+#[command(name = "primes-mpi")]
+#[command(about = "Calculate primes across distributed nodes", long_about = None)]
+#[command(after_help = exit_code::HELP)]
+pub struct Args {
+    /// Upper limit for prime calculation, e.g. 10000000, 10M or 1e7 (TCP workers use the master's)
+    #[arg(short, long, default_value_t = 10_000_000, value_parser = primes_core::parse_limit)]
+    pub limit: u64,
+
+    /// Output in CSV format
+    #[arg(long, default_value_t = false)]
+    pub csv: bool,
+
+    /// Output in JSON format
+    #[arg(long, default_value_t = false, conflicts_with = "csv")]
+    pub json: bool,
+
+    /// Leave out the CSV header line, e.g. when appending to an existing file
+    #[arg(long, default_value_t = false)]
+    pub no_header: bool,
+
+    /// Skip the banners and info logs; without --csv/--json print one `primes=N time=Xms` line
+    #[arg(short, long, default_value_t = false, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Verbose output (per-node ranges, and debug-level logs unless --log-level is set)
+    #[arg(short, long, default_value_t = false)]
+    pub verbose: bool,
+
+    /// Log filter, e.g. `debug` or `primes_mpi=trace` (default: RUST_LOG, else info)
+    #[arg(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// Use TCP fallback instead of MPI
+    #[arg(long, default_value_t = false)]
+    pub tcp: bool,
+
+    /// TCP master address (for TCP mode): IPv4, bracketed IPv6 like [::1]:7878, or host:port
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    pub master_addr: String,
+
+    /// Number of workers (for TCP master mode)
+    #[arg(long, default_value_t = 2)]
+    pub workers: usize,
+
+    /// Numbers per work unit handed to TCP workers (smaller = finer load balancing)
+    #[arg(long, default_value_t = 1_000_000)]
+    pub chunk_size: u64,
+
+    /// Seconds the TCP master waits on a worker before reassigning its chunk
+    #[arg(long, default_value_t = 30)]
+    pub worker_timeout: u64,
+
+    /// Seconds the TCP master waits for all --workers to connect (default: forever)
+    #[arg(long, value_name = "SECS", alias = "tcp-listen-timeout", requires = "tcp")]
+    pub connect_timeout: Option<u64>,
+
+    /// Run as TCP worker
+    #[arg(long, default_value_t = false)]
+    pub worker: bool,
+
+    /// Keep the TCP worker running, reconnecting for each new master session
+    #[arg(long, default_value_t = false, requires = "worker")]
+    pub serve: bool,
+
+    /// Run the TCP master once per limit (comma-separated), reusing the worker connections
+    #[arg(long, value_name = "LIMITS", value_delimiter = ',', requires = "tcp", value_parser = primes_core::parse_limit)]
+    pub sweep: Vec<u64>,
+
+    /// Bytes of read and write buffering on each TCP master/worker connection
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub tcp_buffer_size: usize,
+
+    /// Ship the base primes with every TCP chunk instead of letting workers sieve them
+    #[arg(long, default_value_t = false)]
+    pub send_base_primes: bool,
+
+    /// Have TCP workers send their primes back as they sieve them, with a live running total
+    #[arg(long, default_value_t = false, requires = "tcp")]
+    pub stream: bool,
+
+    /// Serve Prometheus metrics for the TCP master at this address (e.g. 0.0.0.0:9100)
+    #[arg(long, value_name = "ADDR", requires = "tcp")]
+    pub metrics_addr: Option<String>,
+
+    /// Encrypt the TCP master/worker channel with TLS
+    #[arg(long, default_value_t = false)]
+    pub tls: bool,
+
+    /// PEM certificate chain the TLS master presents
+    #[arg(long, value_name = "PATH", requires = "tls")]
+    pub cert: Option<PathBuf>,
+
+    /// PEM private key for --cert
+    #[arg(long, value_name = "PATH", requires = "tls")]
+    pub key: Option<PathBuf>,
+
+    /// PEM certificates a TLS worker trusts for the master
+    #[arg(long, value_name = "PATH", requires = "tls")]
+    pub ca: Option<PathBuf>,
+
+    /// Shared secret a TCP worker must present before the master hands it work
+    #[arg(long, value_name = "STRING")]
+    pub token: Option<String>,
+
+    /// Cores this node reports for sizing its share of the range (default: all available)
+    #[arg(long)]
+    pub cores: Option<usize>,
+
+    /// MPI chunks per rank, dealt round-robin so every rank sees low and high numbers
+    #[arg(long, default_value_t = 1)]
+    pub chunks_per_rank: usize,
+
+    /// Collect every rank's primes at rank 0, not just the counts
+    #[arg(long, default_value_t = false, conflicts_with_all = ["tcp", "worker"])]
+    pub gather_primes: bool,
+
+    /// Recount π(limit) with the sequential sieve afterwards and print PASS/FAIL (exit 5 on FAIL)
+    #[arg(long, default_value_t = false, conflicts_with = "worker")]
+    pub verify: bool,
+
+    /// Sieve each node's share N times and report the mean time; the primes are counted from the first pass
+    #[arg(long, default_value_t = 1, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["tcp", "worker"])]
+    pub iterations: u32,
+
+    /// Print each node's segments, the base-prime count and memory estimates, then exit without sieving
+    #[arg(long, default_value_t = false, conflicts_with_all = ["worker", "csv", "json"])]
+    pub dry_run: bool,
+}
+
+/// Warn when gathering would need more than this many bytes at rank 0
+const GATHER_WARN_BYTES: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Cores this node reports to the others, from `--cores` or the machine
+pub fn node_cores(args: &Args) -> usize {
+    args.cores
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1)
+}
+
+/// Split [low, high] into one contiguous segment per node, sized by cores
+///
+/// Node `i` gets about `cores[i] / sum(cores)` of the range; a node
+/// reporting 0 cores is counted as 1. `low > high` in a segment means the
+/// node has nothing to do (more nodes than numbers).
+fn proportional_split(low: u64, high: u64, cores: &[usize]) -> Vec<(u64, u64)> {
+    let weights: Vec<u128> = cores.iter().map(|&c| c.max(1) as u128).collect();
+    let total_weight: u128 = weights.iter().sum();
+    let range_size = if low <= high { (high - low) as u128 + 1 } else { 0 };
+
+    // Segment i ends where the running share of the cores says it should
+    let mut segments = Vec::with_capacity(cores.len());
+    let mut cumulative = 0;
+    let mut segment_low = low;
+    for weight in weights {
+        cumulative += weight;
+        let end = low as u128 + range_size * cumulative / total_weight;
+        segments.push((segment_low, (end as u64).wrapping_sub(1)));
+        segment_low = end as u64;
+    }
+    segments
+}
+
+/// Chunks of [low, high] for each node when every node takes several
+///
+/// The range is cut into `chunks_per_node` consecutive rounds and each
+/// round is split across the nodes with [`proportional_split`], so node
+/// `i` gets the `i`-th piece of every round. Interleaving like this keeps
+/// any one node from being stuck with only the sparse top of the range.
+/// Empty pieces are left out.
+fn interleaved_chunks(low: u64, high: u64, cores: &[usize], chunks_per_node: usize) -> Vec<Vec<(u64, u64)>> {
+    let mut chunks = vec![Vec::new(); cores.len()];
+    for (round_low, round_high) in proportional_split(low, high, &vec![1; chunks_per_node.max(1)]) {
+        for (node, (chunk_low, chunk_high)) in proportional_split(round_low, round_high, cores).into_iter().enumerate() {
+            if chunk_low <= chunk_high {
+                chunks[node].push((chunk_low, chunk_high));
+            }
+        }
+    }
+    chunks
+}
+
+/// Work split for a distributed run: base primes up to √limit on every
+/// node, and each node's chunks of (√limit, limit]
+///
+/// The MPI run and `--dry-run` both build their split here.
+pub struct WorkPlan {
+    pub sqrt_limit: u64,
+    pub base_primes: Vec<u64>,
+    pub node_chunks: Vec<Vec<(u64, u64)>>,
+}
+
+impl WorkPlan {
+    pub fn new(limit: u64, cores: &[usize], chunks_per_node: usize) -> Result<Self, String> {
+        let sqrt_limit = (limit as f64).sqrt() as u64;
+        Ok(WorkPlan {
+            sqrt_limit,
+            base_primes: simple_sieve(sqrt_limit).map_err(|e| e.to_string())?,
+            node_chunks: interleaved_chunks(sqrt_limit + 1, limit, cores, chunks_per_node),
+        })
+    }
+}
+
+/// π(n) ≈ n / ln(n), or 0 below 2
+fn approx_prime_count(n: u64) -> f64 {
+    if n > 1 {
+        n as f64 / (n as f64).ln()
+    } else {
+        0.0
+    }
+}
+
+/// Rough peak bytes to sieve [low, high]: one flag byte per number plus
+/// 8 bytes per prime collected
+pub fn segment_memory_estimate(low: u64, high: u64) -> f64 {
+    let primes = (approx_prime_count(high) - approx_prime_count(low.saturating_sub(1))).max(0.0);
+    (high - low + 1) as f64 + primes * 8.0
+}
+
+/// Print a warning to stderr if gathering π(limit) primes at one rank is large
+fn warn_if_gather_is_large(limit: u64) {
+    // 8 bytes per prime
+    let expected_bytes = approx_prime_count(limit) * 8.0;
+    if expected_bytes > GATHER_WARN_BYTES {
+        eprintln!(
+            "Warning: --gather-primes will collect about {:.1} GiB of primes at rank 0",
+            expected_bytes / GATHER_WARN_BYTES
+        );
+    }
+}
+
+/// Run `pass` `iterations` times (at least once), keeping the first output
+///
+/// Returns that output and how long each pass took, in milliseconds.
+/// Later passes only repeat the work for timing; their output is dropped.
+fn timed_passes<T>(iterations: u32, mut pass: impl FnMut() -> Result<T, String>) -> Result<(T, Vec<f64>), String> {
+    let mut first = None;
+    let mut times_ms = Vec::new();
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        let output = pass()?;
+        times_ms.push(start.elapsed().as_secs_f64() * 1000.0);
+        first.get_or_insert(output);
+    }
+    Ok((first.expect("at least one pass ran"), times_ms))
+}
+
+/// Mean of `times_ms`, or 0 if there are none
+fn mean_ms(times_ms: &[f64]) -> f64 {
+    if times_ms.is_empty() {
+        0.0
+    } else {
+        times_ms.iter().sum::<f64>() / times_ms.len() as f64
+    }
+}
+
+/// Receive displacements for a variable-count gather: the prefix sums of `counts`
+#[cfg_attr(not(feature = "mpi"), allow(dead_code))]
+fn gather_displacements(counts: &[usize]) -> Vec<usize> {
+    counts
+        .iter()
+        .scan(0, |offset, &count| {
+            let displacement = *offset;
+            *offset += count;
+            Some(displacement)
+        })
+        .collect()
+}
+
+/// Merge the base primes with the per-rank primes gathered into one buffer
+///
+/// `gathered` holds each rank's primes back to back in rank order. With
+/// one chunk per rank that is already sorted; with `--chunks-per-rank`
+/// the ranks' chunks interleave, so the result is sorted here.
+#[cfg_attr(not(feature = "mpi"), allow(dead_code))]
+fn merge_gathered(mut base_primes: Vec<u64>, gathered: &[u64]) -> Vec<u64> {
+    base_primes.extend_from_slice(gathered);
+    base_primes.sort_unstable();
+    base_primes
+}
+
+/// MPI-based distributed calculation
+#[cfg(feature = "mpi")]
+pub mod mpi_impl {
+    use super::*;
+    use mpi::collective::CommunicatorCollectives;
+    use mpi::datatype::PartitionMut;
+    use mpi::traits::*;
+    use mpi::Count;
+    use primes_core::sieve_segment;
+    use tracing::{debug, info_span};
+
+    pub fn run_mpi(args: &Args) -> Result<DistributedResult, String> {
+        let universe = mpi::initialize().ok_or("Failed to initialize MPI")?;
+        let world = universe.world();
+        let rank = world.rank();
+        let size = world.size();
+        let _span = info_span!("run_mpi", rank, limit = args.limit).entered();
+
+        let start_time = Instant::now();
+
+        // Divide work among ranks in proportion to their cores; every rank
+        // derives the same assignment (and base primes), so nothing needs
+        // to be scattered
+        let mut all_cores = vec![0usize; size as usize];
+        world.all_gather_into(&node_cores(args), &mut all_cores);
+        let base_started = Instant::now();
+        let WorkPlan {
+            base_primes,
+            node_chunks: ranges,
+            ..
+        } = WorkPlan::new(args.limit, &all_cores, args.chunks_per_rank)?;
+        let base_ms = base_started.elapsed().as_secs_f64() * 1000.0;
+        let my_chunks = &ranges[rank as usize];
+
+        if rank == 0 {
+            debug!(
+                ranks = size,
+                limit = args.limit,
+                base_primes = base_primes.len(),
+                chunks_per_rank = args.chunks_per_rank.max(1),
+                cores = ?all_cores,
+                "MPI configuration"
+            );
+        }
+
+        // Each rank sieves its chunks, --iterations times
+        let (local_primes, pass_times_ms) = timed_passes(args.iterations, || {
+            let mut local_primes = Vec::new();
+            for &(low, high) in my_chunks {
+                let primes = sieve_segment(low, high, &base_primes).map_err(|e| e.to_string())?;
+                debug!(rank, low, high, count = primes.len(), "Chunk sieved");
+                local_primes.extend(primes);
+            }
+            Ok(local_primes)
+        })?;
+
+        let local_count = local_primes.len();
+
+        // The run waited for the slowest rank's passes; its mean pass time
+        // is what one pass adds to the wall clock
+        let mut all_pass_means = vec![0f64; size as usize];
+        world.all_gather_into(&mean_ms(&pass_times_ms), &mut all_pass_means);
+        let slowest_pass_ms = all_pass_means.iter().copied().fold(0.0, f64::max);
+
+        // Gather counts at root using all_gather_into (gathers to all ranks)
+        let mut all_counts = vec![0usize; size as usize];
+        world.all_gather_into(&local_count, &mut all_counts);
+
+        // Optionally gather the primes themselves; every rank knows all
+        // counts, so every rank can tell if they fit MPI's i32 counts
+        let gathered = if args.gather_primes {
+            if all_counts.iter().any(|&count| count > Count::MAX as usize) {
+                return Err("--gather-primes: a rank found more primes than MPI can send at once".to_string());
+            }
+
+            let root = world.process_at_rank(0);
+            if rank == 0 {
+                warn_if_gather_is_large(args.limit);
+
+                let counts: Vec<Count> = all_counts.iter().map(|&c| c as Count).collect();
+                let displs: Vec<Count> = gather_displacements(&all_counts)
+                    .into_iter()
+                    .map(|d| d as Count)
+                    .collect();
+                let mut buffer = vec![0u64; all_counts.iter().sum()];
+                {
+                    let mut partition = PartitionMut::new(&mut buffer[..], counts, &displs[..]);
+                    root.gather_varcount_into_root(&local_primes[..], &mut partition);
+                }
+                Some(buffer)
+            } else {
+                root.gather_varcount_into(&local_primes[..]);
+                None
+            }
+        } else {
+            None
+        };
+
+        // Calculate total, counting the sieving once
+        let elapsed_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+        let time_ms = (elapsed_ms - slowest_pass_ms * (args.iterations.max(1) - 1) as f64).max(0.0);
+
+        if rank == 0 {
+            let total_from_segments: usize = all_counts.iter().sum();
+            let total_primes = base_primes.len() + total_from_segments;
+
+            let base_prime_count = base_primes.len();
+            Ok(DistributedResult {
+                total_primes,
+                nodes: size as usize,
+                time_ms,
+                base_ms,
+                sieve_ms: slowest_pass_ms,
+                pass_times_ms,
+                node_counts: all_counts,
+                chunk_counts: ranges.iter().map(Vec::len).collect(),
+                node_cores: all_cores,
+                node_ranges: ranges,
+                failed_workers: 0,
+                base_prime_count,
+                primes: gathered.map(|gathered| merge_gathered(base_primes, &gathered)),
+            })
+        } else {
+            // Workers return empty result
+            Ok(DistributedResult::default())
+        }
+    }
+
+    /// Cores of every rank, known at rank 0 only (`None` elsewhere)
+    pub fn rank_cores(args: &Args) -> Result<Option<Vec<usize>>, String> {
+        let universe = mpi::initialize().ok_or("Failed to initialize MPI")?;
+        let world = universe.world();
+        let mut all_cores = vec![0usize; world.size() as usize];
+        world.all_gather_into(&node_cores(args), &mut all_cores);
+        Ok((world.rank() == 0).then_some(all_cores))
+    }
+}
+
+/// Nodes a `--dry-run` plans for: the MPI ranks, or the TCP master and
+/// `--workers` (assumed to have this node's cores), or just this node
+///
+/// `None` on MPI ranks other than 0, which print nothing.
+pub fn dry_run_cores(args: &Args) -> Option<Vec<usize>> {
+    #[cfg(feature = "mpi")]
+    if !args.tcp {
+        match mpi_impl::rank_cores(args) {
+            Ok(cores) => return cores,
+            Err(e) => tracing::warn!(error = %e, "MPI unavailable, planning for a single node"),
+        }
+    }
+
+    let nodes = if args.tcp { args.workers + 1 } else { 1 };
+    Some(vec![node_cores(args); nodes])
+}
+
+mod buffered;
+mod metrics;
+pub mod protocol;
+pub mod tcp_impl;
+mod tls;
+
+/// Result from distributed calculation
+#[derive(Debug, Default)]
+pub struct DistributedResult {
+    pub total_primes: usize,
+    pub nodes: usize,
+    pub time_ms: f64,
+    /// Part of `time_ms` spent finding the base primes up to √limit (0 on a
+    /// single node, whose plain sieve finds them in the same pass)
+    pub base_ms: f64,
+    /// Part of `time_ms` spent sieving past √limit: the slowest rank's mean
+    /// pass (MPI), or handing out and sieving the chunks (TCP)
+    pub sieve_ms: f64,
+    /// Each `--iterations` pass over the master's share; `time_ms` counts
+    /// the sieving once, at the mean (empty in TCP mode)
+    pub pass_times_ms: Vec<f64>,
+    pub node_counts: Vec<usize>,
+    /// Work units processed by each node (one per node unless chunked over TCP)
+    pub chunk_counts: Vec<usize>,
+    /// Cores reported by each node
+    pub node_cores: Vec<usize>,
+    /// Segments [low, high] sieved by each node, in the order they were done
+    pub node_ranges: Vec<Vec<(u64, u64)>>,
+    /// TCP workers that timed out or disconnected; their chunks were redone
+    pub failed_workers: usize,
+    pub base_prime_count: usize,
+    /// All primes up to the limit, only with --gather-primes (MPI) or --stream (TCP)
+    pub primes: Option<Vec<u64>>,
+}
+
+/// Single-node fallback
+pub fn run_single_node(args: &Args) -> Result<DistributedResult, String> {
+    if args.gather_primes {
+        warn_if_gather_is_large(args.limit);
+    }
+
+    let (primes, pass_times_ms) =
+        timed_passes(args.iterations, || simple_sieve(args.limit).map_err(|e| e.to_string()))?;
+    let count = primes.len();
+    let time_ms = mean_ms(&pass_times_ms);
+
+    Ok(DistributedResult {
+        total_primes: count,
+        nodes: 1,
+        time_ms,
+        base_ms: 0.0,
+        sieve_ms: time_ms,
+        pass_times_ms,
+        node_counts: vec![count],
+        chunk_counts: vec![1],
+        node_cores: vec![node_cores(args)],
+        node_ranges: vec![if args.limit >= 2 { vec![(2, args.limit)] } else { vec![] }],
+        failed_workers: 0,
+        base_prime_count: 0,
+        primes: args.gather_primes.then_some(primes),
+    })
+}
+
+/// `--verify` outcome: the distributed figures against a sequential π(limit)
+#[derive(Debug)]
+pub struct Verification {
+    /// Total the run reported
+    pub total: usize,
+    /// Base primes plus every node's count
+    pub sum_of_nodes: usize,
+    /// π(limit) from the sequential sieve
+    pub expected: usize,
+}
+
+impl Verification {
+    pub fn passed(&self) -> bool {
+        self.total == self.expected && self.sum_of_nodes == self.expected
+    }
+}
+
+/// Recount π(limit) sequentially and compare it with `result`
+pub fn verify_result(result: &DistributedResult, limit: u64) -> Result<Verification, String> {
+    Ok(Verification {
+        total: result.total_primes,
+        sum_of_nodes: result.base_prime_count + result.node_counts.iter().sum::<usize>(),
+        expected: count_primes(limit).map_err(|e| e.to_string())?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primes_core::sieve_segment;
+
+    #[test]
+    fn test_single_node() {
+        let args = Args {
+            limit: 1000,
+            csv: false,
+            json: false,
+            no_header: false,
+            quiet: false,
+            verbose: false,
+            log_level: None,
+            tcp: false,
+            master_addr: "127.0.0.1:7878".to_string(),
+            workers: 2,
+            chunk_size: 1_000_000,
+            worker_timeout: 30,
+            connect_timeout: None,
+            worker: false,
+            serve: false,
+            sweep: vec![],
+            chunks_per_rank: 1,
+            tcp_buffer_size: 64 * 1024,
+            send_base_primes: false,
+            stream: false,
+            cores: None,
+            token: None,
+            metrics_addr: None,
+            tls: false,
+            cert: None,
+            key: None,
+            ca: None,
+            gather_primes: false,
+            verify: false,
+            iterations: 1,
+            dry_run: false,
+        };
+
+        let result = run_single_node(&args).unwrap();
+        assert_eq!(result.total_primes, 168); // π(1000) = 168
+    }
+
+    #[test]
+    fn test_iterations_average_the_time_not_the_count() {
+        let once = run_single_node(&Args::parse_from(["primes-mpi", "--limit", "100000"])).unwrap();
+        let args = Args::parse_from(["primes-mpi", "--limit", "100000", "--iterations", "4", "--gather-primes"]);
+        let repeated = run_single_node(&args).unwrap();
+
+        assert_eq!(repeated.total_primes, 9592);
+        assert_eq!(repeated.total_primes, once.total_primes);
+        assert_eq!(repeated.node_counts, vec![9592]);
+        assert_eq!(repeated.primes.map(|primes| primes.len()), Some(9592));
+        assert_eq!(once.pass_times_ms.len(), 1);
+        assert_eq!(repeated.pass_times_ms.len(), 4);
+        let mean = repeated.pass_times_ms.iter().sum::<f64>() / 4.0;
+        assert!((repeated.time_ms - mean).abs() < 1e-9);
+
+        assert!(Args::try_parse_from(["primes-mpi", "--iterations", "0"]).is_err());
+        assert!(Args::try_parse_from(["primes-mpi", "--tcp", "--iterations", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_verify_detects_corrupted_node_count() {
+        // What a two-node run up to 10 000 reports: 25 base primes below 100
+        let mut result = DistributedResult {
+            total_primes: 1229,
+            nodes: 2,
+            node_counts: vec![600, 604],
+            base_prime_count: 25,
+            ..DistributedResult::default()
+        };
+        assert!(verify_result(&result, 10_000).unwrap().passed());
+
+        result.node_counts[1] -= 1;
+        let check = verify_result(&result, 10_000).unwrap();
+        assert!(!check.passed());
+        assert_eq!((check.sum_of_nodes, check.expected), (1228, 1229));
+
+        result.node_counts[1] += 1;
+        result.total_primes += 1;
+        assert!(!verify_result(&result, 10_000).unwrap().passed());
+    }
+
+    #[test]
+    fn test_gathered_primes_match_sequential() {
+        // Simulate a 4-rank run in one process: sieve each rank's chunks,
+        // lay the results out as gather_varcount would, then merge
+        let limit = 10_000;
+        let size = 4;
+        let sqrt_limit = (limit as f64).sqrt() as u64;
+        let base_primes = simple_sieve(sqrt_limit).unwrap();
+
+        let per_rank: Vec<Vec<u64>> = interleaved_chunks(sqrt_limit + 1, limit, &vec![1; size], 3)
+            .into_iter()
+            .map(|chunks| {
+                chunks
+                    .into_iter()
+                    .flat_map(|(low, high)| sieve_segment(low, high, &base_primes).unwrap())
+                    .collect()
+            })
+            .collect();
+        let counts: Vec<usize> = per_rank.iter().map(Vec::len).collect();
+        let displs = gather_displacements(&counts);
+
+        let mut buffer = vec![0u64; counts.iter().sum()];
+        for (rank, primes) in per_rank.iter().enumerate() {
+            buffer[displs[rank]..displs[rank] + counts[rank]].copy_from_slice(primes);
+        }
+
+        assert_eq!(merge_gathered(base_primes, &buffer), simple_sieve(limit).unwrap());
+    }
+
+    #[test]
+    fn test_proportional_split() {
+        let segments = proportional_split(101, 1100, &[1, 2, 4, 1]);
+        assert_eq!(segments, vec![(101, 225), (226, 475), (476, 975), (976, 1100)]);
+
+        // Uneven shares still tile the range with no gaps or overlap
+        let cores = [3, 1, 0, 7, 2];
+        let segments = proportional_split(1_001, 1_000_000, &cores);
+        assert_eq!(segments.first().unwrap().0, 1_001);
+        assert_eq!(segments.last().unwrap().1, 1_000_000);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].1 + 1, pair[1].0);
+        }
+        let range_size = 1_000_000.0 - 1_000.0;
+        let total_cores = 14.0; // 0 cores counts as 1
+        for (&(low, high), &c) in segments.iter().zip(&cores) {
+            let expected = range_size * c.max(1) as f64 / total_cores;
+            assert!(((high - low + 1) as f64 - expected).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_chunks_cover_range_once() {
+        let (low, high) = (1001, 1_000_000);
+        let chunks = interleaved_chunks(low, high, &[1, 2, 1], 4);
+
+        // Every rank gets one chunk per round
+        assert!(chunks.iter().all(|rank_chunks| rank_chunks.len() == 4));
+        // Rank 0 gets the low end of every round, not one block
+        assert_eq!(chunks[0][0].0, low);
+        assert!(chunks[0][1].0 > chunks[2][0].1);
+
+        let mut all: Vec<(u64, u64)> = chunks.into_iter().flatten().collect();
+        all.sort();
+        assert_eq!(all.first().unwrap().0, low);
+        assert_eq!(all.last().unwrap().1, high);
+        assert!(all.windows(2).all(|pair| pair[0].1 + 1 == pair[1].0));
+    }
+
+    #[test]
+    fn test_proportional_split_more_nodes_than_numbers() {
+        let segments = proportional_split(10, 11, &[1, 1, 1, 1]);
+        let sizes: Vec<u64> = segments.iter().map(|&(low, high)| (high + 1).saturating_sub(low)).collect();
+        assert_eq!(sizes.iter().sum::<u64>(), 2);
+        assert!(segments.iter().all(|&(low, _)| (10..=12).contains(&low)));
+    }
+}
@@ -1,468 +1,158 @@
-//! Distributed Prime Number Calculator using MPI
+//! Command line for the distributed prime calculator
 //!
-//! This application calculates prime numbers across multiple nodes
-//! using the Message Passing Interface (MPI) for communication.
-//!
-//! # Architecture
-//!
-//! ```text
-//! ┌─────────────────────────────────────────────────────────────────┐
-//! │                        MPI Cluster                              │
-//! ├─────────────────────────────────────────────────────────────────┤
-//! │                                                                 │
-//! │   ┌─────────────┐     ┌─────────────┐     ┌─────────────┐      │
-//! │   │   Rank 0    │     │   Rank 1    │     │   Rank 2    │      │
-//! │   │  (Master)   │     │  (Worker)   │     │  (Worker)   │      │
-//! │   │             │     │             │     │             │      │
-//! │   │ [2, 3.3M]   │     │ [3.3M, 6.6M]│     │ [6.6M, 10M] │      │
-//! │   └──────┬──────┘     └──────┬──────┘     └──────┬──────┘      │
-//! │          │                   │                   │              │
-//! │          └───────────────────┼───────────────────┘              │
-//! │                              │                                  │
-//! │                        MPI_Gather                               │
-//! │                              │                                  │
-//! │                              ▼                                  │
-//! │                     ┌─────────────┐                             │
-//! │                     │   Results   │                             │
-//! │                     └─────────────┘                             │
-//! └─────────────────────────────────────────────────────────────────┘
-//! ```
-//!
-//! # Usage
-//!
-//! ```bash
-//! # With MPI (requires mpirun)
-//! mpirun -np 4 ./primes-mpi --limit 10000000
-//!
-//! # Without MPI (single process fallback)
-//! ./primes-mpi --limit 10000000
-//! ```
+//! Parses the flags into an [`Args`], runs the chosen mode from the
+//! `primes_mpi` library (TCP worker, TCP master, MPI, or the single-node
+//! fallback) and prints the result as text, CSV or JSON.
 
 use clap::Parser;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::time::Instant;
-
-/// Distributed prime calculator using MPI or TCP fallback
-#[derive(Parser, Debug, Clone)]
-#[command(name = "primes-mpi")]
-#[command(about = "Calculate primes across distributed nodes", long_about = None)]
-struct Args {
-    /// Upper limit for prime calculation
-    #[arg(short, long, default_value_t = 10_000_000)]
-    limit: u64,
-
-    /// Output in CSV format
-    #[arg(long, default_value_t = false)]
-    csv: bool,
-
-    /// Verbose output
-    #[arg(short, long, default_value_t = false)]
-    verbose: bool,
-
-    /// Use TCP fallback instead of MPI
-    #[arg(long, default_value_t = false)]
-    tcp: bool,
-
-    /// TCP master address (for TCP mode)
-    #[arg(long, default_value = "127.0.0.1:7878")]
-    master_addr: String,
-
-    /// Number of workers (for TCP master mode)
-    #[arg(long, default_value_t = 2)]
-    workers: usize,
-
-    /// Run as TCP worker
-    #[arg(long, default_value_t = false)]
-    worker: bool,
-}
-
-/// Simple sieve to find base primes
-fn simple_sieve(limit: u64) -> Vec<u64> {
-    if limit < 2 {
-        return vec![];
-    }
-
-    let mut is_prime = vec![true; (limit + 1) as usize];
-    is_prime[0] = false;
-    is_prime[1] = false;
-
-    let sqrt_limit = (limit as f64).sqrt() as u64;
-
-    for num in 2..=sqrt_limit {
-        if is_prime[num as usize] {
-            let mut multiple = num * num;
-            while multiple <= limit {
-                is_prime[multiple as usize] = false;
-                multiple += num;
-            }
-        }
-    }
-
-    is_prime
-        .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| idx as u64)
-        .collect()
-}
-
-/// Sieve a segment using base primes
-fn sieve_segment(low: u64, high: u64, base_primes: &[u64]) -> Vec<u64> {
-    if low > high {
-        return vec![];
-    }
-
-    let segment_size = (high - low + 1) as usize;
-    let mut is_prime = vec![true; segment_size];
-
-    // Handle 0 and 1
-    if low == 0 && segment_size > 0 {
-        is_prime[0] = false;
-    }
-    if low <= 1 && high >= 1 {
-        is_prime[(1 - low) as usize] = false;
-    }
-
-    for &prime in base_primes {
-        if prime * prime > high {
-            continue;
-        }
-
-        let start = if low <= prime * prime {
-            prime * prime
-        } else {
-            let remainder = low % prime;
-            if remainder == 0 {
-                low
-            } else {
-                low + (prime - remainder)
-            }
-        };
-
-        let mut multiple = start;
-        while multiple <= high {
-            let local_idx = (multiple - low) as usize;
-            is_prime[local_idx] = false;
-            multiple += prime;
-        }
-    }
-
-    is_prime
-        .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| low + idx as u64)
-        .filter(|&n| n > 1)
-        .collect()
-}
+use primes_core::MAX_SIEVE_LIMIT;
+use primes_mpi::{
+    dry_run_cores, exit_code, run_single_node, segment_memory_estimate, tcp_impl, verify_result, Args,
+    DistributedResult, WorkPlan,
+};
+use serde::Serialize;
+use tracing_subscriber::EnvFilter;
 
-/// MPI-based distributed calculation
 #[cfg(feature = "mpi")]
-mod mpi_impl {
-    use super::*;
-    use mpi::collective::CommunicatorCollectives;
-    use mpi::traits::*;
-
-    pub fn run_mpi(args: &Args) -> Result<DistributedResult, String> {
-        let universe = mpi::initialize().ok_or("Failed to initialize MPI")?;
-        let world = universe.world();
-        let rank = world.rank();
-        let size = world.size();
-
-        let start_time = Instant::now();
-
-        // Calculate base primes (all ranks need these)
-        let sqrt_limit = (args.limit as f64).sqrt() as u64;
-        let base_primes = simple_sieve(sqrt_limit);
-
-        // Divide work among ranks
-        let range_start = sqrt_limit + 1;
-        let range_size = args.limit - sqrt_limit;
-        let segment_size = (range_size + size as u64 - 1) / size as u64;
-
-        let my_low = range_start + (rank as u64 * segment_size);
-        let my_high = std::cmp::min(my_low + segment_size - 1, args.limit);
-
-        if args.verbose && rank == 0 {
-            println!("MPI Configuration:");
-            println!("  Total ranks: {}", size);
-            println!("  Limit: {}", args.limit);
-            println!("  Base primes: {}", base_primes.len());
-        }
-
-        // Each rank sieves its segment
-        let local_primes = if my_low <= args.limit {
-            sieve_segment(my_low, my_high, &base_primes)
-        } else {
-            vec![]
-        };
-
-        let local_count = local_primes.len();
+use primes_mpi::mpi_impl;
+
+/// Warn that `--verify` will take a while above this limit
+const VERIFY_WARN_LIMIT: u64 = 1 << 32;
+
+/// Print the `--dry-run` report for `plan` over nodes with `cores`
+fn print_plan(plan: &WorkPlan, cores: &[usize], args: &Args) {
+    const MIB: f64 = 1024.0 * 1024.0;
+
+    println!("═══════════════════════════════════════════════════════════");
+    println!("           DISTRIBUTED PRIME CALCULATION PLAN (dry run)");
+    println!("═══════════════════════════════════════════════════════════");
+    println!("Configuration:");
+    println!("  Limit: {}", args.limit);
+    println!("  Nodes: {}", cores.len());
+    println!("  Base primes: {} (up to {})", plan.base_primes.len(), plan.sqrt_limit);
+    println!("  Sieved range: [{}, {}]", plan.sqrt_limit + 1, args.limit);
+    if args.tcp {
+        println!("  TCP workers take --chunk-size chunks on demand; shown is the split by cores");
+    }
+    println!("───────────────────────────────────────────────────────────");
+    println!("Per-node segments:");
 
-        if args.verbose {
+    for (i, chunks) in plan.node_chunks.iter().enumerate() {
+        let label = if i == 0 { "Master" } else { "Worker" };
+        println!("  {} {} ({} cores):", label, i, cores[i]);
+        for &(low, high) in chunks {
             println!(
-                "  Rank {}: [{}, {}] -> {} primes",
-                rank, my_low, my_high, local_count
+                "      [{}, {}]  {} numbers, ~{:.1} MiB",
+                low,
+                high,
+                high - low + 1,
+                segment_memory_estimate(low, high) / MIB
             );
         }
-
-        // Gather counts at root using all_gather_into (gathers to all ranks)
-        let mut all_counts = vec![0usize; size as usize];
-        world.all_gather_into(&local_count, &mut all_counts);
-
-        // Calculate total
-        let elapsed = start_time.elapsed();
-
-        if rank == 0 {
-            let total_from_segments: usize = all_counts.iter().sum();
-            let total_primes = base_primes.len() + total_from_segments;
-
-            Ok(DistributedResult {
-                total_primes,
-                nodes: size as usize,
-                time_ms: elapsed.as_secs_f64() * 1000.0,
-                node_counts: all_counts,
-                base_prime_count: base_primes.len(),
-            })
-        } else {
-            // Workers return empty result
-            Ok(DistributedResult::default())
-        }
     }
-}
 
-/// TCP-based distributed calculation (fallback when MPI not available)
-mod tcp_impl {
-    use super::*;
-
-    /// Message types for TCP communication
-    #[derive(Debug)]
-    enum Message {
-        Work { low: u64, high: u64, base_primes: Vec<u64> },
-        Result { count: usize, node_id: usize },
-        Shutdown,
-    }
+    println!("═══════════════════════════════════════════════════════════");
+}
 
-    fn serialize_work(low: u64, high: u64, base_primes: &[u64]) -> Vec<u8> {
-        let mut data = Vec::new();
-        data.extend(&low.to_le_bytes());
-        data.extend(&high.to_le_bytes());
-        data.extend(&(base_primes.len() as u64).to_le_bytes());
-        for &p in base_primes {
-            data.extend(&p.to_le_bytes());
-        }
-        data
+/// With `--csv`, print the header line (once, before any rows) unless `--no-header`
+fn print_csv_header(args: &Args) {
+    if args.csv && !args.no_header {
+        println!("limit,nodes,time_ms,prime_count,base_ms,sieve_ms");
     }
+}
 
-    fn deserialize_work(data: &[u8]) -> (u64, u64, Vec<u64>) {
-        let low = u64::from_le_bytes(data[0..8].try_into().unwrap());
-        let high = u64::from_le_bytes(data[8..16].try_into().unwrap());
-        let count = u64::from_le_bytes(data[16..24].try_into().unwrap()) as usize;
-
-        let mut base_primes = Vec::with_capacity(count);
-        for i in 0..count {
-            let start = 24 + i * 8;
-            let p = u64::from_le_bytes(data[start..start + 8].try_into().unwrap());
-            base_primes.push(p);
-        }
-
-        (low, high, base_primes)
+/// Print `result` and, with `--verify`, the check; returns the exit code
+///
+/// The verdict goes to stdout with the human-readable report, and to
+/// stderr when stdout is CSV, JSON or the `--quiet` line.
+fn report(result: &DistributedResult, args: &Args) -> i32 {
+    print_results(result, args);
+    if !args.verify {
+        return result_exit_code(result);
     }
 
-    /// Run as TCP master
-    pub fn run_master(args: &Args) -> Result<DistributedResult, String> {
-        let start_time = Instant::now();
-
-        // Calculate base primes
-        let sqrt_limit = (args.limit as f64).sqrt() as u64;
-        let base_primes = simple_sieve(sqrt_limit);
-
-        if args.verbose {
-            println!("TCP Master Configuration:");
-            println!("  Workers expected: {}", args.workers);
-            println!("  Limit: {}", args.limit);
-            println!("  Base primes: {}", base_primes.len());
-        }
-
-        // Bind to address
-        let listener = TcpListener::bind(&args.master_addr)
-            .map_err(|e| format!("Failed to bind: {}", e))?;
-
-        println!("Master listening on {}", args.master_addr);
-        println!("Waiting for {} workers to connect...", args.workers);
-
-        // Accept worker connections
-        let mut workers: Vec<TcpStream> = Vec::new();
-        for i in 0..args.workers {
-            let (stream, addr) = listener
-                .accept()
-                .map_err(|e| format!("Accept failed: {}", e))?;
-            println!("  Worker {} connected from {}", i, addr);
-            workers.push(stream);
-        }
-
-        // Divide work
-        let total_nodes = args.workers + 1; // workers + master
-        let range_start = sqrt_limit + 1;
-        let range_size = args.limit - sqrt_limit;
-        let segment_size = (range_size + total_nodes as u64 - 1) / total_nodes as u64;
-
-        // Send work to workers
-        for (i, worker) in workers.iter_mut().enumerate() {
-            let worker_id = i + 1; // Master is 0
-            let low = range_start + (worker_id as u64 * segment_size);
-            let high = std::cmp::min(low + segment_size - 1, args.limit);
-
-            if args.verbose {
-                println!("  Sending work to worker {}: [{}, {}]", worker_id, low, high);
-            }
-
-            let data = serialize_work(low, high, &base_primes);
-            let len = data.len() as u32;
-
-            worker
-                .write_all(&len.to_le_bytes())
-                .map_err(|e| format!("Send failed: {}", e))?;
-            worker
-                .write_all(&data)
-                .map_err(|e| format!("Send failed: {}", e))?;
-        }
-
-        // Master does its own work
-        let master_low = range_start;
-        let master_high = std::cmp::min(master_low + segment_size - 1, args.limit);
-        let master_primes = sieve_segment(master_low, master_high, &base_primes);
-        let master_count = master_primes.len();
-
-        if args.verbose {
-            println!("  Master [{}, {}] -> {} primes", master_low, master_high, master_count);
-        }
-
-        // Collect results from workers
-        let mut node_counts = vec![master_count];
-
-        for (i, worker) in workers.iter_mut().enumerate() {
-            let mut len_buf = [0u8; 4];
-            worker
-                .read_exact(&mut len_buf)
-                .map_err(|e| format!("Read failed: {}", e))?;
-            let count = u32::from_le_bytes(len_buf) as usize;
-
-            if args.verbose {
-                println!("  Worker {} returned {} primes", i + 1, count);
-            }
-
-            node_counts.push(count);
-        }
-
-        let elapsed = start_time.elapsed();
-        let total_from_segments: usize = node_counts.iter().sum();
-        let total_primes = base_primes.len() + total_from_segments;
-
-        Ok(DistributedResult {
-            total_primes,
-            nodes: total_nodes,
-            time_ms: elapsed.as_secs_f64() * 1000.0,
-            node_counts,
-            base_prime_count: base_primes.len(),
-        })
+    if args.limit > VERIFY_WARN_LIMIT {
+        eprintln!(
+            "Warning: --verify sieves up to {} on one node; this may take a while",
+            args.limit
+        );
     }
-
-    /// Run as TCP worker
-    pub fn run_worker(args: &Args) -> Result<(), String> {
-        println!("Connecting to master at {}...", args.master_addr);
-
-        let mut stream = None;
-        let start_time = Instant::now();
-        let timeout = std::time::Duration::from_secs(10);
-
-        // RETRY LOOP: Keep trying to connect until Master is ready
-        while start_time.elapsed() < timeout {
-            match TcpStream::connect(&args.master_addr) {
-                Ok(s) => {
-                    stream = Some(s);
-                    break;
-                }
-                Err(_) => {
-                    std::thread::sleep(std::time::Duration::from_millis(200));
-                }
-            }
-        }
-
-        let mut stream = stream.ok_or_else(|| 
-            format!("Connection failed: Could not reach master at {} after 10s", args.master_addr)
-        )?;
-
-        // Add timeouts so it doesn't hang forever if the master crashes
-        stream.set_read_timeout(Some(std::time::Duration::from_secs(30))).ok();
-        stream.set_write_timeout(Some(std::time::Duration::from_secs(30))).ok();
-
-        println!("Connected to master");
-
-        // Receive work
-        let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).map_err(|e| format!("Read len failed: {}", e))?;
-        let len = u32::from_le_bytes(len_buf) as usize;
-
-        let mut data = vec![0u8; len];
-        stream.read_exact(&mut data).map_err(|e| format!("Read data failed: {}", e))?;
-
-        let (low, high, base_primes) = deserialize_work(&data);
-
-        if args.verbose {
-            println!("Received work: [{}, {}]", low, high);
-        }
-
-        // Do the work
-        let primes = sieve_segment(low, high, &base_primes);
-        let count = primes.len();
-
-        // Send result
-        stream.write_all(&(count as u32).to_le_bytes()).map_err(|e| format!("Write result failed: {}", e))?;
-    
-        // Explicitly flush to ensure data is sent before worker exits
-        stream.flush().ok();
-    
-        println!("Result sent: {} primes found", count);
-        Ok(())
+    let (line, code) = match verify_result(result, args.limit) {
+        Ok(check) if check.passed() => (
+            format!("Verify: PASS (π({}) = {})", args.limit, check.expected),
+            result_exit_code(result),
+        ),
+        Ok(check) => (
+            format!(
+                "Verify: FAIL (distributed total {}, node counts sum to {}, sequential π({}) = {})",
+                check.total, check.sum_of_nodes, args.limit, check.expected
+            ),
+            exit_code::VERIFY_FAILED,
+        ),
+        Err(e) => (format!("Verify: FAIL ({})", e), exit_code::VERIFY_FAILED),
+    };
+    if args.csv || args.json || args.quiet {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
     }
+    code
 }
 
-/// Result from distributed calculation
-#[derive(Debug, Default)]
-struct DistributedResult {
-    total_primes: usize,
+/// Result object emitted by `--json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    limit: u64,
     nodes: usize,
     time_ms: f64,
-    node_counts: Vec<usize>,
+    base_ms: f64,
+    sieve_ms: f64,
+    iterations: u32,
+    prime_count: usize,
     base_prime_count: usize,
+    density: f64,
+    node_counts: &'a [usize],
+    chunk_counts: &'a [usize],
+    node_cores: &'a [usize],
+    node_ranges: &'a [Vec<(u64, u64)>],
+    failed_workers: usize,
 }
 
-/// Single-node fallback
-fn run_single_node(args: &Args) -> DistributedResult {
-    let start_time = Instant::now();
-
-    let primes = simple_sieve(args.limit);
-    let count = primes.len();
-
-    let elapsed = start_time.elapsed();
-
-    DistributedResult {
-        total_primes: count,
-        nodes: 1,
-        time_ms: elapsed.as_secs_f64() * 1000.0,
-        node_counts: vec![count],
-        base_prime_count: 0,
-    }
+/// Render a distributed result as a single-line JSON object
+fn json_report(result: &DistributedResult, args: &Args) -> String {
+    let report = JsonReport {
+        limit: args.limit,
+        nodes: result.nodes,
+        time_ms: result.time_ms,
+        base_ms: result.base_ms,
+        sieve_ms: result.sieve_ms,
+        iterations: args.iterations,
+        prime_count: result.total_primes,
+        base_prime_count: result.base_prime_count,
+        density: if args.limit > 0 {
+            result.total_primes as f64 / args.limit as f64
+        } else {
+            0.0
+        },
+        node_counts: &result.node_counts,
+        chunk_counts: &result.chunk_counts,
+        node_cores: &result.node_cores,
+        node_ranges: &result.node_ranges,
+        failed_workers: result.failed_workers,
+    };
+    serde_json::to_string(&report).expect("JSON report serialization cannot fail")
 }
 
 fn print_results(result: &DistributedResult, args: &Args) {
     if args.csv {
         println!(
-            "{},{},{:.3},{}",
-            args.limit, result.nodes, result.time_ms, result.total_primes
+            "{},{},{:.3},{},{:.3},{:.3}",
+            args.limit, result.nodes, result.time_ms, result.total_primes, result.base_ms, result.sieve_ms
         );
+    } else if args.json {
+        println!("{}", json_report(result, args));
+    } else if args.quiet {
+        println!("primes={} time={:.3}ms", result.total_primes, result.time_ms);
     } else {
         println!("═══════════════════════════════════════════════════════════");
         println!("           DISTRIBUTED PRIME CALCULATION RESULTS");
@@ -475,20 +165,115 @@ fn print_results(result: &DistributedResult, args: &Args) {
         println!("  Total primes found: {}", result.total_primes);
         println!("  Base primes: {}", result.base_prime_count);
         println!("  Execution time: {:.3} ms", result.time_ms);
+        println!("    Base primes: {:.3} ms", result.base_ms);
+        println!("    Sieving: {:.3} ms", result.sieve_ms);
+        if result.pass_times_ms.len() > 1 {
+            println!("  Iterations: {} (mean time per pass)", result.pass_times_ms.len());
+            if args.verbose {
+                let passes: Vec<String> = result.pass_times_ms.iter().map(|ms| format!("{:.3}", ms)).collect();
+                println!("  Master pass times: {} ms", passes.join(", "));
+            }
+        }
+        if result.failed_workers > 0 {
+            println!("  Failed workers: {} (chunks reassigned)", result.failed_workers);
+        }
+        if let Some(primes) = &result.primes {
+            println!(
+                "  Primes gathered at the master: {} (largest {})",
+                primes.len(),
+                primes.last().copied().unwrap_or(0)
+            );
+        }
         println!("───────────────────────────────────────────────────────────");
         println!("Per-node breakdown:");
 
         for (i, count) in result.node_counts.iter().enumerate() {
             let label = if i == 0 { "Master" } else { "Worker" };
-            println!("  {} {}: {} primes", label, i, count);
+            let chunks = result.chunk_counts.get(i).copied().unwrap_or(0);
+            let cores = result.node_cores.get(i).copied().unwrap_or(0);
+            println!("  {} {}: {} primes ({} chunks, {} cores)", label, i, count, chunks, cores);
+
+            if args.verbose {
+                for (low, high) in result.node_ranges.get(i).into_iter().flatten() {
+                    println!("      [{}, {}]", low, high);
+                }
+            }
         }
 
         println!("═══════════════════════════════════════════════════════════");
     }
 }
 
+/// Filter directives for the log subscriber
+///
+/// `--log-level` wins, then `RUST_LOG` (passed in as `env`), then `debug`
+/// with `--verbose`, `warn` with `--quiet` and `info` otherwise.
+fn log_directives(args: &Args, env: Option<&str>) -> String {
+    match (&args.log_level, env) {
+        (Some(level), _) => level.clone(),
+        (None, Some(env)) if !env.is_empty() => env.to_string(),
+        _ if args.verbose => "debug".to_string(),
+        _ if args.quiet => "warn".to_string(),
+        _ => "info".to_string(),
+    }
+}
+
+/// Send log events to stderr, so CSV and JSON on stdout stay parseable
+fn init_logging(args: &Args) -> Result<(), String> {
+    let directives = log_directives(args, std::env::var("RUST_LOG").ok().as_deref());
+    let filter = EnvFilter::try_new(&directives).map_err(|e| format!("invalid log filter {:?}: {}", directives, e))?;
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init()
+        .map_err(|e| e.to_string())
+}
+
+/// Print a clap error (or `--help`/`--version`) and exit with the matching code
+fn exit_on_clap_error(e: clap::Error) -> ! {
+    let _ = e.print();
+    std::process::exit(if e.use_stderr() {
+        exit_code::INVALID_ARGS
+    } else {
+        exit_code::SUCCESS
+    })
+}
+
+/// Exit code for a finished run: lost workers count as a failure
+fn result_exit_code(result: &DistributedResult) -> i32 {
+    if result.failed_workers > 0 {
+        exit_code::WORKER_FAILURE
+    } else {
+        exit_code::SUCCESS
+    }
+}
+
 fn main() {
-    let args = Args::parse();
+    let args = Args::try_parse().unwrap_or_else(|e| exit_on_clap_error(e));
+
+    if let Err(e) = init_logging(&args) {
+        eprintln!("Error: {}", e);
+        std::process::exit(exit_code::INVALID_ARGS);
+    }
+
+    if args.dry_run {
+        if let Some(cores) = dry_run_cores(&args) {
+            match WorkPlan::new(args.limit, &cores, args.chunks_per_rank) {
+                Ok(plan) => print_plan(&plan, &cores, &args),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(exit_code::INVALID_ARGS);
+                }
+            }
+        }
+        return;
+    }
+
+    let largest_limit = tcp_impl::master_limits(&args).into_iter().max().unwrap_or(args.limit);
+    if args.verify && largest_limit > MAX_SIEVE_LIMIT {
+        eprintln!("Error: --verify can check limits up to {} only", MAX_SIEVE_LIMIT);
+        std::process::exit(exit_code::INVALID_ARGS);
+    }
 
     // Determine mode
     if args.worker {
@@ -497,7 +282,7 @@ fn main() {
             Ok(()) => {}
             Err(e) => {
                 eprintln!("Worker error: {}", e);
-                std::process::exit(1);
+                std::process::exit(exit_code::WORKER_FAILURE);
             }
         }
         return;
@@ -506,13 +291,21 @@ fn main() {
     if args.tcp {
         // TCP master mode
         match tcp_impl::run_master(&args) {
-            Ok(result) => print_results(&result, &args),
+            Ok(results) => {
+                print_csv_header(&args);
+                let code = tcp_impl::master_limits(&args)
+                    .into_iter()
+                    .zip(&results)
+                    .map(|(limit, result)| report(result, &Args { limit, ..args.clone() }))
+                    .max()
+                    .unwrap_or(exit_code::SUCCESS);
+                std::process::exit(code);
+            }
             Err(e) => {
                 eprintln!("Master error: {}", e);
-                std::process::exit(1);
+                std::process::exit(exit_code::WORKER_FAILURE);
             }
         }
-        return;
     }
 
     // Try MPI first
@@ -520,27 +313,36 @@ fn main() {
     {
         match mpi_impl::run_mpi(&args) {
             Ok(result) => {
-                // Only rank 0 prints results
+                // Only rank 0 prints (and verifies) results
                 if result.nodes > 0 {
-                    print_results(&result, &args);
+                    print_csv_header(&args);
+                    std::process::exit(report(&result, &args));
                 }
                 return;
             }
             Err(e) => {
-                eprintln!("MPI error: {}, falling back to single node", e);
+                tracing::warn!(error = %e, "MPI unavailable, falling back to single node");
             }
         }
     }
 
     // Fallback to single node
-    if !args.csv {
+    if !args.csv && !args.json && !args.quiet {
         println!("Running in single-node mode (MPI not available)");
         println!("Use --tcp flag for TCP-based distribution");
-        println!("");
+        println!();
     }
 
-    let result = run_single_node(&args);
-    print_results(&result, &args);
+    match run_single_node(&args) {
+        Ok(result) => {
+            print_csv_header(&args);
+            std::process::exit(report(&result, &args))
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(exit_code::WORKER_FAILURE);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -548,31 +350,33 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_simple_sieve() {
-        let primes = simple_sieve(100);
-        assert_eq!(primes.len(), 25);
-    }
+    fn test_log_directives_precedence() {
+        let args = Args::parse_from(["primes-mpi"]);
+        assert_eq!(log_directives(&args, None), "info");
+        assert_eq!(log_directives(&args, Some("primes_mpi=trace")), "primes_mpi=trace");
 
-    #[test]
-    fn test_segment_sieve() {
-        let base_primes = simple_sieve(10);
-        let segment = sieve_segment(10, 30, &base_primes);
-        assert_eq!(segment, vec![11, 13, 17, 19, 23, 29]);
+        let verbose = Args::parse_from(["primes-mpi", "--verbose"]);
+        assert_eq!(log_directives(&verbose, None), "debug");
+
+        let quiet = Args::parse_from(["primes-mpi", "--quiet"]);
+        assert_eq!(log_directives(&quiet, None), "warn");
+
+        let explicit = Args::parse_from(["primes-mpi", "--verbose", "--log-level", "warn"]);
+        assert_eq!(log_directives(&explicit, Some("trace")), "warn");
     }
 
     #[test]
-    fn test_single_node() {
-        let args = Args {
-            limit: 1000,
-            csv: false,
-            verbose: false,
-            tcp: false,
-            master_addr: "127.0.0.1:7878".to_string(),
-            workers: 2,
-            worker: false,
-        };
-
-        let result = run_single_node(&args);
-        assert_eq!(result.total_primes, 168); // π(1000) = 168
+    fn test_json_report_round_trip() {
+        let args = Args::parse_from(["primes-mpi", "--limit", "1000", "--json"]);
+        let result = run_single_node(&args).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&json_report(&result, &args)).unwrap();
+        assert_eq!(json["limit"], 1000);
+        assert_eq!(json["nodes"], 1);
+        assert_eq!(json["prime_count"], 168);
+        assert_eq!(json["node_counts"], serde_json::json!([168]));
+        assert!((json["density"].as_f64().unwrap() - 0.168).abs() < 1e-12);
+        assert_eq!(json["base_ms"], 0.0);
+        assert_eq!(json["sieve_ms"], json["time_ms"]);
     }
 }
@@ -0,0 +1,257 @@
+//! Prometheus metrics for the TCP master (`--metrics-addr`)
+//!
+//! The master updates a [`MasterMetrics`] as it works, whether or not
+//! anyone is watching. With `--metrics-addr` a [`MetricsServer`] thread
+//! answers `GET /metrics` with those values in the Prometheus text
+//! exposition format. It is a deliberately tiny HTTP/1.0-style responder:
+//! one request per connection, then close.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Counters and gauges for one master session
+#[derive(Debug, Default)]
+pub struct MasterMetrics {
+    primes_found: AtomicU64,
+    active_workers: AtomicU64,
+    chunks_completed: AtomicU64,
+    runs_completed: AtomicU64,
+    /// `f64` bits of the last run's wall time in seconds
+    last_run_seconds: AtomicU64,
+    /// `f64` bits of the seconds that run spent on base primes, then on chunks
+    last_base_seconds: AtomicU64,
+    last_sieve_seconds: AtomicU64,
+}
+
+impl MasterMetrics {
+    pub fn add_primes(&self, count: usize) {
+        self.primes_found.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn chunk_done(&self, count: usize) {
+        self.chunks_completed.fetch_add(1, Ordering::Relaxed);
+        self.add_primes(count);
+    }
+
+    pub fn worker_connected(&self) {
+        self.active_workers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn worker_gone(&self) {
+        self.active_workers.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn run_done(&self, seconds: f64, base_seconds: f64, sieve_seconds: f64) {
+        self.runs_completed.fetch_add(1, Ordering::Relaxed);
+        self.last_run_seconds.store(seconds.to_bits(), Ordering::Relaxed);
+        self.last_base_seconds.store(base_seconds.to_bits(), Ordering::Relaxed);
+        self.last_sieve_seconds.store(sieve_seconds.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The current values in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics: [(&str, &str, &str, f64); 7] = [
+            (
+                "primes_found_total",
+                "counter",
+                "Primes found so far, base primes included",
+                self.primes_found.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "primes_active_workers",
+                "gauge",
+                "Workers currently connected and serving chunks",
+                self.active_workers.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "primes_chunks_completed_total",
+                "counter",
+                "Chunks sieved by the master and its workers",
+                self.chunks_completed.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "primes_runs_completed_total",
+                "counter",
+                "Runs (limits) finished in this session",
+                self.runs_completed.load(Ordering::Relaxed) as f64,
+            ),
+            (
+                "primes_last_run_seconds",
+                "gauge",
+                "Wall time of the last finished run",
+                f64::from_bits(self.last_run_seconds.load(Ordering::Relaxed)),
+            ),
+            (
+                "primes_last_run_base_seconds",
+                "gauge",
+                "Part of the last run spent finding the base primes",
+                f64::from_bits(self.last_base_seconds.load(Ordering::Relaxed)),
+            ),
+            (
+                "primes_last_run_sieve_seconds",
+                "gauge",
+                "Part of the last run spent sieving the chunks",
+                f64::from_bits(self.last_sieve_seconds.load(Ordering::Relaxed)),
+            ),
+        ];
+
+        let mut body = String::new();
+        for (name, kind, help, value) in metrics {
+            body.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+        }
+        body
+    }
+}
+
+/// Background thread serving `GET /metrics` until dropped
+pub struct MetricsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Bind `addr` and start answering scrapes of `metrics`
+    pub fn start(addr: &str, metrics: Arc<MasterMetrics>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("metrics".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        if let Ok(stream) = stream {
+                            // A misbehaving client only loses its own response
+                            let _ = respond(stream, &metrics);
+                        }
+                    }
+                })?
+        };
+
+        Ok(MetricsServer {
+            addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the blocking accept() so the thread sees the stop flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answer one HTTP request: the metrics for `GET /metrics`, 404 otherwise
+fn respond(mut stream: TcpStream, metrics: &MasterMetrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    // Only the request line matters; read until the end of the headers
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_scrape_parses_as_exposition_format() {
+        let metrics = Arc::new(MasterMetrics::default());
+        metrics.worker_connected();
+        metrics.worker_connected();
+        metrics.worker_gone();
+        metrics.add_primes(168);
+        metrics.chunk_done(100);
+        metrics.run_done(0.25, 0.01, 0.2);
+
+        let server = MetricsServer::start("127.0.0.1:0", Arc::clone(&metrics)).unwrap();
+        let response = get(server.local_addr(), "/metrics");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("text/plain; version=0.0.4"));
+
+        // Every sample is `name value`, preceded by its HELP and TYPE
+        let mut samples = std::collections::HashMap::new();
+        let mut typed = std::collections::HashSet::new();
+        for line in body.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(kind == "counter" || kind == "gauge");
+                typed.insert(name.to_string());
+            } else if !line.starts_with("# HELP ") {
+                let (name, value) = line.split_once(' ').unwrap();
+                assert!(typed.contains(name), "{} has no TYPE line", name);
+                samples.insert(name.to_string(), value.parse::<f64>().unwrap());
+            }
+        }
+        assert_eq!(samples["primes_found_total"], 268.0);
+        assert_eq!(samples["primes_active_workers"], 1.0);
+        assert_eq!(samples["primes_chunks_completed_total"], 1.0);
+        assert_eq!(samples["primes_runs_completed_total"], 1.0);
+        assert_eq!(samples["primes_last_run_seconds"], 0.25);
+        assert_eq!(samples["primes_last_run_base_seconds"], 0.01);
+        assert_eq!(samples["primes_last_run_sieve_seconds"], 0.2);
+
+        assert!(get(server.local_addr(), "/").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_drop_stops_the_server() {
+        let server = MetricsServer::start("127.0.0.1:0", Arc::new(MasterMetrics::default())).unwrap();
+        let addr = server.local_addr();
+        drop(server);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
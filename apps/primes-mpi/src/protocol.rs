@@ -0,0 +1,405 @@
+//! Wire format shared by the TCP master and workers
+//!
+//! Every message travels in a frame:
+//!
+//! ```text
+//! [magic: u8 = 0xA7][version: u8][len: u32][payload: len bytes][checksum: u32]
+//! ```
+//!
+//! The checksum is FNV-1a over the payload, so a truncated or corrupted
+//! frame is rejected instead of silently producing a wrong total. Bumping
+//! [`PROTOCOL_VERSION`] makes old peers fail loudly rather than misparse.
+//!
+//! Payloads start with a one-byte tag:
+//!
+//! ```text
+//! 0 = Work     { low: u64, high: u64, limit: u64, stream: u8, has_primes: u8,
+//!                [n: u64, base_primes: [u64; n]] if has_primes }     master -> worker
+//! 1 = Shutdown                                                   master -> worker
+//! 2 = Result   { low: u64, high: u64, count: u64 }               worker -> master
+//! 3 = Hello    { cores: u64, token: [u8; rest] }                 worker -> master
+//! 4 = Rejected { reason: [u8; rest] }                            master -> worker
+//! 5 = PartialResult { chunk_id: u64, n: u64, primes: [u64; n] }  worker -> master
+//! ```
+//!
+//! By default `Work` carries only `limit`, and the worker sieves the base
+//! primes up to √limit itself; the list is sent only with
+//! `--send-base-primes`. Because every `Work` names its limit, the master's
+//! limit is the one that counts: a worker's own `--limit` is never used.
+//!
+//! With `stream` set (`--stream`), the worker sends the primes of each
+//! sub-range of the chunk as a `PartialResult` as soon as it is sieved,
+//! in ascending order, and then the usual `Result`. `chunk_id` is the
+//! `low` of the `Work` it belongs to.
+//!
+//! A worker sends `Hello` once, right after connecting, so the master can
+//! size its work units by core count. The rest of the payload is the
+//! worker's `--token` (UTF-8, empty if none); a master started with
+//! `--token` answers a wrong one with `Rejected` and closes the connection.
+//!
+//! All integers are little-endian. A frame whose `len` exceeds
+//! [`MAX_FRAME_LEN`] is rejected before its payload is read.
+
+use std::io::{self, Read, Write};
+
+/// First byte of every frame
+pub const MAGIC: u8 = 0xA7;
+
+/// Version of the frame and message layout
+pub const PROTOCOL_VERSION: u8 = 5;
+
+/// Largest payload accepted, far above the biggest real message (the
+/// base primes up to √[`MAX_SIEVE_LIMIT`](primes_core::MAX_SIEVE_LIMIT))
+pub const MAX_FRAME_LEN: usize = 16 << 20;
+
+const TAG_WORK: u8 = 0;
+const TAG_SHUTDOWN: u8 = 1;
+const TAG_RESULT: u8 = 2;
+const TAG_HELLO: u8 = 3;
+const TAG_REJECTED: u8 = 4;
+const TAG_PARTIAL_RESULT: u8 = 5;
+
+/// Message exchanged between the TCP master and a worker
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Sieve [low, high] of a run up to `limit` and reply with a `Result`
+    Work {
+        low: u64,
+        high: u64,
+        limit: u64,
+        /// Primes up to √limit, or `None` for the worker to compute them
+        base_primes: Option<Vec<u64>>,
+        /// Send the primes back in `PartialResult`s before the `Result`
+        stream: bool,
+    },
+    /// No work left; the worker should exit
+    Shutdown,
+    /// Number of primes the worker found in [low, high]
+    Result { low: u64, high: u64, count: u64 },
+    /// Handshake: the number of cores the worker sieves with and its token
+    Hello { cores: u64, token: String },
+    /// The master refused the handshake and is closing the connection
+    Rejected { reason: String },
+    /// Primes of one sub-range of the chunk starting at `chunk_id`
+    PartialResult { chunk_id: u64, primes: Vec<u64> },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Message::Work {
+                low,
+                high,
+                limit,
+                base_primes,
+                stream,
+            } => {
+                let primes = base_primes.as_deref().unwrap_or(&[]);
+                let mut data = Vec::with_capacity(35 + primes.len() * 8);
+                data.push(TAG_WORK);
+                data.extend(&low.to_le_bytes());
+                data.extend(&high.to_le_bytes());
+                data.extend(&limit.to_le_bytes());
+                data.push(*stream as u8);
+                data.push(base_primes.is_some() as u8);
+                if base_primes.is_some() {
+                    data.extend(&(primes.len() as u64).to_le_bytes());
+                    for &p in primes {
+                        data.extend(&p.to_le_bytes());
+                    }
+                }
+                data
+            }
+            Message::Shutdown => vec![TAG_SHUTDOWN],
+            Message::Result { low, high, count } => {
+                let mut data = Vec::with_capacity(25);
+                data.push(TAG_RESULT);
+                data.extend(&low.to_le_bytes());
+                data.extend(&high.to_le_bytes());
+                data.extend(&count.to_le_bytes());
+                data
+            }
+            Message::Hello { cores, token } => {
+                let mut data = vec![TAG_HELLO];
+                data.extend(&cores.to_le_bytes());
+                data.extend(token.as_bytes());
+                data
+            }
+            Message::Rejected { reason } => {
+                let mut data = vec![TAG_REJECTED];
+                data.extend(reason.as_bytes());
+                data
+            }
+            Message::PartialResult { chunk_id, primes } => {
+                let mut data = Vec::with_capacity(17 + primes.len() * 8);
+                data.push(TAG_PARTIAL_RESULT);
+                data.extend(&chunk_id.to_le_bytes());
+                data.extend(&(primes.len() as u64).to_le_bytes());
+                for &p in primes {
+                    data.extend(&p.to_le_bytes());
+                }
+                data
+            }
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Message, String> {
+        let read_u64 = |offset: usize| -> Result<u64, String> {
+            data.get(offset..offset + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or_else(|| format!("Truncated message ({} bytes)", data.len()))
+        };
+        let read_string = |offset: usize| -> Result<String, String> {
+            let bytes = data.get(offset..).ok_or_else(|| format!("Truncated message ({} bytes)", data.len()))?;
+            String::from_utf8(bytes.to_vec()).map_err(|_| "Message text is not valid UTF-8".to_string())
+        };
+
+        match data.first() {
+            Some(&TAG_WORK) => {
+                let low = read_u64(1)?;
+                let high = read_u64(9)?;
+                let limit = read_u64(17)?;
+                let stream = match data.get(25) {
+                    Some(&flag @ (0 | 1)) => flag == 1,
+                    _ => return Err(format!("Malformed work message ({} bytes)", data.len())),
+                };
+
+                let base_primes = match data.get(26) {
+                    Some(0) if data.len() == 27 => None,
+                    Some(1) => {
+                        let count = read_u64(27)? as usize;
+                        if count.checked_mul(8).and_then(|n| n.checked_add(35)) != Some(data.len()) {
+                            return Err(format!(
+                                "Work message declares {} base primes but is {} bytes",
+                                count,
+                                data.len()
+                            ));
+                        }
+                        let primes = (0..count)
+                            .map(|i| read_u64(35 + i * 8))
+                            .collect::<Result<Vec<u64>, String>>()?;
+                        Some(primes)
+                    }
+                    _ => return Err(format!("Malformed work message ({} bytes)", data.len())),
+                };
+
+                Ok(Message::Work {
+                    low,
+                    high,
+                    limit,
+                    base_primes,
+                    stream,
+                })
+            }
+            Some(&TAG_SHUTDOWN) => Ok(Message::Shutdown),
+            Some(&TAG_RESULT) => Ok(Message::Result {
+                low: read_u64(1)?,
+                high: read_u64(9)?,
+                count: read_u64(17)?,
+            }),
+            Some(&TAG_HELLO) => Ok(Message::Hello {
+                cores: read_u64(1)?,
+                token: read_string(9)?,
+            }),
+            Some(&TAG_REJECTED) => Ok(Message::Rejected {
+                reason: read_string(1)?,
+            }),
+            Some(&TAG_PARTIAL_RESULT) => {
+                let chunk_id = read_u64(1)?;
+                let count = read_u64(9)? as usize;
+                if count.checked_mul(8).and_then(|n| n.checked_add(17)) != Some(data.len()) {
+                    return Err(format!(
+                        "Partial result declares {} primes but is {} bytes",
+                        count,
+                        data.len()
+                    ));
+                }
+                let primes = (0..count)
+                    .map(|i| read_u64(17 + i * 8))
+                    .collect::<Result<Vec<u64>, String>>()?;
+                Ok(Message::PartialResult { chunk_id, primes })
+            }
+            Some(tag) => Err(format!("Unknown message tag {}", tag)),
+            None => Err("Empty message".to_string()),
+        }
+    }
+}
+
+/// 32-bit FNV-1a hash of `data`
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Write one message as a checksummed frame, then flush it
+///
+/// The frame goes out in three writes; over a [`Buffered`](crate::buffered::Buffered)
+/// channel they are gathered and sent by the flush.
+pub fn write_message(stream: &mut impl Write, message: &Message) -> io::Result<()> {
+    let payload = message.encode();
+
+    let mut header = [MAGIC, PROTOCOL_VERSION, 0, 0, 0, 0];
+    header[2..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    stream.write_all(&header)?;
+    stream.write_all(&payload)?;
+    stream.write_all(&checksum(&payload).to_le_bytes())?;
+    stream.flush()
+}
+
+/// Read one frame and decode its message
+///
+/// A bad magic byte, unknown version, checksum mismatch or undecodable
+/// payload is reported as [`io::ErrorKind::InvalidData`]; the caller can
+/// tell that apart from a broken or timed-out connection.
+pub fn read_message(stream: &mut impl Read) -> io::Result<Message> {
+    let mut header = [0u8; 6];
+    stream.read_exact(&mut header)?;
+
+    if header[0] != MAGIC {
+        return Err(invalid_data(format!("Bad frame magic 0x{:02x}", header[0])));
+    }
+    if header[1] != PROTOCOL_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported protocol version {} (expected {})",
+            header[1], PROTOCOL_VERSION
+        )));
+    }
+
+    let len = u32::from_le_bytes(header[2..6].try_into().unwrap()) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data(format!(
+            "Frame of {} bytes exceeds the {} byte maximum",
+            len, MAX_FRAME_LEN
+        )));
+    }
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload)?;
+
+    let mut checksum_buf = [0u8; 4];
+    stream.read_exact(&mut checksum_buf)?;
+    let expected = u32::from_le_bytes(checksum_buf);
+    let actual = checksum(&payload);
+    if actual != expected {
+        return Err(invalid_data(format!(
+            "Checksum mismatch (got 0x{:08x}, expected 0x{:08x})",
+            actual, expected
+        )));
+    }
+
+    Message::decode(&payload).map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(message: &Message) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, message).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = [
+            Message::Work {
+                low: 100,
+                high: 200,
+                limit: 200,
+                base_primes: Some(vec![2, 3, 5, 7, 11, 13]),
+                stream: false,
+            },
+            Message::Work {
+                low: 100,
+                high: 200,
+                limit: 200,
+                base_primes: None,
+                stream: true,
+            },
+            Message::Shutdown,
+            Message::Result {
+                low: 100,
+                high: 200,
+                count: 21,
+            },
+            Message::Hello {
+                cores: 8,
+                token: "s3cret".to_string(),
+            },
+            Message::Hello {
+                cores: 1,
+                token: String::new(),
+            },
+            Message::Rejected {
+                reason: "invalid token".to_string(),
+            },
+            Message::PartialResult {
+                chunk_id: 100,
+                primes: vec![101, 103, 107, 109, 113],
+            },
+            Message::PartialResult {
+                chunk_id: 114,
+                primes: vec![],
+            },
+        ];
+        for message in messages {
+            assert_eq!(read_message(&mut &frame(&message)[..]).unwrap(), message);
+        }
+        assert!(Message::decode(&[TAG_WORK, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_corrupted_payload_is_detected() {
+        let mut buffer = frame(&Message::Result {
+            low: 1,
+            high: 1000,
+            count: 168,
+        });
+        // Flip one bit of the count inside the payload
+        buffer[6 + 17] ^= 0x01;
+
+        let err = read_message(&mut &buffer[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+
+    #[test]
+    fn test_bad_magic_and_version_are_rejected() {
+        let good = frame(&Message::Shutdown);
+
+        let mut bad_magic = good.clone();
+        bad_magic[0] = 0x00;
+        assert_eq!(read_message(&mut &bad_magic[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut bad_version = good;
+        bad_version[1] = PROTOCOL_VERSION + 1;
+        assert_eq!(read_message(&mut &bad_version[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_oversized_lengths_are_rejected() {
+        // A header announcing a 4 GiB payload fails before anything is allocated
+        let mut huge = vec![MAGIC, PROTOCOL_VERSION];
+        huge.extend(u32::MAX.to_le_bytes());
+        let err = read_message(&mut &huge[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("exceeds"), "{}", err);
+
+        // A base prime count whose byte size overflows usize
+        let mut work = vec![TAG_WORK];
+        work.extend([0u8; 24]);
+        work.extend([0, 1]);
+        work.extend(u64::MAX.to_le_bytes());
+        assert!(Message::decode(&work).unwrap_err().contains("declares"));
+
+        let mut partial = vec![TAG_PARTIAL_RESULT];
+        partial.extend(100u64.to_le_bytes());
+        partial.extend((u64::MAX / 4).to_le_bytes());
+        assert!(Message::decode(&partial).unwrap_err().contains("declares"));
+    }
+}
@@ -0,0 +1,1496 @@
+//! TCP-based distributed calculation (fallback when MPI not available)
+//!
+//! # Protocol
+//!
+//! The master keeps (√limit, limit] as a queue of unsieved ranges and
+//! serves it dynamically: every connected worker (and the master itself)
+//! takes the next chunk as soon as it finishes the previous one, so a slow
+//! worker only delays its own chunk. Each worker reports its core count in
+//! a `Hello` when it connects, and its chunks are `--chunk-size` numbers
+//! per core, so a node with twice the cores gets twice the work per round.
+//!
+//! With `--token`, the master only accepts workers whose `Hello` carries
+//! the same token; any other worker is sent `Rejected` and disconnected
+//! before it sees any work, so it cannot inject results.
+//!
+//! A session can hold several runs: with `--sweep` the master keeps the
+//! workers connected and hands out the chunks of each limit in turn, and
+//! only sends `Shutdown` after the last one. A worker started with
+//! `--serve` reconnects after each session to wait for the next master.
+//!
+//! With `--metrics-addr` the master also serves Prometheus metrics for the
+//! session (see [`crate::metrics`]); the endpoint closes with the session.
+//!
+//! With `--tls` the same frames run inside a TLS session (see
+//! [`crate::tls`]): the master presents `--cert`/`--key` and workers verify
+//! it against `--ca`.
+//!
+//! Messages travel in checksummed frames (see [`crate::protocol`]): the
+//! master sends `Work` or `Shutdown`, the worker answers each `Work` with a
+//! `Result` echoing the chunk bounds and its prime count. Workers sieve
+//! their own base primes from the run's limit unless `--send-base-primes`
+//! asks the master to ship them with every chunk.
+//!
+//! With `--stream` the worker also sends the primes themselves, one
+//! `PartialResult` per `STREAM_PIECE_LEN` numbers, before the `Result`.
+//! The master holds a chunk's primes until its `Result` confirms the count,
+//! then files them by chunk, so the reassembled list is in order no matter
+//! which node finished first. A running total is redrawn on stderr while
+//! the primes arrive.
+//!
+//! Without `--stream`, the master instead redraws a progress line on
+//! stderr as chunks complete: chunks done, how much of the range they
+//! cover, and an ETA (see [`estimate_eta`]). Both lines are left out with
+//! `--quiet`, `--csv` or `--json`, or when stderr is not a terminal.
+//!
+//! `--master-addr` is resolved with `to_socket_addrs`, so it may be an
+//! IPv4 address, a bracketed IPv6 address such as `[::1]:7878`, or a
+//! hostname; every address it resolves to is tried in turn.
+//!
+//! # Failures
+//!
+//! With `--connect-timeout` the master gives up if fewer than `--workers`
+//! have connected in time: it sends `Shutdown` to the ones that did and
+//! fails the session.
+//!
+//! Every worker socket has a read timeout (`--worker-timeout`). If a worker
+//! times out or its connection breaks, the chunk it was working on goes
+//! back to the front of the queue for another node, and the worker is
+//! counted in `failed_workers`. A result that fails its checksum or names
+//! the wrong chunk is rejected and the chunk requeued; after
+//! `MAX_BAD_RESULTS` such results the worker is dropped as failed. Chunks
+//! still queued once every node has stopped are sieved on the master as a
+//! last resort.
+
+use super::*;
+use crate::metrics::{MasterMetrics, MetricsServer};
+use crate::protocol::{read_message, write_message, Message};
+use crate::buffered::Buffered;
+use crate::tls::{self, Channel};
+use primes_core::sieve_segment;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{ErrorKind, IsTerminal};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, debug_span, info, info_span, warn};
+
+/// Corrupted results tolerated from one worker before it is dropped
+const MAX_BAD_RESULTS: usize = 3;
+
+/// Numbers per `PartialResult` a streaming worker sends
+const STREAM_PIECE_LEN: u64 = 65_536;
+
+/// Take the next chunk of at most `max_len` numbers off the front of the queue
+///
+/// A longer range is split and its remainder left at the front, so the
+/// queue is always consumed in ascending order.
+fn take_chunk(queue: &Mutex<VecDeque<(u64, u64)>>, max_len: u64) -> Option<(u64, u64)> {
+    let mut queue = queue.lock().unwrap();
+    let (low, high) = queue.pop_front()?;
+    let chunk_high = low.saturating_add(max_len.max(1) - 1).min(high);
+    if chunk_high < high {
+        queue.push_front((chunk_high + 1, high));
+    }
+    Some((low, chunk_high))
+}
+
+/// Time left for a round that has covered `done` of its `total` numbers
+/// in `elapsed`, assuming the rest goes at the same average pace
+///
+/// `None` before anything is done, when there is no pace to go by.
+fn estimate_eta(done: u64, total: u64, elapsed: Duration) -> Option<Duration> {
+    if done == 0 {
+        return None;
+    }
+    let remaining = total.saturating_sub(done);
+    Some(elapsed.mul_f64(remaining as f64 / done as f64))
+}
+
+/// An ETA as `42s` or `3m07s`, or `--` when there is none yet
+fn format_eta(eta: Option<Duration>) -> String {
+    match eta.map(|eta| eta.as_secs_f64().round() as u64) {
+        None => "--".to_string(),
+        Some(secs) if secs < 60 => format!("{}s", secs),
+        Some(secs) => format!("{}m{:02}s", secs / 60, secs % 60),
+    }
+}
+
+/// The unsieved ranges of a round, and how far the round has got
+struct WorkQueue {
+    pending: Mutex<VecDeque<(u64, u64)>>,
+    /// Numbers in the whole round
+    total: u64,
+    numbers_done: AtomicU64,
+    chunks_done: AtomicUsize,
+    started: Instant,
+    /// Redraw the progress line on stderr as chunks complete
+    live: bool,
+}
+
+impl WorkQueue {
+    /// A queue holding [low, high] (nothing if `low > high`)
+    fn new(low: u64, high: u64, live: bool) -> Self {
+        let (pending, total) = if low <= high {
+            (VecDeque::from([(low, high)]), high - low + 1)
+        } else {
+            (VecDeque::new(), 0)
+        };
+        WorkQueue {
+            pending: Mutex::new(pending),
+            total,
+            numbers_done: AtomicU64::new(0),
+            chunks_done: AtomicUsize::new(0),
+            started: Instant::now(),
+            live,
+        }
+    }
+
+    /// The next chunk of at most `max_len` numbers, see [`take_chunk`]
+    fn take(&self, max_len: u64) -> Option<(u64, u64)> {
+        take_chunk(&self.pending, max_len)
+    }
+
+    /// Return a chunk that was not finished, to be handed out next
+    fn put_back(&self, chunk: (u64, u64)) {
+        self.pending.lock().unwrap().push_front(chunk);
+    }
+
+    /// Record [low, high] as sieved, redrawing the progress line if live
+    fn chunk_done(&self, low: u64, high: u64) {
+        let numbers = self.numbers_done.fetch_add(high - low + 1, Ordering::Relaxed) + (high - low + 1);
+        let chunks = self.chunks_done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.live {
+            eprint!("\r  {}   ", self.progress_line(chunks, numbers));
+        }
+    }
+
+    /// `Chunks: done/~total (percent), ETA ...`; the chunk total is
+    /// extrapolated, as chunk sizes follow each node's core count
+    fn progress_line(&self, chunks: usize, numbers: u64) -> String {
+        let fraction = numbers as f64 / self.total.max(1) as f64;
+        let expected_chunks = (chunks as f64 / fraction.max(f64::MIN_POSITIVE)).ceil() as usize;
+        format!(
+            "Chunks: {}/~{} ({:.1}%), ETA {}",
+            chunks,
+            expected_chunks.max(chunks),
+            fraction * 100.0,
+            format_eta(estimate_eta(numbers, self.total, self.started.elapsed()))
+        )
+    }
+}
+
+/// What one node (master or worker) contributed to the run
+#[derive(Debug, Default, Clone)]
+struct NodeTally {
+    primes: usize,
+    /// Cores the node reported
+    cores: usize,
+    /// Chunks [low, high] the node sieved
+    ranges: Vec<(u64, u64)>,
+    /// The worker timed out or disconnected before the queue drained
+    failed: bool,
+    /// Results rejected for a bad checksum or mismatched chunk
+    rejected: usize,
+}
+
+/// Primes collected from every node with `--stream`
+struct StreamAccumulator {
+    /// Primes of each confirmed chunk, keyed by the chunk's low end
+    chunks: Mutex<BTreeMap<u64, Vec<u64>>>,
+    /// Primes received so far, including chunks still in flight
+    running_total: AtomicUsize,
+    /// Redraw the running total on stderr as primes arrive
+    live: bool,
+}
+
+impl StreamAccumulator {
+    /// Start the running total at the `base_count` base primes
+    fn new(base_count: usize, live: bool) -> Self {
+        StreamAccumulator {
+            chunks: Mutex::new(BTreeMap::new()),
+            running_total: AtomicUsize::new(base_count),
+            live,
+        }
+    }
+
+    fn received(&self, count: usize) {
+        let total = self.running_total.fetch_add(count, Ordering::Relaxed) + count;
+        if self.live {
+            eprint!("\r  Primes so far: {}", total);
+        }
+    }
+
+    /// Take back primes of a chunk that will be redone
+    fn discarded(&self, count: usize) {
+        self.running_total.fetch_sub(count, Ordering::Relaxed);
+    }
+
+    fn commit(&self, chunk_low: u64, primes: Vec<u64>) {
+        self.chunks.lock().unwrap().insert(chunk_low, primes);
+    }
+
+    /// Every committed prime, in ascending order
+    fn into_primes(self) -> Vec<u64> {
+        self.chunks.into_inner().unwrap().into_values().flatten().collect()
+    }
+}
+
+/// Whether `part` carries on the ascending primes `so_far` of [low, high]
+fn continues_chunk(so_far: &[u64], part: &[u64], low: u64, high: u64) -> bool {
+    let mut previous = so_far.last().copied();
+    part.iter().all(|&p| {
+        let in_order = (low..=high).contains(&p) && previous.is_none_or(|q| p > q);
+        previous = Some(p);
+        in_order
+    })
+}
+
+/// Compare two byte strings in time independent of where they differ
+///
+/// Only the length can leak, which says nothing about the contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Whether a worker's `Hello` token lets it in: always when the master has
+/// no `--token`, otherwise only on an exact match
+fn token_accepted(expected: Option<&str>, presented: &str) -> bool {
+    expected.is_none_or(|expected| constant_time_eq(expected.as_bytes(), presented.as_bytes()))
+}
+
+/// Base primes for a run up to `limit`: every prime up to √limit
+///
+/// The master and the workers both call this, so a worker that computes
+/// its own base primes gets exactly the list the master would send.
+fn base_primes_for(limit: u64) -> Result<Vec<u64>, String> {
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+    simple_sieve(sqrt_limit).map_err(|e| e.to_string())
+}
+
+/// Send one chunk to a worker and wait for its count
+///
+/// With `streamed`, the worker's primes for the chunk are collected as
+/// they arrive and committed once the `Result` count matches them.
+///
+/// A corrupted frame, or a result for a chunk other than the one sent, is
+/// returned as [`ErrorKind::InvalidData`]; so is a streamed prime out of
+/// order or outside the chunk.
+fn exchange_chunk(
+    stream: &mut Channel,
+    low: u64,
+    high: u64,
+    limit: u64,
+    base_primes: Option<&[u64]>,
+    streamed: Option<&StreamAccumulator>,
+) -> std::io::Result<usize> {
+    let work = Message::Work {
+        low,
+        high,
+        limit,
+        base_primes: base_primes.map(<[u64]>::to_vec),
+        stream: streamed.is_some(),
+    };
+    write_message(stream, &work)?;
+
+    let mut primes = Vec::new();
+    let reply = loop {
+        match (read_message(stream), streamed) {
+            (Ok(Message::PartialResult { chunk_id, primes: part }), Some(accumulator))
+                if chunk_id == low && continues_chunk(&primes, &part, low, high) =>
+            {
+                accumulator.received(part.len());
+                primes.extend(part);
+            }
+            (reply, _) => break reply,
+        }
+    };
+
+    let outcome = match reply {
+        Ok(Message::Result {
+            low: result_low,
+            high: result_high,
+            count,
+        }) if (result_low, result_high) == (low, high)
+            && streamed.is_none_or(|_| count as usize == primes.len()) =>
+        {
+            Ok(count as usize)
+        }
+        Ok(other) => Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unexpected reply {:?}", other),
+        )),
+        Err(e) => Err(e),
+    };
+
+    if let Some(accumulator) = streamed {
+        match outcome {
+            Ok(_) => accumulator.commit(low, primes),
+            Err(_) => accumulator.discarded(primes.len()),
+        }
+    }
+    outcome
+}
+
+/// Serve chunks to one worker until the queue drains
+///
+/// A rejected result puts the chunk back on the queue and the worker keeps
+/// being served. If the worker fails (or keeps sending bad results), its
+/// in-flight chunk is put back on the queue and the tally so far is
+/// returned with `failed` set.
+fn serve_worker(
+    worker_id: usize,
+    worker: &mut Worker,
+    queue: &WorkQueue,
+    base_primes: &[u64],
+    args: &Args,
+    metrics: &MasterMetrics,
+    streamed: Option<&StreamAccumulator>,
+) -> NodeTally {
+    let cores = worker.cores;
+    let mut tally = NodeTally {
+        cores,
+        ..NodeTally::default()
+    };
+    let sent_primes = args.send_base_primes.then_some(base_primes);
+    let _span = info_span!("serve_worker", worker_id, cores).entered();
+
+    while let Some((low, high)) = queue.take(args.chunk_size.saturating_mul(cores as u64)) {
+        let _dispatch = debug_span!("dispatch", low, high).entered();
+        match exchange_chunk(&mut worker.stream, low, high, args.limit, sent_primes, streamed) {
+            Ok(count) => {
+                debug!(worker_id, low, high, count, "Chunk done");
+                metrics.chunk_done(count);
+                queue.chunk_done(low, high);
+                tally.primes += count;
+                tally.ranges.push((low, high));
+            }
+            Err(e) if e.kind() == ErrorKind::InvalidData && tally.rejected + 1 < MAX_BAD_RESULTS => {
+                warn!(worker_id, low, high, error = %e, "Bad result, retrying chunk");
+                queue.put_back((low, high));
+                tally.rejected += 1;
+            }
+            Err(e) => {
+                warn!(worker_id, low, high, error = %e, "Worker failed, reassigning chunk");
+                queue.put_back((low, high));
+                metrics.worker_gone();
+                tally.failed = true;
+                return tally;
+            }
+        }
+    }
+
+    tally
+}
+
+/// Sieve chunks locally on the master until the queue drains
+fn serve_local(
+    queue: &WorkQueue,
+    base_primes: &[u64],
+    cores: usize,
+    args: &Args,
+    metrics: &MasterMetrics,
+    streamed: Option<&StreamAccumulator>,
+) -> Result<NodeTally, String> {
+    let mut tally = NodeTally {
+        cores,
+        ..NodeTally::default()
+    };
+
+    while let Some((low, high)) = queue.take(args.chunk_size.saturating_mul(cores as u64)) {
+        let count = match streamed {
+            Some(accumulator) => {
+                let primes = sieve_segment(low, high, base_primes).map_err(|e| e.to_string())?;
+                let count = primes.len();
+                accumulator.received(count);
+                accumulator.commit(low, primes);
+                count
+            }
+            None => count_segment(low, high, base_primes).map_err(|e| e.to_string())?,
+        };
+        debug!(worker_id = 0, low, high, count, "Chunk done on master");
+        metrics.chunk_done(count);
+        queue.chunk_done(low, high);
+
+        tally.primes += count;
+        tally.ranges.push((low, high));
+    }
+
+    Ok(tally)
+}
+
+/// A worker that completed the handshake, connected for the whole session
+struct Worker {
+    stream: Channel,
+    cores: usize,
+}
+
+/// Limits the master runs, in order: `--sweep` if given, else `--limit`
+pub fn master_limits(args: &Args) -> Vec<u64> {
+    if args.sweep.is_empty() {
+        vec![args.limit]
+    } else {
+        args.sweep.clone()
+    }
+}
+
+/// Every socket address `addr` resolves to, in resolver order
+///
+/// Accepts `host:port` with an IPv4 address, a bracketed IPv6 address
+/// (`[::1]:7878`) or a hostname.
+fn resolve_addrs(addr: &str) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve master address {}: {}", addr, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("Master address {} resolved to no addresses", addr));
+    }
+    Ok(addrs)
+}
+
+/// Bind to the first address `addr` resolves to that accepts a listener
+fn bind_master(addr: &str) -> Result<TcpListener, String> {
+    let mut last_error = None;
+    for resolved in resolve_addrs(addr)? {
+        match TcpListener::bind(resolved) {
+            Ok(listener) => return Ok(listener),
+            Err(e) => {
+                debug!(addr = %resolved, error = %e, "Bind failed");
+                last_error = Some(format!("{}: {}", resolved, e));
+            }
+        }
+    }
+    Err(format!("Failed to bind {}: {}", addr, last_error.unwrap_or_default()))
+}
+
+/// Run as TCP master, one round per limit in [`master_limits`]
+pub fn run_master(args: &Args) -> Result<Vec<DistributedResult>, String> {
+    let listener = bind_master(&args.master_addr)?;
+    let bound = listener.local_addr().map_err(|e| e.to_string())?;
+
+    info!(addr = %args.master_addr, %bound, "Master listening");
+
+    run_master_on(listener, args)
+}
+
+/// Run as TCP master on an already bound listener
+///
+/// Workers connect once; every limit is then a round over the same
+/// connections, and they are only sent `Shutdown` after the last round.
+pub fn run_master_on(listener: TcpListener, args: &Args) -> Result<Vec<DistributedResult>, String> {
+    let _span = info_span!("run_master", workers = args.workers).entered();
+
+    // The endpoint lives exactly as long as the session: dropping the
+    // server at the end of this function stops its thread
+    let metrics = Arc::new(MasterMetrics::default());
+    let _metrics_server = match &args.metrics_addr {
+        Some(addr) => {
+            let server = MetricsServer::start(addr, Arc::clone(&metrics))
+                .map_err(|e| format!("Failed to bind metrics endpoint {}: {}", addr, e))?;
+            info!(addr = %server.local_addr(), "Serving metrics");
+            Some(server)
+        }
+        None => None,
+    };
+
+    let mut workers = accept_workers(&listener, args, &metrics)?;
+
+    let mut results = Vec::new();
+    for limit in master_limits(args) {
+        let round_args = Args { limit, ..args.clone() };
+        match run_round(&mut workers, &round_args, &metrics) {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                shutdown_workers(&mut workers, &metrics);
+                return Err(e);
+            }
+        }
+    }
+
+    shutdown_workers(&mut workers, &metrics);
+    Ok(results)
+}
+
+/// Wait for the next connection, giving up at `deadline`
+///
+/// With a deadline the listener must be nonblocking; it is polled every
+/// 50 ms until a worker connects or time runs out.
+fn accept_until(
+    listener: &TcpListener,
+    deadline: Option<Instant>,
+) -> Result<Option<(TcpStream, std::net::SocketAddr)>, String> {
+    loop {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                stream.set_nonblocking(false).map_err(|e| format!("Accept failed: {}", e))?;
+                return Ok(Some((stream, addr)));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => match deadline {
+                Some(deadline) if Instant::now() >= deadline => return Ok(None),
+                _ => thread::sleep(std::time::Duration::from_millis(50)),
+            },
+            Err(e) => return Err(format!("Accept failed: {}", e)),
+        }
+    }
+}
+
+/// Accept `--workers` connections and read each one's `Hello`
+///
+/// A worker whose handshake fails is `None`; its connection is dropped
+/// (and so closed) right away. If `--connect-timeout` runs out first, the
+/// workers already connected are shut down and the session fails.
+fn accept_workers(
+    listener: &TcpListener,
+    args: &Args,
+    metrics: &MasterMetrics,
+) -> Result<Vec<Option<Worker>>, String> {
+    info!(workers = args.workers, "Waiting for workers to connect");
+
+    let tls_config = if args.tls {
+        Some(tls::server_config(args.cert.as_deref(), args.key.as_deref())?)
+    } else {
+        None
+    };
+
+    let deadline = args
+        .connect_timeout
+        .map(|secs| Instant::now() + std::time::Duration::from_secs(secs));
+    listener
+        .set_nonblocking(deadline.is_some())
+        .map_err(|e| format!("Failed to configure listener: {}", e))?;
+
+    let worker_timeout = std::time::Duration::from_secs(args.worker_timeout.max(1));
+    let mut workers = Vec::new();
+    for i in 0..args.workers {
+        let Some((stream, addr)) = accept_until(listener, deadline)? else {
+            shutdown_workers(&mut workers, metrics);
+            return Err(format!(
+                "Timed out after {} s waiting for workers: {} of {} connected",
+                args.connect_timeout.unwrap_or_default(),
+                i,
+                args.workers
+            ));
+        };
+
+        // A crashed or hung worker must not block the master forever
+        stream.set_read_timeout(Some(worker_timeout)).ok();
+        stream.set_write_timeout(Some(worker_timeout)).ok();
+
+        let stream: Channel = match &tls_config {
+            Some(config) => tls::accept(stream, config)?,
+            None => Box::new(stream),
+        };
+        let mut stream: Channel = Box::new(Buffered::new(stream, args.tcp_buffer_size));
+
+        let cores = match read_message(&mut stream) {
+            Ok(Message::Hello { cores, token }) if token_accepted(args.token.as_deref(), &token) => {
+                info!(worker_id = i + 1, %addr, cores, "Worker connected");
+                metrics.worker_connected();
+                Some((cores as usize).max(1))
+            }
+            Ok(Message::Hello { .. }) => {
+                warn!(worker_id = i + 1, %addr, "Worker rejected: invalid token");
+                let rejected = Message::Rejected {
+                    reason: "invalid token".to_string(),
+                };
+                write_message(&mut stream, &rejected).ok();
+                None
+            }
+            Ok(other) => {
+                warn!(worker_id = i + 1, %addr, message = ?other, "Worker skipped: expected Hello");
+                None
+            }
+            Err(e) => {
+                warn!(worker_id = i + 1, %addr, error = %e, "Worker skipped: handshake failed");
+                None
+            }
+        };
+        workers.push(cores.map(|cores| Worker { stream, cores }));
+    }
+
+    Ok(workers)
+}
+
+/// Tell every worker still connected that the session is over
+fn shutdown_workers(workers: &mut [Option<Worker>], metrics: &MasterMetrics) {
+    for (i, worker) in workers.iter_mut().enumerate() {
+        // A worker that misses the shutdown message exits on its own read timeout
+        if let Some(worker) = worker.take() {
+            metrics.worker_gone();
+            let mut stream = worker.stream;
+            if let Err(e) = write_message(&mut stream, &Message::Shutdown) {
+                warn!(worker_id = i + 1, error = %e, "Shutdown failed");
+            }
+        }
+    }
+}
+
+/// Sieve (√limit, limit] for `args.limit` across the master and `workers`
+///
+/// A worker that fails during the round is dropped (set to `None`) and
+/// gets no work in later rounds.
+fn run_round(
+    workers: &mut [Option<Worker>],
+    args: &Args,
+    metrics: &MasterMetrics,
+) -> Result<DistributedResult, String> {
+    let _span = info_span!("round", limit = args.limit).entered();
+    let start_time = Instant::now();
+
+    // Calculate base primes
+    let sqrt_limit = (args.limit as f64).sqrt() as u64;
+    let base_primes = base_primes_for(args.limit)?;
+    let base_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+    let sieve_started = Instant::now();
+    metrics.add_primes(base_primes.len());
+
+    debug!(
+        workers = args.workers,
+        limit = args.limit,
+        chunk_size = args.chunk_size,
+        base_primes = base_primes.len(),
+        send_base_primes = args.send_base_primes,
+        "TCP master configuration"
+    );
+
+    // Unsieved part of (√limit, limit], carved into chunks on demand; the
+    // streamed running total, if any, takes the place of the progress line
+    let master_cores = node_cores(args);
+    let live = !args.quiet && !args.csv && !args.json && std::io::stderr().is_terminal();
+    let queue = WorkQueue::new(sqrt_limit + 1, args.limit, live && !args.stream);
+    let streamed = args.stream.then(|| StreamAccumulator::new(base_primes.len(), live));
+
+    // One thread per worker connection, plus the master sieving locally
+    let tallies = thread::scope(|scope| {
+        let handles: Vec<_> = workers
+            .iter_mut()
+            .enumerate()
+            .map(|(i, slot)| {
+                let queue = &queue;
+                let base_primes = &base_primes;
+                let streamed = streamed.as_ref();
+                scope.spawn(move || {
+                    let Some(worker) = slot else {
+                        return NodeTally {
+                            failed: true,
+                            ..NodeTally::default()
+                        };
+                    };
+                    let tally = serve_worker(i + 1, worker, queue, base_primes, args, metrics, streamed);
+                    if tally.failed {
+                        *slot = None;
+                    }
+                    tally
+                })
+            })
+            .collect();
+
+        let local = serve_local(&queue, &base_primes, master_cores, args, metrics, streamed.as_ref());
+        let workers: Vec<NodeTally> = handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().unwrap_or(NodeTally {
+                    failed: true,
+                    ..NodeTally::default()
+                })
+            })
+            .collect();
+        (local, workers)
+    });
+    let (mut master_tally, worker_tallies) = (tallies.0?, tallies.1);
+
+    // Last resort: chunks given back by workers that failed after the
+    // master had already run out of work
+    let leftover = serve_local(&queue, &base_primes, master_cores, args, metrics, streamed.as_ref())?;
+    master_tally.primes += leftover.primes;
+    master_tally.ranges.extend(leftover.ranges);
+
+    let sieve_ms = sieve_started.elapsed().as_secs_f64() * 1000.0;
+    let elapsed = start_time.elapsed();
+    metrics.run_done(elapsed.as_secs_f64(), base_ms / 1000.0, sieve_ms / 1000.0);
+    let all_tallies: Vec<NodeTally> = std::iter::once(master_tally).chain(worker_tallies).collect();
+    let node_counts: Vec<usize> = all_tallies.iter().map(|tally| tally.primes).collect();
+    let total_from_segments: usize = node_counts.iter().sum();
+
+    // Finish the redrawn line, whichever of the two it was
+    let drew_progress = queue.live && queue.chunks_done.load(Ordering::Relaxed) > 0;
+    if drew_progress || (live && streamed.is_some()) {
+        eprintln!();
+    }
+    let primes = streamed.map(|accumulator| {
+        let mut primes = base_primes.clone();
+        primes.extend(accumulator.into_primes());
+        primes
+    });
+
+    Ok(DistributedResult {
+        total_primes: base_primes.len() + total_from_segments,
+        nodes: args.workers + 1,
+        time_ms: elapsed.as_secs_f64() * 1000.0,
+        base_ms,
+        sieve_ms,
+        pass_times_ms: Vec::new(),
+        node_counts,
+        chunk_counts: all_tallies.iter().map(|tally| tally.ranges.len()).collect(),
+        node_cores: all_tallies.iter().map(|tally| tally.cores).collect(),
+        failed_workers: all_tallies.iter().filter(|tally| tally.failed).count(),
+        node_ranges: all_tallies.into_iter().map(|tally| tally.ranges).collect(),
+        base_prime_count: base_primes.len(),
+        primes,
+    })
+}
+
+/// Connect to the master, retrying every 200 ms for up to `timeout`
+/// (forever if `None`)
+///
+/// Each attempt tries every address `--master-addr` resolves to in turn.
+/// An address that does not resolve at all fails straight away.
+fn connect_to_master(args: &Args, timeout: Option<std::time::Duration>) -> Result<TcpStream, String> {
+    let start_time = Instant::now();
+    let addrs = resolve_addrs(&args.master_addr)?;
+
+    // RETRY LOOP: Keep trying to connect until Master is ready
+    loop {
+        let mut last_error = None;
+        for &resolved in &addrs {
+            match TcpStream::connect(resolved) {
+                Ok(stream) => {
+                    info!(addr = %resolved, "Reached master");
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(format!("{}: {}", resolved, e)),
+            }
+        }
+
+        if timeout.is_some_and(|timeout| start_time.elapsed() >= timeout) {
+            return Err(format!(
+                "Connection failed: Could not reach master at {} after {}s (last tried {})",
+                args.master_addr,
+                timeout.unwrap_or_default().as_secs(),
+                last_error.unwrap_or_default()
+            ));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+/// Run as TCP worker
+///
+/// Without `--serve` the worker handles one master session and exits on
+/// its `Shutdown`. With `--serve` it then reconnects and waits for the
+/// next master, for as long as it runs; a session that fails is logged and
+/// followed by a reconnect.
+pub fn run_worker(args: &Args) -> Result<(), String> {
+    let _span = info_span!("run_worker", master = %args.master_addr).entered();
+
+    loop {
+        info!("Connecting to master");
+        let timeout = (!args.serve).then(|| std::time::Duration::from_secs(10));
+        let stream = connect_to_master(args, timeout)?;
+
+        match worker_session(stream, args) {
+            Ok(()) if !args.serve => return Ok(()),
+            Err(e) if !args.serve => return Err(e),
+            Ok(()) => info!("Session over, waiting for the next master"),
+            Err(e) => {
+                warn!(error = %e, "Session failed, reconnecting");
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+        }
+    }
+}
+
+/// Sieve [low, high] in pieces, sending each piece's primes as a
+/// `PartialResult`, and return how many there were
+fn stream_chunk(stream: &mut Channel, low: u64, high: u64, base_primes: &[u64]) -> Result<usize, String> {
+    let mut count = 0;
+    let mut piece_low = low;
+    loop {
+        let piece_high = piece_low.saturating_add(STREAM_PIECE_LEN - 1).min(high);
+        let primes = sieve_segment(piece_low, piece_high, base_primes).map_err(|e| e.to_string())?;
+        count += primes.len();
+
+        let partial = Message::PartialResult { chunk_id: low, primes };
+        write_message(stream, &partial).map_err(|e| format!("Write partial result failed: {}", e))?;
+
+        if piece_high >= high {
+            return Ok(count);
+        }
+        piece_low = piece_high + 1;
+    }
+}
+
+/// Handshake with the master, then sieve its `Work` until `Shutdown`
+///
+/// Work for any number of runs (limits) can arrive on one connection; the
+/// base primes are recomputed whenever the limit changes. The master is
+/// authoritative: every chunk is sieved for the limit its `Work` names,
+/// whatever this worker's own `--limit` says.
+fn worker_session(stream: TcpStream, args: &Args) -> Result<(), String> {
+    // The master only waits for the accept of every worker before handing
+    // out chunks, so a generous read timeout still catches a dead master
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(60))).ok();
+    stream.set_write_timeout(Some(std::time::Duration::from_secs(30))).ok();
+
+    let stream: Channel = if args.tls {
+        let config = tls::client_config(args.ca.as_deref())?;
+        tls::connect(stream, &config, &args.master_addr)?
+    } else {
+        Box::new(stream)
+    };
+    let mut stream: Channel = Box::new(Buffered::new(stream, args.tcp_buffer_size));
+
+    let cores = node_cores(args);
+    let hello = Message::Hello {
+        cores: cores as u64,
+        token: args.token.clone().unwrap_or_default(),
+    };
+    write_message(&mut stream, &hello).map_err(|e| format!("Handshake failed: {}", e))?;
+
+    info!(cores, "Connected to master");
+
+    // Receive work until the master says to stop
+    let mut chunks = 0;
+    let mut total = 0;
+    // Base primes sieved here, kept for every chunk of the same run
+    let mut own_base_primes: Option<(u64, Vec<u64>)> = None;
+    let mut run_limit = None;
+    loop {
+        let message = read_message(&mut stream).map_err(|e| format!("Read failed: {}", e))?;
+
+        match message {
+            Message::Work {
+                low,
+                high,
+                limit,
+                base_primes,
+                stream: streaming,
+            } => {
+                debug!(low, high, limit, streaming, "Received work");
+                if run_limit.replace(limit) != Some(limit) && limit != args.limit {
+                    info!(limit, own_limit = args.limit, "Adopting the master's limit");
+                }
+
+                let base_primes: &[u64] = match &base_primes {
+                    Some(sent) => sent,
+                    None => {
+                        if own_base_primes.as_ref().map(|(cached_limit, _)| *cached_limit) != Some(limit) {
+                            own_base_primes = Some((limit, base_primes_for(limit)?));
+                        }
+                        &own_base_primes.as_ref().unwrap().1
+                    }
+                };
+
+                let count = if streaming {
+                    stream_chunk(&mut stream, low, high, base_primes)?
+                } else {
+                    count_segment(low, high, base_primes).map_err(|e| e.to_string())?
+                };
+
+                let result = Message::Result {
+                    low,
+                    high,
+                    count: count as u64,
+                };
+                write_message(&mut stream, &result).map_err(|e| format!("Write result failed: {}", e))?;
+
+                chunks += 1;
+                total += count;
+            }
+            Message::Shutdown => break,
+            Message::Rejected { reason } => return Err(format!("Master rejected connection: {}", reason)),
+            other => return Err(format!("Unexpected message from master: {:?}", other)),
+        }
+    }
+
+    info!(chunks, count = total, "Shutdown received");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_ipv6_loopback_round_trip() {
+        let listener = match bind_master("[::1]:0") {
+            Ok(listener) => listener,
+            // No IPv6 on this host; nothing to test
+            Err(_) => return,
+        };
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.is_ipv6());
+        let addr = addr.to_string();
+        assert!(addr.starts_with("[::1]:"), "{}", addr);
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "100000",
+            "--workers",
+            "1",
+            "--master-addr",
+            &addr,
+        ]);
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 9_592);
+    }
+
+    #[test]
+    fn test_bad_master_address_is_reported() {
+        let err = resolve_addrs("no-such-host.invalid:7878").unwrap_err();
+        assert!(err.contains("Cannot resolve master address no-such-host.invalid:7878"), "{}", err);
+        assert!(resolve_addrs("127.0.0.1").unwrap_err().contains("127.0.0.1"));
+        assert_eq!(resolve_addrs("[::1]:7878").unwrap(), vec!["[::1]:7878".parse().unwrap()]);
+
+        let args = Args::parse_from(["primes-mpi", "--worker", "--tcp", "--master-addr", "no-such-host.invalid:1"]);
+        let err = connect_to_master(&args, Some(std::time::Duration::from_secs(5))).unwrap_err();
+        assert!(err.contains("Cannot resolve"), "{}", err);
+    }
+
+    #[test]
+    fn test_estimate_eta() {
+        let elapsed = Duration::from_secs(10);
+        assert_eq!(estimate_eta(0, 1000, elapsed), None);
+        assert_eq!(estimate_eta(0, 0, Duration::ZERO), None);
+        assert_eq!(estimate_eta(250, 1000, elapsed), Some(Duration::from_secs(30)));
+        assert_eq!(estimate_eta(500, 1000, elapsed), Some(elapsed));
+        assert_eq!(estimate_eta(1000, 1000, elapsed), Some(Duration::ZERO));
+
+        assert_eq!(format_eta(None), "--");
+        assert_eq!(format_eta(Some(Duration::from_millis(41_600))), "42s");
+        assert_eq!(format_eta(Some(Duration::from_secs(187))), "3m07s");
+    }
+
+    #[test]
+    fn test_work_queue_progress() {
+        let queue = WorkQueue::new(101, 1100, false);
+        assert_eq!(queue.progress_line(0, 0), "Chunks: 0/~0 (0.0%), ETA --");
+        let chunk = queue.take(250).unwrap();
+        assert_eq!(chunk, (101, 350));
+        queue.chunk_done(chunk.0, chunk.1);
+        let line = queue.progress_line(1, 250);
+        assert!(line.starts_with("Chunks: 1/~4 (25.0%), ETA "), "{}", line);
+
+        // Nothing to sieve when √limit already reaches the limit
+        let empty = WorkQueue::new(11, 10, false);
+        assert_eq!((empty.total, empty.take(5)), (0, None));
+    }
+
+    #[test]
+    fn test_take_chunk() {
+        let queue = Mutex::new(VecDeque::from([(10, 35)]));
+        assert_eq!(take_chunk(&queue, 10), Some((10, 19)));
+        assert_eq!(take_chunk(&queue, 20), Some((20, 35)));
+        assert_eq!(take_chunk(&queue, 10), None);
+    }
+
+    #[test]
+    fn test_worker_base_primes_match_master() {
+        // What the master sends is exactly every prime p with p² <= limit
+        for limit in [0, 1, 4, 99, 100, 101, 9_973 * 9_973, 1_000_000] {
+            let expected: Vec<u64> = simple_sieve(10_000)
+                .unwrap()
+                .into_iter()
+                .filter(|&p| p * p <= limit)
+                .collect();
+            assert_eq!(base_primes_for(limit).unwrap(), expected, "limit {}", limit);
+        }
+    }
+
+    #[test]
+    fn test_send_base_primes_gives_same_total() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--send-base-primes",
+            "--master-addr",
+            &addr,
+        ]);
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 78_498);
+    }
+
+    #[test]
+    fn test_tiny_tcp_buffer_gives_same_total() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        // 16 bytes is less than one frame: every message takes several reads
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--send-base-primes",
+            "--tcp-buffer-size",
+            "16",
+            "--master-addr",
+            &addr,
+        ]);
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.failed_workers, 0);
+    }
+
+    #[test]
+    fn test_worker_adopts_the_masters_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--master-addr",
+            &addr,
+        ]);
+        // Base primes up to √1000 would miss most of those up to √1000000
+        let worker_args = Args {
+            worker: true,
+            limit: 1000,
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 78_498);
+        assert!(result.node_counts[1] > 0);
+    }
+
+    #[test]
+    fn test_master_with_two_workers_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "2",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--master-addr",
+            &addr,
+        ]);
+
+        // One single-core worker and one reporting three cores
+        let workers: Vec<_> = [1, 3]
+            .into_iter()
+            .map(|cores| {
+                let args = Args {
+                    worker: true,
+                    cores: Some(cores),
+                    ..args.clone()
+                };
+                thread::spawn(move || run_worker(&args))
+            })
+            .collect();
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.nodes, 3);
+        assert_eq!(result.node_cores, vec![1, 1, 3]);
+
+        // Chunks are --chunk-size numbers per core and tile (1000, 1_000_000]
+        let mut all_ranges: Vec<(u64, u64)> = Vec::new();
+        for (ranges, &cores) in result.node_ranges.iter().zip(&result.node_cores) {
+            assert!(ranges.iter().all(|&(low, high)| high - low < 50_000 * cores as u64));
+            all_ranges.extend(ranges);
+        }
+        all_ranges.sort();
+        assert_eq!(all_ranges.first().unwrap().0, 1001);
+        assert_eq!(all_ranges.last().unwrap().1, 1_000_000);
+        assert!(all_ranges.windows(2).all(|pair| pair[0].1 + 1 == pair[1].0));
+    }
+
+    #[test]
+    fn test_master_recovers_from_worker_that_disconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "2",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--master-addr",
+            &addr,
+        ]);
+
+        // Takes one chunk, then drops the connection without answering
+        let flaky_addr = addr.clone();
+        let flaky = thread::spawn(move || {
+            let mut stream = TcpStream::connect(flaky_addr).unwrap();
+            write_message(
+                &mut stream,
+                &Message::Hello {
+                    cores: 1,
+                    token: String::new(),
+                },
+            )
+            .unwrap();
+            read_message(&mut stream).unwrap();
+        });
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        let healthy = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        flaky.join().unwrap();
+        healthy.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.failed_workers, 1);
+        assert_eq!(result.chunk_counts.iter().sum::<usize>(), 20);
+    }
+
+    #[test]
+    fn test_master_rejects_corrupted_results() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--master-addr",
+            &addr,
+        ]);
+
+        // Answers every chunk with a frame whose payload was damaged in transit
+        let corrupt = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write_message(
+                &mut stream,
+                &Message::Hello {
+                    cores: 1,
+                    token: String::new(),
+                },
+            )
+            .unwrap();
+            while let Ok(Message::Work { low, high, .. }) = read_message(&mut stream) {
+                let mut frame = Vec::new();
+                write_message(&mut frame, &Message::Result { low, high, count: 0 }).unwrap();
+                frame[10] ^= 0xff;
+                if stream.write_all(&frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        corrupt.join().unwrap();
+
+        // None of the bad counts were accepted; the master sieved everything
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.failed_workers, 1);
+        assert_eq!(result.chunk_counts, vec![20, 0]);
+    }
+
+    #[test]
+    fn test_token_mismatch_is_rejected() {
+        assert!(token_accepted(None, "anything"));
+        assert!(token_accepted(Some("s3cret"), "s3cret"));
+        assert!(!token_accepted(Some("s3cret"), "s3creT"));
+        assert!(!token_accepted(Some("s3cret"), ""));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "2",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--token",
+            "s3cret",
+            "--master-addr",
+            &addr,
+        ]);
+
+        let intruder_args = Args {
+            worker: true,
+            token: Some("guess".to_string()),
+            ..args.clone()
+        };
+        let intruder = thread::spawn(move || run_worker(&intruder_args));
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+        let err = intruder.join().unwrap().unwrap_err();
+
+        assert!(err.contains("invalid token"), "{}", err);
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.failed_workers, 1);
+    }
+
+    #[test]
+    fn test_tls_round_trip_with_self_signed_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::write(&cert_path, cert.pem()).unwrap();
+        std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--tls",
+            "--master-addr",
+            &addr,
+        ]);
+        let master_args = Args {
+            cert: Some(cert_path.clone()),
+            key: Some(key_path),
+            ..args.clone()
+        };
+        let worker_args = Args {
+            worker: true,
+            ca: Some(cert_path),
+            ..args.clone()
+        };
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let result = run_master_on(listener, &master_args).unwrap().remove(0);
+        worker.join().unwrap().unwrap();
+
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.failed_workers, 0);
+        assert!(result.chunk_counts[1] > 0, "worker did no work over TLS");
+    }
+
+    #[test]
+    fn test_worker_handles_consecutive_jobs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let args = Args::parse_from(["primes-mpi", "--worker", "--cores", "1", "--master-addr", &addr]);
+        let worker = thread::spawn(move || run_worker(&args));
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream: Channel = Box::new(stream);
+        assert!(matches!(read_message(&mut stream).unwrap(), Message::Hello { cores: 1, .. }));
+
+        // Two jobs from different runs on the same connection
+        assert_eq!(exchange_chunk(&mut stream, 32, 1_000, 1_000, None, None).unwrap(), 168 - 11);
+        assert_eq!(
+            exchange_chunk(&mut stream, 1_001, 1_000_000, 1_000_000, None, None).unwrap(),
+            78_498 - 168
+        );
+
+        write_message(&mut stream, &Message::Shutdown).unwrap();
+        worker.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_stream_reassembles_primes_in_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "2",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--stream",
+            "--master-addr",
+            &addr,
+        ]);
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let args = Args {
+                    worker: true,
+                    ..args.clone()
+                };
+                thread::spawn(move || run_worker(&args))
+            })
+            .collect();
+
+        let result = run_master_on(listener, &args).unwrap().remove(0);
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+
+        assert_eq!(result.total_primes, 78_498);
+        assert_eq!(result.primes.unwrap(), simple_sieve(1_000_000).unwrap());
+    }
+
+    #[test]
+    fn test_streamed_primes_must_stay_in_chunk() {
+        assert!(continues_chunk(&[], &[11, 13], 10, 20));
+        assert!(continues_chunk(&[11, 13], &[17, 19], 10, 20));
+        assert!(!continues_chunk(&[11, 13], &[13], 10, 20));
+        assert!(!continues_chunk(&[], &[7], 10, 20));
+        assert!(!continues_chunk(&[], &[23], 10, 20));
+    }
+
+    #[test]
+    fn test_master_times_out_waiting_for_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--limit",
+            "1000000",
+            "--workers",
+            "2",
+            "--connect-timeout",
+            "1",
+            "--master-addr",
+            &addr,
+        ]);
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        // Only one of the two expected workers shows up
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let err = run_master_on(listener, &args).unwrap_err();
+        assert!(err.contains("1 of 2 connected"), "{}", err);
+
+        // The worker that did connect was told to shut down, not left hanging
+        worker.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_sweep_reuses_worker_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let args = Args::parse_from([
+            "primes-mpi",
+            "--tcp",
+            "--workers",
+            "1",
+            "--chunk-size",
+            "50000",
+            "--cores",
+            "1",
+            "--sweep",
+            "1000,1000000,100000",
+            "--master-addr",
+            &addr,
+        ]);
+        let worker_args = Args {
+            worker: true,
+            ..args.clone()
+        };
+        // A worker without --serve exits after one session
+        let worker = thread::spawn(move || run_worker(&worker_args));
+
+        let results = run_master_on(listener, &args).unwrap();
+        worker.join().unwrap().unwrap();
+
+        let totals: Vec<usize> = results.iter().map(|result| result.total_primes).collect();
+        assert_eq!(totals, vec![168, 78_498, 9_592]);
+        assert!(results.iter().all(|result| result.failed_workers == 0));
+    }
+}
@@ -0,0 +1,110 @@
+//! Optional TLS for the TCP master/worker channel (`--tls`)
+//!
+//! The framed protocol in [`crate::protocol`] only needs something it can
+//! read from and write to, so both a plain [`TcpStream`] and a rustls
+//! stream on top of one are handed around as a boxed [`Transport`]. The
+//! master presents the certificate chain from `--cert` with the key from
+//! `--key`; a worker trusts exactly the certificates in `--ca`.
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A byte stream the framed protocol can run over
+pub trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+/// Connection to a peer, plain or encrypted
+pub type Channel = Box<dyn Transport>;
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+/// TLS settings for the master, from `--cert` and `--key`
+pub fn server_config(cert: Option<&Path>, key: Option<&Path>) -> Result<Arc<ServerConfig>, String> {
+    let (Some(cert), Some(key)) = (cert, key) else {
+        return Err("--tls on the master needs --cert and --key".to_string());
+    };
+
+    let chain = load_certs(cert)?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| format!("Failed to read private key from {}: {}", key.display(), e))?;
+
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(|e| format!("Invalid certificate or key: {}", e))?;
+    Ok(Arc::new(config))
+}
+
+/// TLS settings for a worker, trusting only the certificates in `--ca`
+pub fn client_config(ca: Option<&Path>) -> Result<Arc<ClientConfig>, String> {
+    let ca = ca.ok_or("--tls on a worker needs --ca")?;
+
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca)? {
+        roots
+            .add(cert)
+            .map_err(|e| format!("Invalid CA certificate in {}: {}", ca.display(), e))?;
+    }
+
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Wrap an accepted connection; the handshake runs on the first read
+pub fn accept(stream: TcpStream, config: &Arc<ServerConfig>) -> Result<Channel, String> {
+    let connection = ServerConnection::new(Arc::clone(config)).map_err(|e| e.to_string())?;
+    Ok(Box::new(StreamOwned::new(connection, stream)))
+}
+
+/// Wrap a connection to the master at `addr` (`host:port`), checking its
+/// certificate against `host`
+pub fn connect(stream: TcpStream, config: &Arc<ClientConfig>, addr: &str) -> Result<Channel, String> {
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let server_name =
+        ServerName::try_from(host.to_string()).map_err(|e| format!("Invalid server name {:?}: {}", host, e))?;
+
+    let connection = ClientConnection::new(Arc::clone(config), server_name).map_err(|e| e.to_string())?;
+    Ok(Box::new(StreamOwned::new(connection, stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_paths_are_reported() {
+        let err = server_config(Some(Path::new("cert.pem")), None).unwrap_err();
+        assert!(err.contains("--key"), "{}", err);
+        assert!(client_config(None).unwrap_err().contains("--ca"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.pem");
+        std::fs::write(&empty, "").unwrap();
+        assert!(client_config(Some(&empty)).unwrap_err().contains("No certificates"));
+    }
+}
@@ -0,0 +1,113 @@
+//! End-to-end checks of the `primes-mpi` binary
+
+use assert_cmd::Command;
+
+/// Whether `text` contains any Unicode box-drawing character (U+2500-U+257F)
+fn has_box_drawing(text: &str) -> bool {
+    text.chars().any(|c| ('\u{2500}'..='\u{257F}').contains(&c))
+}
+
+#[test]
+fn test_quiet_prints_one_plain_line() {
+    let output = Command::cargo_bin("primes-mpi")
+        .unwrap()
+        .args(["--limit", "1000", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!has_box_drawing(&stdout), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].starts_with("primes=168 time=") && lines[0].ends_with("ms"), "{}", stdout);
+}
+
+#[test]
+fn test_invalid_arguments_exit_4() {
+    let output = Command::cargo_bin("primes-mpi").unwrap().arg("--no-such-flag").output().unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let output = Command::cargo_bin("primes-mpi").unwrap().arg("--help").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exit codes:"));
+}
+
+#[test]
+fn test_master_connect_timeout_exits_3() {
+    // Reserve a free port, then release it for the master
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+    let output = Command::cargo_bin("primes-mpi")
+        .unwrap()
+        .args(["--tcp", "--workers", "1", "--connect-timeout", "1", "--master-addr", &addr])
+        .timeout(std::time::Duration::from_secs(30))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("0 of 1 connected"), "{}", stderr);
+}
+
+#[test]
+fn test_dry_run_segments_tile_the_range() {
+    let output = Command::cargo_bin("primes-mpi")
+        .unwrap()
+        .args(["--dry-run", "--tcp", "--workers", "3", "--cores", "2", "--limit", "1000000"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Base primes: 168 (up to 1000)"), "{}", stdout);
+
+    // One "[low, high]" line per node, ascending and back to back
+    let segments: Vec<(u64, u64)> = stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('[')?.split_once(']'))
+        .map(|(range, _)| {
+            let (low, high) = range.split_once(", ").unwrap();
+            (low.parse().unwrap(), high.parse().unwrap())
+        })
+        .collect();
+    assert_eq!(segments.len(), 4, "{}", stdout);
+    assert_eq!(segments.first().unwrap().0, 1001);
+    assert_eq!(segments.last().unwrap().1, 1_000_000);
+    assert!(segments.windows(2).all(|pair| pair[0].1 + 1 == pair[1].0), "{:?}", segments);
+}
+
+#[test]
+fn test_verify_prints_pass() {
+    let output = Command::cargo_bin("primes-mpi")
+        .unwrap()
+        .args(["--limit", "100000", "--verify"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Verify: PASS (π(100000) = 9592)"), "{}", stdout);
+}
+
+#[test]
+fn test_csv_header_unless_no_header() {
+    let run = |extra: &[&str]| {
+        let output = Command::cargo_bin("primes-mpi")
+            .unwrap()
+            .args(["--limit", "1000", "--csv"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&[]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert_eq!(lines[0], "limit,nodes,time_ms,prime_count,base_ms,sieve_ms");
+
+    // Just the data row, which starts like the one after the header
+    let bare = run(&["--no-header"]);
+    assert_eq!(bare.lines().count(), 1, "{}", bare);
+    assert_eq!(bare.split(',').next(), lines[1].split(',').next());
+}
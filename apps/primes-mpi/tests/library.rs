@@ -0,0 +1,59 @@
+//! The `primes_mpi` library called directly, without the binary
+
+use clap::Parser;
+use primes_mpi::tcp_impl::{run_master_on, run_worker};
+use primes_mpi::{run_single_node, verify_result, Args};
+use std::net::TcpListener;
+use std::thread;
+
+#[test]
+fn test_single_node_returns_result() {
+    let args = Args::parse_from(["primes-mpi", "--limit", "10000", "--gather-primes"]);
+    let result = run_single_node(&args).unwrap();
+
+    assert_eq!(result.total_primes, 1229);
+    assert_eq!((result.nodes, result.failed_workers), (1, 0));
+    assert_eq!(result.node_counts, [1229]);
+    assert_eq!(result.node_ranges, [vec![(2, 10_000)]]);
+    assert_eq!(result.primes.as_ref().map(|primes| primes[..4].to_vec()), Some(vec![2, 3, 5, 7]));
+    assert!(verify_result(&result, 10_000).unwrap().passed());
+}
+
+#[test]
+fn test_tcp_master_returns_per_node_result() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let args = Args::parse_from([
+        "primes-mpi",
+        "--tcp",
+        "--limit",
+        "100000",
+        "--workers",
+        "1",
+        "--chunk-size",
+        "10000",
+        "--master-addr",
+        &addr,
+    ]);
+    let worker_args = Args {
+        worker: true,
+        ..args.clone()
+    };
+    let worker = thread::spawn(move || run_worker(&worker_args));
+
+    let results = run_master_on(listener, &args).unwrap();
+    worker.join().unwrap().unwrap();
+
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result.total_primes, 9592);
+    assert_eq!(result.nodes, 2);
+    assert_eq!(result.base_prime_count + result.node_counts.iter().sum::<usize>(), 9592);
+    assert_eq!(result.chunk_counts.iter().sum::<usize>(), result.node_ranges.iter().map(Vec::len).sum());
+
+    // The two phases make up the round, less a little bookkeeping
+    assert!(result.base_ms >= 0.0 && result.sieve_ms > 0.0, "{:?}", result);
+    let phases_ms = result.base_ms + result.sieve_ms;
+    assert!(phases_ms <= result.time_ms, "{:?}", result);
+    assert!(result.time_ms - phases_ms <= result.time_ms / 10.0 + 5.0, "{:?}", result);
+}
@@ -6,409 +6,1047 @@
 //! # Parallelization Strategy
 //!
 //! 1. Calculate "base primes" (primes up to √limit) sequentially
-//! 2. Divide the remaining range into segments, one per thread
+//! 2. Divide the remaining range into segments, one per thread (or, with
+//!    `--segments N`, into N segments the threads take from a shared queue)
 //! 3. Each thread uses the base primes to sieve its segment
 //! 4. Collect and merge results from all threads
+//!
+//! With `--rayon`, step 2 instead cuts the range into many small chunks
+//! that rayon's work-stealing scheduler balances across the threads.
+
+mod self_check;
 
-use clap::Parser;
-use std::sync::{Arc, Mutex};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use primes_core::{
+    clock_seed, l2_cache_bytes, peak_rss_mb, physical_cores, prime_count_fast, read_bin_delta, resolve_threads, sample_indices,
+    segmented_count_parallel_cached_with, segmented_count_parallel_with, segmented_sieve_parallel_cached_with, segmented_sieve_parallel_with,
+    segmented_sieve_rayon, write_primes_file, BasePrimeCache, OutputFormat, ParallelOptions, SieveError, SieveProgress, ThreadMetrics,
+    DEFAULT_MAX_SEGMENT_BYTES, FAST_COUNT_THRESHOLD, MAX_FAST_COUNT_LIMIT, RAYON_CHUNK_SIZE,
+};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// How the results are printed (`--format`)
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum ReportFormat {
+    /// Boxed summary with per-thread metrics
+    Human,
+    /// One line: limit,threads,time_ms,prime_count[,speedup,efficiency],thread_ms,base_ms,sieve_ms
+    Csv,
+    /// One JSON object
+    Json,
+    /// Whitespace-aligned table: a row per segment, plus base and total rows
+    Table,
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportFormat::Human => write!(f, "human"),
+            ReportFormat::Csv => write!(f, "csv"),
+            ReportFormat::Json => write!(f, "json"),
+            ReportFormat::Table => write!(f, "table"),
+        }
+    }
+}
 
 /// Multithreaded prime number calculator using Segmented Sieve
 #[derive(Parser, Debug)]
 #[command(name = "primes-multithread")]
 #[command(about = "Calculate prime numbers using multiple threads", long_about = None)]
 struct Args {
-    /// Upper limit of the range to search for primes (inclusive)
-    #[arg(short, long, default_value_t = 10_000_000)]
+    /// Upper limit of the range to search for primes (inclusive), e.g. 10000000, 10M or 1e7; the flag overrides PRIMES_LIMIT
+    #[arg(short, long, env = "PRIMES_LIMIT", default_value_t = 10_000_000, value_parser = primes_core::parse_limit)]
     limit: u64,
 
-    /// Number of threads to use
-    #[arg(short, long, default_value_t = 4)]
-    threads: usize,
+    /// Number of threads to use (default: available parallelism); the flag overrides PRIMES_THREADS
+    #[arg(short, long, env = "PRIMES_THREADS")]
+    threads: Option<usize>,
 
     /// Show the list of primes found (warning: can be very long)
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
 
-    /// Output results in CSV format for benchmarking
+    /// With --verbose, list only every N-th prime (plus the last)
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), requires = "verbose")]
+    sample: usize,
+
+    /// Output format; the flag overrides PRIMES_FORMAT
+    #[arg(long, env = "PRIMES_FORMAT", default_value_t = ReportFormat::Human)]
+    format: ReportFormat,
+
+    /// Deprecated: use --format csv
     #[arg(long, default_value_t = false)]
     csv: bool,
+
+    /// Deprecated: use --format json
+    #[arg(long, default_value_t = false, conflicts_with = "csv")]
+    json: bool,
+
+    /// Leave out the CSV header line, e.g. when appending to an existing file
+    #[arg(long, default_value_t = false)]
+    no_header: bool,
+
+    /// Skip the banners; with --format human print one `primes=N time=Xms` line
+    #[arg(short, long, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Write the primes found to this file instead of the terminal
+    #[arg(short, long, conflicts_with = "count_only")]
+    output: Option<PathBuf>,
+
+    /// Format of the --output file: lines, comma, binary (little-endian u64), or bin-delta (varint gaps)
+    #[arg(long, default_value_t = OutputFormat::Lines, requires = "output")]
+    output_format: OutputFormat,
+
+    /// Only count the primes (π(limit)) without storing them; above 10^9 by Legendre's formula, without sieving
+    #[arg(long, default_value_t = false, conflicts_with = "verbose")]
+    count_only: bool,
+
+    /// Load-balance small chunks with rayon instead of one segment per thread
+    #[arg(long, default_value_t = false, conflicts_with = "count_only")]
+    rayon: bool,
+
+    /// Sequential run time in ms, used to report speedup and efficiency
+    #[arg(long, value_name = "MS")]
+    baseline_ms: Option<f64>,
+
+    /// Split the range into N segments handed out to the threads from a shared queue (default: one per thread)
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with = "rayon")]
+    segments: Option<usize>,
+
+    /// Largest sieve buffer per thread in bytes; bigger segments are sieved in pieces
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_SEGMENT_BYTES)]
+    max_segment: u64,
+
+    /// Load the base primes (up to √limit) from a file written by --dump-seed-primes instead of sieving them
+    #[arg(long, value_name = "PATH", conflicts_with = "rayon")]
+    seed_primes_file: Option<PathBuf>,
+
+    /// Write the base primes for --limit to PATH (bin-delta), for a later --seed-primes-file
+    #[arg(long, value_name = "PATH")]
+    dump_seed_primes: Option<PathBuf>,
+
+    /// Cap each thread's sieve window at the CPU's L2 cache size, so every window stays in cache
+    #[arg(long, default_value_t = false, conflicts_with = "rayon")]
+    cache_blocking: bool,
+
+    /// Report the peak memory (VmHWM) after sieving; N/A where it cannot be read
+    #[arg(long, default_value_t = false)]
+    report_mem: bool,
+
+    /// Print percent complete to stderr while sieving (human and table formats only)
+    #[arg(long, default_value_t = false, conflicts_with = "rayon")]
+    progress: bool,
+
+    /// Sieve once per thread count, e.g. 1,2,4,8, and report each time and its speedup over the first; not with --threads
+    #[arg(long, value_name = "N,...", value_delimiter = ',', value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with_all = ["output", "verbose", "progress", "baseline_ms", "report_mem"])]
+    threads_list: Vec<usize>,
+
+    /// After sieving, re-test a random sample of each segment's primes with Miller-Rabin and fail on any composite
+    #[arg(long, default_value_t = false, conflicts_with_all = ["count_only", "threads_list"])]
+    self_check: bool,
 }
 
-/// Simple Sieve for finding base primes (primes up to sqrt(limit))
+/// How often the progress monitor redraws its line
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Parse the command line, rejecting a legacy --csv/--json given next to
+/// --format, or --threads next to --threads-list
 ///
-/// This is used to find the "seed" primes that will be used by all
-/// threads to sieve their respective segments.
-fn simple_sieve(limit: u64) -> Vec<u64> {
-    if limit < 2 {
-        return vec![];
+/// This is checked here rather than with `conflicts_with` because clap
+/// counts a PRIMES_FORMAT (or PRIMES_THREADS) value as the flag, and a
+/// flag on the command line must still win over the environment.
+fn parse_args_from<I, T>(itr: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut command = Args::command();
+    let matches = command.try_get_matches_from_mut(itr)?;
+    let args = Args::from_arg_matches(&matches)?;
+
+    if (args.csv || args.json) && matches.value_source("format") == Some(ValueSource::CommandLine) {
+        let legacy = if args.csv { "--csv" } else { "--json" };
+        return Err(command.error(
+            ErrorKind::ArgumentConflict,
+            format!("the argument '{}' cannot be used with '--format <FORMAT>'", legacy),
+        ));
+    }
+    if !args.threads_list.is_empty() && matches.value_source("threads") == Some(ValueSource::CommandLine) {
+        return Err(command.error(
+            ErrorKind::ArgumentConflict,
+            "the argument '--threads <THREADS>' cannot be used with '--threads-list <N,...>'",
+        ));
     }
+    Ok(args)
+}
 
-    let mut is_prime = vec![true; (limit + 1) as usize];
-    is_prime[0] = false;
-    is_prime[1] = false;
+/// The output format, mapping the deprecated `--csv`/`--json` onto `--format`
+fn report_format(args: &Args) -> ReportFormat {
+    if args.csv {
+        ReportFormat::Csv
+    } else if args.json {
+        ReportFormat::Json
+    } else {
+        args.format
+    }
+}
 
-    let sqrt_limit = (limit as f64).sqrt() as u64;
+struct PrimeStatistics {
+    count: usize,
+    largest: u64,
+    density: f64,
+}
 
-    for num in 2..=sqrt_limit {
-        if is_prime[num as usize] {
-            let mut multiple = num * num;
-            while multiple <= limit {
-                is_prime[multiple as usize] = false;
-                multiple += num;
-            }
-        }
+fn calculate_statistics(primes: &[u64], limit: u64) -> PrimeStatistics {
+    PrimeStatistics {
+        largest: primes.last().copied().unwrap_or(0),
+        ..count_statistics(primes.len(), limit)
     }
+}
 
-    is_prime
-        .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| idx as u64)
-        .collect()
+/// Statistics that only need the prime count (used by `--count-only`)
+fn count_statistics(count: usize, limit: u64) -> PrimeStatistics {
+    PrimeStatistics {
+        count,
+        largest: 0,
+        density: if limit > 0 {
+            count as f64 / limit as f64
+        } else {
+            0.0
+        },
+    }
 }
 
-/// Sieve a segment of numbers using pre-computed base primes
-///
-/// # Algorithm
-///
-/// For each base prime p, we need to mark all multiples of p in our segment.
-/// The first multiple of p in range [low, high] is:
-///   - If low <= p*p: start at p*p
-///   - Otherwise: start at the smallest multiple of p >= low
-///
-/// # Arguments
-///
-/// * `low` - Start of the segment (inclusive)
-/// * `high` - End of the segment (inclusive)
-/// * `base_primes` - Pre-computed primes up to sqrt(limit)
+/// Parallel speedup and efficiency relative to a sequential baseline
 ///
-/// # Returns
-///
-/// Vector of primes found in the segment [low, high]
-fn sieve_segment(low: u64, high: u64, base_primes: &[u64]) -> Vec<u64> {
-    // Handle edge case where segment is invalid
-    if low > high {
-        return vec![];
-    }
+/// speedup = baseline / parallel, efficiency = speedup / threads
+fn speedup_efficiency(baseline_ms: f64, parallel_ms: f64, threads: usize) -> (f64, f64) {
+    let speedup = if parallel_ms > 0.0 {
+        baseline_ms / parallel_ms
+    } else {
+        0.0
+    };
+    (speedup, speedup / threads.max(1) as f64)
+}
 
-    let segment_size = (high - low + 1) as usize;
+/// One-line progress report, e.g. `Progress:  40.0% (2/5 chunks)`
+fn progress_line(done: u64, total: u64) -> String {
+    let percent = if total > 0 {
+        done as f64 / total as f64 * 100.0
+    } else {
+        100.0
+    };
+    format!("Progress: {:>5.1}% ({}/{} chunks)", percent, done, total)
+}
 
-    // Create a local sieve for this segment
-    // Index i represents number (low + i)
-    let mut is_prime = vec![true; segment_size];
+/// Whether `--count-only` skips the threads and uses Legendre's formula
+fn counts_fast(args: &Args) -> bool {
+    args.count_only && args.limit > FAST_COUNT_THRESHOLD
+}
 
-    // Mark 0 and 1 as non-prime if they fall within our segment
-    if low == 0 && segment_size > 0 {
-        is_prime[0] = false;
-    }
-    if low <= 1 && high >= 1 {
-        is_prime[(1 - low) as usize] = false;
+/// π(limit) by Legendre's formula, for limits up to [`MAX_FAST_COUNT_LIMIT`]
+fn count_fast(limit: u64) -> Result<usize, SieveError> {
+    if limit > MAX_FAST_COUNT_LIMIT {
+        return Err(SieveError::LimitTooLarge {
+            requested: limit,
+            max_supported: MAX_FAST_COUNT_LIMIT,
+        });
     }
+    Ok(prime_count_fast(limit) as usize)
+}
 
-    // For each base prime, mark its multiples in our segment
-    for &prime in base_primes {
-        // Skip if prime^2 is beyond our segment
-        if prime * prime > high {
-            continue;
+/// Redraw the progress line on stderr until `finished` is set
+fn monitor_progress(progress: &SieveProgress, finished: &AtomicBool) {
+    let mut last_draw = Instant::now();
+    while !finished.load(Ordering::Relaxed) {
+        // Poll often so the monitor stops promptly once the sieve is done
+        thread::sleep(Duration::from_millis(50));
+        if last_draw.elapsed() >= PROGRESS_INTERVAL {
+            eprint!("\r{}", progress_line(progress.chunks_done(), progress.total_chunks()));
+            last_draw = Instant::now();
         }
+    }
+    eprintln!("\r{}", progress_line(progress.chunks_done(), progress.total_chunks()));
+}
 
-        // Find the first multiple of prime in our segment
-        // We want the smallest k such that k >= low and k % prime == 0
-        let start = if low <= prime * prime {
-            // If our segment includes prime^2, start there
-            prime * prime
-        } else {
-            // Find the first multiple of prime >= low
-            // Formula: ((low + prime - 1) / prime) * prime
-            // This rounds up low to the nearest multiple of prime
-            let remainder = low % prime;
-            if remainder == 0 {
-                low
-            } else {
-                low + (prime - remainder)
-            }
-        };
+/// Result object emitted by `--json`
+#[derive(Serialize)]
+struct JsonReport {
+    limit: u64,
+    threads: usize,
+    time_ms: f64,
+    /// Finding the base primes up to √limit (0 when one thread sieves inline)
+    base_ms: f64,
+    /// Sieving the segments and merging them
+    sieve_ms: f64,
+    prime_count: usize,
+    /// `null` in `--count-only` mode, where no primes are collected
+    largest: Option<u64>,
+    density: f64,
+    /// Only present when `--baseline-ms` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speedup: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    efficiency: Option<f64>,
+    segments: Vec<JsonSegment>,
+    /// Only present with `--report-mem`, and only where it can be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak_rss_mb: Option<f64>,
+}
 
-        // Mark all multiples of prime in our segment as composite
-        let mut multiple = start;
-        while multiple <= high {
-            // Convert global index to local segment index
-            let local_idx = (multiple - low) as usize;
-            is_prime[local_idx] = false;
-            multiple += prime;
-        }
-    }
+/// One entry of the `segments` array (a thread's range, or a rayon chunk)
+#[derive(Serialize)]
+struct JsonSegment {
+    low: u64,
+    high: u64,
+    prime_count: usize,
+    time_ms: f64,
+}
 
-    // Collect primes from this segment
-    is_prime
+/// CSV header; the speedup columns only appear with `--baseline-ms`
+fn csv_header(args: &Args) -> String {
+    let scaling = if args.baseline_ms.is_some() {
+        ",speedup,efficiency"
+    } else {
+        ""
+    };
+    let mem = if args.report_mem { ",peak_rss_mb" } else { "" };
+    format!("limit,threads,time_ms,prime_count{},thread_ms,base_ms,sieve_ms{}", scaling, mem)
+}
+
+/// Per-thread sieve times for the last CSV column, `;`-separated in range order
+fn thread_times_csv(metrics: &ThreadMetrics) -> String {
+    metrics
+        .segments
         .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| low + idx as u64)
-        // Filter out base primes (they're handled separately)
-        .filter(|&n| n > 1)
-        .collect()
+        .map(|&(.., time_ms)| format!("{:.3}", time_ms))
+        .collect::<Vec<_>>()
+        .join(";")
 }
 
-/// Segmented Sieve of Eratosthenes - Multithreaded Implementation
-///
-/// # Parallelization Strategy
-///
-/// ```text
-/// Range: [2, limit]
-///
-/// Step 1: Calculate base primes [2, √limit] sequentially
-///         These are needed by all threads
-///
-/// Step 2: Divide remaining range into segments
-///         Thread 0: [√limit + 1, segment_end_0]
-///         Thread 1: [segment_end_0 + 1, segment_end_1]
-///         ...
-///
-/// Step 3: Each thread sieves its segment independently
-///         (No synchronization needed during sieving!)
+/// Everything a finished run reports
+struct RunResult {
+    num_threads: usize,
+    primes: Vec<u64>,
+    stats: PrimeStatistics,
+    metrics: ThreadMetrics,
+    time_ms: f64,
+    /// Peak RSS in MiB, with `--report-mem` where it can be read
+    peak_rss_mb: Option<f64>,
+    /// Numbers re-tested by `--self-check`
+    self_checked: Option<usize>,
+}
+
+/// Render the results of a run as a single-line JSON object
+fn json_report(
+    args: &Args,
+    num_threads: usize,
+    stats: &PrimeStatistics,
+    metrics: &ThreadMetrics,
+    time_ms: f64,
+    peak_rss_mb: Option<f64>,
+) -> String {
+    let scaling = args
+        .baseline_ms
+        .map(|baseline_ms| speedup_efficiency(baseline_ms, time_ms, num_threads));
+    let report = JsonReport {
+        limit: args.limit,
+        threads: num_threads,
+        time_ms,
+        base_ms: metrics.base_ms,
+        sieve_ms: metrics.sieve_ms,
+        prime_count: stats.count,
+        largest: (!args.count_only).then_some(stats.largest),
+        density: stats.density,
+        speedup: scaling.map(|(speedup, _)| speedup),
+        efficiency: scaling.map(|(_, efficiency)| efficiency),
+        segments: metrics
+            .segments
+            .iter()
+            .map(|&(low, high, prime_count, time_ms)| JsonSegment {
+                low,
+                high,
+                prime_count,
+                time_ms,
+            })
+            .collect(),
+        peak_rss_mb,
+    };
+    serde_json::to_string(&report).expect("JSON report serialization cannot fail")
+}
+
+/// Render the per-segment table for `--format table`
 ///
-/// Step 4: Collect and merge results
-/// ```
-fn segmented_sieve_parallel(limit: u64, num_threads: usize) -> (Vec<u64>, ThreadMetrics) {
-    if limit < 2 {
-        return (vec![], ThreadMetrics::default());
+/// Rows tile [2, limit]: the base primes up to √limit, then each thread's
+/// segment (or each `--segments` chunk, or rayon chunk), then the whole run.
+/// The base row's time is how long finding the base primes took.
+/// `label` heads the column numbering those rows.
+fn table_report(result: &RunResult, limit: u64, label: &str) -> String {
+    let segments = &result.metrics.segments;
+    let segment_primes: usize = segments.iter().map(|&(_, _, count, _)| count).sum();
+    let base_high = segments.first().map_or(limit, |&(low, ..)| low - 1);
+
+    let mut table = format!(
+        "{:>6} {:>12} {:>12} {:>10} {:>12}\n",
+        label, "low", "high", "primes", "time_ms"
+    );
+    table += &format!(
+        "{:>6} {:>12} {:>12} {:>10} {:>12}\n",
+        "base",
+        2,
+        base_high,
+        result.stats.count - segment_primes,
+        format!("{:.3}", result.metrics.base_ms)
+    );
+    for (i, &(low, high, count, time_ms)) in segments.iter().enumerate() {
+        table += &format!("{:>6} {:>12} {:>12} {:>10} {:>12.3}\n", i, low, high, count, time_ms);
     }
+    table += &format!(
+        "{:>6} {:>12} {:>12} {:>10} {:>12.3}",
+        "total", 2, limit, result.stats.count, result.time_ms
+    );
+    table
+}
 
-    let sqrt_limit = (limit as f64).sqrt() as u64;
+/// One run of a `--threads-list` sweep
+#[derive(Debug, Serialize)]
+struct SweepRow {
+    threads: usize,
+    time_ms: f64,
+    /// Time of the first run divided by this one's
+    speedup: f64,
+}
 
-    // Step 1: Find base primes (sequential)
-    // These are all primes up to sqrt(limit)
-    let base_primes = simple_sieve(sqrt_limit);
-
-    // If limit is small, base primes might be all we need
-    if sqrt_limit >= limit {
-        return (
-            base_primes,
-            ThreadMetrics {
-                segments: vec![],
-            },
-        );
-    }
+/// Result object emitted by `--threads-list` with `--format json`
+#[derive(Serialize)]
+struct SweepJsonReport<'a> {
+    limit: u64,
+    prime_count: usize,
+    runs: &'a [SweepRow],
+}
 
-    // Step 2: Divide the range (sqrt_limit + 1, limit] among threads
-    let range_start = sqrt_limit + 1;
-    let range_size = limit - sqrt_limit;
-    let segment_size = (range_size + num_threads as u64 - 1) / num_threads as u64;
+/// Sieve at each of `threads_list` in turn, timing every run
+///
+/// The first run's primes (or count) are kept and every later run must
+/// find exactly the same, so a thread count that loses or duplicates a
+/// segment fails the sweep instead of reporting a misleading time.
+fn run_sweep(
+    args: &Args,
+    threads_list: &[usize],
+    seed_cache: &mut Option<BasePrimeCache>,
+) -> Result<(PrimeStatistics, Vec<SweepRow>), String> {
+    let options = ParallelOptions {
+        max_segment_bytes: segment_bytes(args),
+        progress: None,
+        segments: args.segments,
+    };
+    let mut reference: Option<(Vec<u64>, Option<usize>)> = None;
+    let mut rows: Vec<SweepRow> = Vec::with_capacity(threads_list.len());
+    for &threads in threads_list {
+        let start_time = Instant::now();
+        let (primes, count, _) = sieve(args, threads, &options, seed_cache).map_err(|e| e.to_string())?;
+        let time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
 
-    // Shared storage for results from each thread
-    // Using Arc<Mutex<Vec>> for thread-safe collection
-    let results: Arc<Mutex<Vec<Vec<u64>>>> = Arc::new(Mutex::new(vec![vec![]; num_threads]));
+        let found = (primes, count);
+        match &reference {
+            Some(expected) if *expected != found => {
+                return Err(format!(
+                    "the run on {} threads found {} primes, which does not match the {} found on {} threads",
+                    threads,
+                    found.1.unwrap_or(found.0.len()),
+                    expected.1.unwrap_or(expected.0.len()),
+                    threads_list[0]
+                ));
+            }
+            Some(_) => {}
+            None => reference = Some(found),
+        }
+
+        let first_ms = rows.first().map_or(time_ms, |first| first.time_ms);
+        rows.push(SweepRow {
+            threads,
+            time_ms,
+            speedup: speedup_efficiency(first_ms, time_ms, threads).0,
+        });
+    }
 
-    // Metrics for reporting
-    let metrics: Arc<Mutex<Vec<(u64, u64, usize)>>> = Arc::new(Mutex::new(vec![]));
+    let stats = match reference {
+        Some((_, Some(count))) => count_statistics(count, args.limit),
+        Some((primes, None)) => calculate_statistics(&primes, args.limit),
+        None => count_statistics(0, args.limit),
+    };
+    Ok((stats, rows))
+}
 
-    // Share base_primes among threads (read-only, so Arc is sufficient)
-    let base_primes = Arc::new(base_primes);
+/// Render a `--threads-list` sweep as an aligned table, one row per run
+fn sweep_table(rows: &[SweepRow]) -> String {
+    let mut table = format!("{:>8} {:>12} {:>9}", "threads", "time_ms", "speedup");
+    for row in rows {
+        table += &format!("\n{:>8} {:>12.3} {:>9.3}", row.threads, row.time_ms, row.speedup);
+    }
+    table
+}
 
-    // Step 3: Spawn threads
-    let mut handles = vec![];
+/// Print a `--threads-list` sweep in `format`
+fn emit_sweep(stats: &PrimeStatistics, rows: &[SweepRow], args: &Args, format: ReportFormat) {
+    match format {
+        ReportFormat::Csv => {
+            if !args.no_header {
+                println!("limit,threads,time_ms,prime_count,speedup");
+            }
+            for row in rows {
+                println!("{},{},{:.3},{},{:.3}", args.limit, row.threads, row.time_ms, stats.count, row.speedup);
+            }
+        }
+        ReportFormat::Json => {
+            let report = SweepJsonReport {
+                limit: args.limit,
+                prime_count: stats.count,
+                runs: rows,
+            };
+            println!("{}", serde_json::to_string(&report).expect("JSON report serialization cannot fail"));
+        }
+        ReportFormat::Table => println!("{}", sweep_table(rows)),
+        ReportFormat::Human if args.quiet => {
+            for row in rows {
+                println!("threads={} time={:.3}ms speedup={:.3}", row.threads, row.time_ms, row.speedup);
+            }
+        }
+        ReportFormat::Human => {
+            println!("═══════════════════════════════════════════════════════════");
+            println!("                  THREAD COUNT SWEEP");
+            println!("═══════════════════════════════════════════════════════════");
+            println!("  Primes found:        {:>12} (same on every run)", stats.count);
+            println!("───────────────────────────────────────────────────────────");
+            for line in sweep_table(rows).lines() {
+                println!("  {}", line);
+            }
+            println!("═══════════════════════════════════════════════════════════");
+        }
+    }
+}
 
-    for thread_id in 0..num_threads {
-        // Calculate this thread's segment boundaries
-        let seg_low = range_start + (thread_id as u64 * segment_size);
-        let seg_high = std::cmp::min(seg_low + segment_size - 1, limit);
+/// Print the results of a run in `format`
+fn emit(result: &RunResult, args: &Args, format: ReportFormat) {
+    let RunResult { primes, stats, metrics, .. } = result;
+    let (num_threads, time_ms) = (result.num_threads, result.time_ms);
+    let scaling = args
+        .baseline_ms
+        .map(|baseline_ms| speedup_efficiency(baseline_ms, time_ms, num_threads));
 
-        // Skip if this thread has no work (can happen with few numbers)
-        if seg_low > limit {
-            continue;
+    match format {
+        ReportFormat::Csv => {
+            if !args.no_header {
+                println!("{}", csv_header(args));
+            }
+            print!("{},{},{:.3},{}", args.limit, num_threads, time_ms, stats.count);
+            if let Some((speedup, efficiency)) = scaling {
+                print!(",{:.3},{:.3}", speedup, efficiency);
+            }
+            print!(",{},{:.3},{:.3}", thread_times_csv(metrics), metrics.base_ms, metrics.sieve_ms);
+            if args.report_mem {
+                // Left empty where the peak cannot be read
+                print!(",{}", result.peak_rss_mb.map_or(String::new(), |mb| format!("{:.1}", mb)));
+            }
+            println!();
+        }
+        ReportFormat::Json => println!(
+            "{}",
+            json_report(args, num_threads, stats, metrics, time_ms, result.peak_rss_mb)
+        ),
+        ReportFormat::Table => {
+            let label = if args.segments.is_some() { "chunk" } else { "thread" };
+            println!("{}", table_report(result, args.limit, label))
         }
+        ReportFormat::Human if args.quiet => println!("primes={} time={:.3}ms", stats.count, time_ms),
+        ReportFormat::Human => {
+            println!("═══════════════════════════════════════════════════════════");
+            println!("                      RESULTS");
+            println!("═══════════════════════════════════════════════════════════");
+            println!("  Primes found:        {:>12}", stats.count);
+            if !args.count_only {
+                println!("  Largest prime:       {:>12}", stats.largest);
+            }
+            println!("  Prime density:       {:>12.6}", stats.density);
+            println!("───────────────────────────────────────────────────────────");
+            println!("  Execution time:      {:>12.3} ms", time_ms);
+            println!("  Execution time:      {:>12.6} s", time_ms / 1000.0);
+            if !counts_fast(args) {
+                println!("    Base primes:       {:>12.3} ms", metrics.base_ms);
+                println!("    Segments:          {:>12.3} ms", metrics.sieve_ms);
+            }
+            if args.report_mem {
+                match result.peak_rss_mb {
+                    Some(mb) => println!("  Peak memory (RSS):   {:>12.1} MB", mb),
+                    None => println!("  Peak memory (RSS):   {:>12}", "N/A"),
+                }
+            }
+            if let Some((speedup, efficiency)) = scaling {
+                println!("  Speedup:             {:>12.3}x", speedup);
+                println!("  Efficiency:          {:>11.1} %", efficiency * 100.0);
+            }
+            if let Some(tested) = result.self_checked {
+                println!("  Self-check:          {:>12} primes re-tested, all prime", tested);
+            }
+            if let Some(path) = &args.output {
+                println!("  Primes written to:   {} ({})", path.display(), args.output_format);
+            }
+            println!("───────────────────────────────────────────────────────────");
+            if counts_fast(args) {
+                println!("  Counted by Legendre's formula on one thread; nothing sieved");
+            } else if args.rayon {
+                // One entry per chunk; too many to list individually
+                println!("  Chunks sieved:       {:>12}", metrics.segments.len());
+            } else {
+                let label = if args.segments.is_some() { "Chunk" } else { "Thread" };
+                println!("  {} Metrics:", label);
 
-        // Clone Arc references for this thread
-        let results = Arc::clone(&results);
-        let metrics = Arc::clone(&metrics);
-        let base_primes = Arc::clone(&base_primes);
-
-        let handle = thread::spawn(move || {
-            // Each thread sieves its segment independently
-            // No synchronization needed during computation!
-            let segment_primes = sieve_segment(seg_low, seg_high, &base_primes);
-
-            let prime_count = segment_primes.len();
-
-            // Store results (requires lock)
-            // CRITICAL SECTION: Accessing shared data
-            {
-                let mut results_guard = results.lock().unwrap();
-                results_guard[thread_id] = segment_primes;
-            } // Lock is released here
-
-            // Store metrics
-            {
-                let mut metrics_guard = metrics.lock().unwrap();
-                metrics_guard.push((seg_low, seg_high, prime_count));
+                for (i, (low, high, count, time_ms)) in metrics.segments.iter().enumerate() {
+                    println!(
+                        "    {} {}: [{:>10}, {:>10}] -> {} primes in {:.3} ms",
+                        label, i, low, high, count, time_ms
+                    );
+                }
             }
-        });
 
-        handles.push(handle);
-    }
+            println!("═══════════════════════════════════════════════════════════");
 
-    // Step 4: Wait for all threads to complete
-    for handle in handles {
-        handle.join().expect("Thread panicked");
+            if args.verbose {
+                println!("\n{}:", listing_title(args.sample));
+                for (i, prime) in sample_indices(primes.len(), args.sample).map(|i| primes[i]).enumerate() {
+                    if i > 0 && i % 10 == 0 {
+                        println!();
+                    }
+                    print!("{:>8} ", prime);
+                }
+                println!();
+            }
+        }
     }
+}
 
-    // Collect all primes in order
-    let mut all_primes = simple_sieve(sqrt_limit); // Start with base primes
-
-    // Add primes from each segment (already sorted within each segment)
-    let results_guard = results.lock().unwrap();
-    for segment_primes in results_guard.iter() {
-        all_primes.extend(segment_primes);
+/// Heading of the `--verbose` prime listing, which `--sample` thins out
+fn listing_title(sample: usize) -> String {
+    if sample > 1 {
+        format!("Prime numbers found (one in every {}, plus the last)", sample)
+    } else {
+        "Prime numbers found".to_string()
     }
-
-    // Build metrics
-    let metrics_guard = metrics.lock().unwrap();
-    let thread_metrics = ThreadMetrics {
-        segments: metrics_guard.clone(),
-    };
-
-    (all_primes, thread_metrics)
 }
 
-#[derive(Default)]
-struct ThreadMetrics {
-    segments: Vec<(u64, u64, usize)>, // (low, high, prime_count)
+/// Sieve buffer per thread: `--max-segment`, capped at L2 with `--cache-blocking`
+fn segment_bytes(args: &Args) -> u64 {
+    if args.cache_blocking {
+        args.max_segment.min(l2_cache_bytes())
+    } else {
+        args.max_segment
+    }
 }
 
-struct PrimeStatistics {
-    count: usize,
-    largest: u64,
-    density: f64,
+/// Base primes for `limit` read from a `--seed-primes-file`, checked to reach √limit
+fn load_seed_primes(path: &std::path::Path, limit: u64) -> Result<BasePrimeCache, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    let primes = read_bin_delta(&mut std::io::BufReader::new(&mut file))
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let cache = BasePrimeCache::from_primes(primes).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+    if cache.sieved_to() < sqrt_limit {
+        return Err(format!(
+            "{} only covers primes up to {}, but --limit {} needs them up to {}",
+            path.display(),
+            cache.sieved_to(),
+            limit,
+            sqrt_limit
+        ));
+    }
+    Ok(cache)
 }
 
-fn calculate_statistics(primes: &[u64], limit: u64) -> PrimeStatistics {
-    PrimeStatistics {
-        count: primes.len(),
-        largest: primes.last().copied().unwrap_or(0),
-        density: if limit > 0 {
-            primes.len() as f64 / limit as f64
-        } else {
-            0.0
-        },
+/// Sieve (or count, with `--count-only`) on `num_threads` threads
+///
+/// Returns the primes, or an empty list and the count in `--count-only`
+/// mode, along with the per-segment metrics.
+fn sieve(
+    args: &Args,
+    num_threads: usize,
+    options: &ParallelOptions,
+    seed_cache: &mut Option<BasePrimeCache>,
+) -> Result<(Vec<u64>, Option<usize>, ThreadMetrics), SieveError> {
+    if counts_fast(args) {
+        // Nothing is sieved, so there are no segments to report
+        count_fast(args.limit).map(|count| (vec![], Some(count), ThreadMetrics::default()))
+    } else if args.count_only {
+        match seed_cache {
+            Some(cache) => segmented_count_parallel_cached_with(args.limit, num_threads, options, cache),
+            None => segmented_count_parallel_with(args.limit, num_threads, options),
+        }
+        .map(|(count, metrics)| (vec![], Some(count), metrics))
+    } else if args.rayon {
+        segmented_sieve_rayon(args.limit, num_threads).map(|(primes, metrics)| (primes, None, metrics))
+    } else {
+        match seed_cache {
+            Some(cache) => segmented_sieve_parallel_cached_with(args.limit, num_threads, options, cache),
+            None => segmented_sieve_parallel_with(args.limit, num_threads, options),
+        }
+        .map(|(primes, metrics)| (primes, None, metrics))
     }
 }
 
 fn main() {
-    let args = Args::parse();
+    let args = parse_args_from(std::env::args_os()).unwrap_or_else(|e| e.exit());
 
-    // Validate thread count
-    let num_threads = if args.threads == 0 { 1 } else { args.threads };
+    let num_threads = resolve_threads(args.threads);
+    let format = report_format(&args);
+    if let Some(cores) = physical_cores().filter(|&cores| args.threads.is_some_and(|threads| threads > cores)) {
+        eprintln!(
+            "Warning: {} threads exceed the {} physical core(s); threads sharing a core (SMT siblings) understate the speedup (try --threads {})",
+            num_threads, cores, cores
+        );
+    }
+    if args.csv || args.json {
+        eprintln!(
+            "Warning: --{0} is deprecated, use --format {0}",
+            if args.csv { "csv" } else { "json" }
+        );
+    }
 
-    if !args.csv {
+    if format == ReportFormat::Human && !args.quiet {
         println!("═══════════════════════════════════════════════════════════");
         println!("       MULTITHREADED PRIME NUMBER CALCULATOR");
         println!("═══════════════════════════════════════════════════════════");
         println!("Configuration:");
         println!("  Range: 2 to {}", args.limit);
-        println!("  Threads: {}", num_threads);
-        println!("  Algorithm: Segmented Sieve of Eratosthenes");
-        println!("  Mode: Parallel (multithreaded)");
+        if args.threads_list.is_empty() {
+            println!("  Threads: {}", num_threads);
+        } else {
+            let list: Vec<String> = args.threads_list.iter().map(usize::to_string).collect();
+            println!("  Threads: {} (one run each)", list.join(", "));
+        }
+        if counts_fast(&args) {
+            println!("  Algorithm: Legendre's formula (prime count, no sieve)");
+        } else {
+            println!("  Algorithm: Segmented Sieve of Eratosthenes");
+        }
+        if args.rayon {
+            println!("  Mode: Parallel (rayon, {}-number chunks)", RAYON_CHUNK_SIZE);
+        } else {
+            println!("  Mode: Parallel (multithreaded)");
+            if let Some(segments) = args.segments {
+                println!("  Segments: {} (shared queue)", segments);
+            }
+            if let Some(path) = &args.seed_primes_file {
+                println!("  Base primes: read from {}", path.display());
+            }
+            if args.cache_blocking {
+                println!("  Max segment: {} bytes per thread (L2 cache blocking)", segment_bytes(&args));
+            } else {
+                println!("  Max segment: {} bytes per thread", args.max_segment);
+            }
+        }
         println!("═══════════════════════════════════════════════════════════");
         println!("\nCalculating primes...\n");
     }
 
+    if let Some(path) = &args.dump_seed_primes {
+        let written = BasePrimeCache::seed_primes(args.limit)
+            .map_err(|e| e.to_string())
+            .and_then(|seed| {
+                write_primes_file(path, &seed, OutputFormat::BinDelta).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            eprintln!("Error: failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut seed_cache = args.seed_primes_file.as_deref().map(|path| {
+        load_seed_primes(path, args.limit).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        })
+    });
+
+    if !args.threads_list.is_empty() {
+        match run_sweep(&args, &args.threads_list, &mut seed_cache) {
+            Ok((stats, rows)) => emit_sweep(&stats, &rows, &args, format),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // Start timing
     let start_time = Instant::now();
 
-    // Run the parallel sieve
-    let (primes, metrics) = segmented_sieve_parallel(args.limit, num_threads);
+    // Run the parallel sieve (or just count in --count-only mode), with a
+    // monitor thread reporting progress alongside it if asked
+    let progress = Arc::new(SieveProgress::new());
+    let options = ParallelOptions {
+        max_segment_bytes: segment_bytes(&args),
+        progress: Some(Arc::clone(&progress)),
+        segments: args.segments,
+    };
+    let finished = AtomicBool::new(false);
+    let result = thread::scope(|scope| {
+        if args.progress && !counts_fast(&args) && matches!(format, ReportFormat::Human | ReportFormat::Table) {
+            scope.spawn(|| monitor_progress(&progress, &finished));
+        }
+
+        let result = sieve(&args, num_threads, &options, &mut seed_cache);
+        finished.store(true, Ordering::Relaxed);
+        result
+    });
+    let (primes, count_only, metrics) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Stop timing
     let elapsed = start_time.elapsed();
 
-    // Calculate statistics
-    let stats = calculate_statistics(&primes, args.limit);
+    // Re-test a sample of the primes, also outside the timed section
+    let self_checked = args.self_check.then(|| {
+        self_check::self_check(&primes, &metrics.segments, self_check::SAMPLES_PER_RANGE, clock_seed()).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        })
+    });
 
-    if args.csv {
-        // CSV format: limit,threads,time_ms,prime_count
-        println!(
-            "{},{},{:.3},{}",
-            args.limit,
-            num_threads,
-            elapsed.as_secs_f64() * 1000.0,
-            stats.count
-        );
-    } else {
-        println!("═══════════════════════════════════════════════════════════");
-        println!("                      RESULTS");
-        println!("═══════════════════════════════════════════════════════════");
-        println!("  Primes found:        {:>12}", stats.count);
-        println!("  Largest prime:       {:>12}", stats.largest);
-        println!("  Prime density:       {:>12.6}", stats.density);
-        println!("───────────────────────────────────────────────────────────");
-        println!("  Execution time:      {:>12.3} ms", elapsed.as_secs_f64() * 1000.0);
-        println!("  Execution time:      {:>12.6} s", elapsed.as_secs_f64());
-        println!("───────────────────────────────────────────────────────────");
-        println!("  Thread Metrics:");
-
-        for (i, (low, high, count)) in metrics.segments.iter().enumerate() {
-            println!(
-                "    Thread {}: [{:>10}, {:>10}] -> {} primes",
-                i, low, high, count
-            );
+    // Save the prime list outside the timed section
+    if let Some(path) = &args.output {
+        if let Err(e) = write_primes_file(path, &primes, args.output_format) {
+            eprintln!("Error: failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
         }
+    }
 
-        println!("═══════════════════════════════════════════════════════════");
+    // Calculate statistics
+    let stats = match count_only {
+        Some(count) => count_statistics(count, args.limit),
+        None => calculate_statistics(&primes, args.limit),
+    };
 
-        if args.verbose {
-            println!("\nPrime numbers found:");
-            for (i, prime) in primes.iter().enumerate() {
-                if i > 0 && i % 10 == 0 {
-                    println!();
-                }
-                print!("{:>8} ", prime);
-            }
-            println!();
-        }
-    }
+    let result = RunResult {
+        num_threads,
+        primes,
+        stats,
+        metrics,
+        time_ms: elapsed.as_secs_f64() * 1000.0,
+        peak_rss_mb: if args.report_mem { peak_rss_mb() } else { None },
+        self_checked,
+    };
+    emit(&result, &args, format);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn test_simple_sieve() {
-        let primes = simple_sieve(30);
-        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
-    }
+    use primes_core::{segmented_count_parallel, segmented_sieve_parallel, simple_sieve};
 
     #[test]
     fn test_parallel_sieve_matches_sequential() {
         let limit = 10_000;
-        let sequential = simple_sieve(limit);
-        let (parallel, _) = segmented_sieve_parallel(limit, 4);
+        let sequential = simple_sieve(limit).unwrap();
+        let (parallel, _) = segmented_sieve_parallel(limit, 4).unwrap();
         assert_eq!(sequential, parallel);
     }
 
     #[test]
-    fn test_different_thread_counts() {
-        let limit = 10_000;
-        let expected = simple_sieve(limit);
-
-        for threads in [1, 2, 4, 8] {
-            let (result, _) = segmented_sieve_parallel(limit, threads);
-            assert_eq!(
-                result, expected,
-                "Mismatch with {} threads",
-                threads
-            );
+    fn test_calculate_statistics() {
+        let (primes, _) = segmented_sieve_parallel(1000, 4).unwrap();
+        let stats = calculate_statistics(&primes, 1000);
+        assert_eq!(stats.count, 168);
+        assert_eq!(stats.largest, 997);
+    }
+
+    #[test]
+    fn test_count_only_matches_full_sieve() {
+        let (primes, _) = segmented_sieve_parallel(100_000, 4).unwrap();
+        let (count, metrics) = segmented_count_parallel(100_000, 4).unwrap();
+        assert_eq!(count, primes.len());
+        assert_eq!(count_statistics(count, 100_000).count, 9592);
+        assert!(!metrics.segments.is_empty());
+    }
+
+    #[test]
+    fn test_rayon_sieve_matches_sequential() {
+        let sequential = simple_sieve(1_000_000).unwrap();
+        let (rayon, _) = segmented_sieve_rayon(1_000_000, 4).unwrap();
+        assert_eq!(sequential, rayon);
+    }
+
+    #[test]
+    fn test_json_report_round_trip() {
+        let args = Args::parse_from(["primes-multithread", "--limit", "1000", "--json"]);
+        let (primes, metrics) = segmented_sieve_parallel(args.limit, 4).unwrap();
+        let stats = calculate_statistics(&primes, args.limit);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&json_report(&args, 4, &stats, &metrics, 2.0, None)).unwrap();
+        assert_eq!(json["limit"], 1000);
+        assert_eq!(json["threads"], 4);
+        assert_eq!(json["prime_count"], 168);
+        assert_eq!(json["largest"], 997);
+
+        let segments = json["segments"].as_array().unwrap();
+        assert_eq!(segments.len(), metrics.segments.len());
+        let segment_total: u64 = segments.iter().map(|s| s["prime_count"].as_u64().unwrap()).sum();
+        assert_eq!(segment_total, 168 - 11); // 11 base primes up to √1000
+    }
+
+    #[test]
+    fn test_metrics_one_entry_per_thread() {
+        let (_, metrics) = segmented_sieve_parallel(1_000_000, 4).unwrap();
+        assert_eq!(metrics.segments.len(), 4);
+        assert!(metrics.segments.windows(2).all(|pair| pair[0].1 + 1 == pair[1].0));
+        assert!(metrics.segments.iter().all(|&(.., time_ms)| time_ms >= 0.0));
+        assert_eq!(thread_times_csv(&metrics).split(';').count(), 4);
+    }
+
+    #[test]
+    fn test_output_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("primes.txt");
+
+        let (primes, _) = segmented_sieve_parallel(100_000, 4).unwrap();
+        write_primes_file(&path, &primes, OutputFormat::Comma).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let read_back: Vec<u64> = text.trim_end().split(',').map(|n| n.parse().unwrap()).collect();
+        assert_eq!(read_back.len(), 9592); // π(100_000)
+        assert_eq!(read_back, primes);
+    }
+
+    #[test]
+    fn test_output_format_requires_output() {
+        let result = Args::try_parse_from(["primes-multithread", "--output-format", "binary"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_speedup_efficiency() {
+        let (speedup, efficiency) = speedup_efficiency(800.0, 250.0, 4);
+        assert!((speedup - 3.2).abs() < 1e-12);
+        assert!((efficiency - 0.8).abs() < 1e-12);
+
+        let args = Args::parse_from(["primes-multithread", "-l", "1000", "--baseline-ms", "10"]);
+        let (primes, metrics) = segmented_sieve_parallel(args.limit, 2).unwrap();
+        let stats = calculate_statistics(&primes, args.limit);
+        let json: serde_json::Value =
+            serde_json::from_str(&json_report(&args, 2, &stats, &metrics, 4.0, None)).unwrap();
+        assert_eq!(json["speedup"], 2.5);
+        assert_eq!(json["efficiency"], 1.25);
+    }
+
+    #[test]
+    fn test_legacy_format_flags() {
+        let csv = Args::parse_from(["primes-multithread", "--csv"]);
+        assert_eq!(report_format(&csv), ReportFormat::Csv);
+        let json = Args::parse_from(["primes-multithread", "--json"]);
+        assert_eq!(report_format(&json), ReportFormat::Json);
+        let table = Args::parse_from(["primes-multithread", "--format", "table"]);
+        assert_eq!(report_format(&table), ReportFormat::Table);
+
+        let err = parse_args_from(["primes-multithread", "--csv", "--format", "json"]).unwrap_err();
+        assert!(err.to_string().contains("cannot be used with"), "{}", err);
+    }
+
+    #[test]
+    fn test_table_rows_tile_the_range() {
+        let (primes, metrics) = segmented_sieve_parallel(1000, 2).unwrap();
+        let result = RunResult {
+            num_threads: 2,
+            stats: calculate_statistics(&primes, 1000),
+            primes,
+            metrics,
+            time_ms: 1.5,
+            peak_rss_mb: None,
+            self_checked: None,
+        };
+        let table = table_report(&result, 1000, "thread");
+        let rows: Vec<Vec<&str>> = table.lines().skip(1).map(|line| line.split_whitespace().collect()).collect();
+
+        assert_eq!(rows.len(), 4); // base, two threads, total
+        assert_eq!(rows[0][..4], ["base", "2", "31", "11"]);
+        assert!(rows[0][4].parse::<f64>().unwrap() >= 0.0);
+        assert_eq!(rows[3], ["total", "2", "1000", "168", "1.500"]);
+        let row_primes: usize = rows[..3].iter().map(|row| row[3].parse::<usize>().unwrap()).sum();
+        assert_eq!(row_primes, 168);
+    }
+
+    #[test]
+    fn test_cache_blocking_matches_plain_sieve() {
+        let args = Args::parse_from(["primes-multithread", "--cache-blocking", "--max-segment", "1000000000"]);
+        assert_eq!(segment_bytes(&args), l2_cache_bytes());
+
+        let expected = simple_sieve(2_000_000).unwrap();
+        for max_segment_bytes in [segment_bytes(&args), 4096] {
+            let options = ParallelOptions {
+                max_segment_bytes,
+                ..ParallelOptions::default()
+            };
+            let (primes, _) = segmented_sieve_parallel_with(2_000_000, 3, &options).unwrap();
+            assert_eq!(primes, expected, "{} byte windows", max_segment_bytes);
         }
     }
 
     #[test]
-    fn test_segment_sieve() {
-        let base_primes = vec![2, 3, 5, 7];
-        let segment = sieve_segment(10, 20, &base_primes);
-        assert_eq!(segment, vec![11, 13, 17, 19]);
+    fn test_progress_reaches_every_chunk() {
+        let progress = Arc::new(SieveProgress::new());
+        let options = ParallelOptions {
+            progress: Some(Arc::clone(&progress)),
+            ..ParallelOptions::default()
+        };
+        let (count, _) = segmented_count_parallel_with(10_000_000, 4, &options).unwrap();
+        assert_eq!(count, 664_579);
+        assert!(progress.total_chunks() >= 4);
+        assert_eq!(progress.chunks_done(), progress.total_chunks());
+        assert_eq!(
+            progress_line(progress.chunks_done(), progress.total_chunks()),
+            format!("Progress: 100.0% ({0}/{0} chunks)", progress.total_chunks())
+        );
+        assert_eq!(progress_line(1, 4), "Progress:  25.0% (1/4 chunks)");
+    }
+
+    #[test]
+    fn test_threads_list_parsing() {
+        let args = parse_args_from(["primes-multithread", "--threads-list", "1,2,4"]).unwrap();
+        assert_eq!(args.threads_list, [1, 2, 4]);
+        assert!(parse_args_from(["primes-multithread"]).unwrap().threads_list.is_empty());
+
+        for bad in ["1,,3", "1,-2", "-1", "0,2", "two"] {
+            assert!(parse_args_from(["primes-multithread", "--threads-list", bad]).is_err(), "{}", bad);
+        }
+
+        let both = parse_args_from(["primes-multithread", "--threads", "2", "--threads-list", "1,2"]).unwrap_err();
+        assert_eq!(both.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_sweep_has_one_row_per_entry() {
+        for extra in [&[][..], &["--count-only"], &["--rayon"]] {
+            let args = parse_args_from(["primes-multithread", "--limit", "100000", "--threads-list", "1,3,2,3"].iter().chain(extra)).unwrap();
+            let (stats, rows) = run_sweep(&args, &args.threads_list, &mut None).unwrap();
+            assert_eq!(stats.count, 9592, "{:?}", extra);
+            assert_eq!(rows.iter().map(|row| row.threads).collect::<Vec<_>>(), [1, 3, 2, 3]);
+            assert_eq!(rows[0].speedup, 1.0);
+            assert!(rows.iter().all(|row| row.time_ms > 0.0 && row.speedup > 0.0), "{:?}", rows);
+            assert_eq!(sweep_table(&rows).lines().count(), 5);
+        }
     }
 }
@@ -0,0 +1,114 @@
+//! `--self-check`: re-test a random sample of the primes found
+//!
+//! The range is split the way the sieve split it (the base primes, then
+//! each thread's segment or chunk), and the primes of every part are
+//! checked with the Miller-Rabin test: always the first and last, where
+//! an off-by-one in a thread's bounds lands, plus [`SAMPLES_PER_RANGE`]
+//! drawn at random. A composite in the list names the segment it came
+//! from. Parts with no more primes than that are checked in full.
+
+use primes_core::{is_prime_mr, SplitMix64};
+
+/// Primes drawn at random from each segment (with replacement), besides its ends
+pub const SAMPLES_PER_RANGE: usize = 256;
+
+/// Check a sample of `primes` from each of `segments` and the base range below them
+///
+/// `segments` are the `(low, high, ..)` ranges from the run's metrics;
+/// with none, the whole list is one range. Returns how many numbers were
+/// tested, or an error naming the first composite and its segment. The
+/// same `seed` always draws the same sample.
+pub fn self_check(primes: &[u64], segments: &[(u64, u64, usize, f64)], samples: usize, seed: u64) -> Result<usize, String> {
+    let mut ranges = Vec::with_capacity(segments.len() + 1);
+    match segments.first() {
+        Some(&(low, ..)) => ranges.push(("base primes".to_string(), 0, low.saturating_sub(1))),
+        None => ranges.push(("the whole range".to_string(), 0, u64::MAX)),
+    }
+    for (i, &(low, high, ..)) in segments.iter().enumerate() {
+        ranges.push((format!("segment {} [{}, {}]", i, low, high), low, high));
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    let mut tested = 0;
+    for (name, low, high) in ranges {
+        let slice = &primes[primes.partition_point(|&p| p < low)..primes.partition_point(|&p| p <= high)];
+        let check = |n: u64| {
+            if is_prime_mr(n) {
+                Ok(())
+            } else {
+                Err(format!("self-check failed: {} in {} is not prime (seed {})", n, name, seed))
+            }
+        };
+        if slice.len() <= samples + 2 {
+            slice.iter().try_for_each(|&n| check(n))?;
+            tested += slice.len();
+        } else {
+            check(slice[0])?;
+            check(slice[slice.len() - 1])?;
+            for _ in 0..samples {
+                check(slice[(rng.next_u64() % slice.len() as u64) as usize])?;
+            }
+            tested += samples + 2;
+        }
+    }
+    Ok(tested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primes_core::segmented_sieve_parallel;
+
+    #[test]
+    fn test_clean_run_passes() {
+        let (primes, metrics) = segmented_sieve_parallel(1_000_000, 4).unwrap();
+        let tested = self_check(&primes, &metrics.segments, SAMPLES_PER_RANGE, 7).unwrap();
+        assert_eq!(tested, 168 + 4 * (SAMPLES_PER_RANGE + 2)); // every base prime below 1000
+        assert_eq!(self_check(&primes, &[], 10, 7).unwrap(), 12);
+        assert_eq!(self_check(&[], &metrics.segments, 10, 7).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_false_positive_is_caught() {
+        let (mut primes, metrics) = segmented_sieve_parallel(10_000, 3).unwrap();
+        let last = metrics.segments.len() - 1;
+
+        // 9991 = 97 * 103, slipped in among the last segment's primes
+        let at = primes.partition_point(|&p| p < 9991);
+        primes.insert(at, 9991);
+        let err = self_check(&primes, &metrics.segments, SAMPLES_PER_RANGE, 1).unwrap_err();
+        assert!(err.contains("9991") && err.contains(&format!("segment {} ", last)), "{}", err);
+
+        // And one among the base primes
+        let (mut primes, _) = segmented_sieve_parallel(10_000, 3).unwrap();
+        primes.insert(4, 9);
+        let err = self_check(&primes, &metrics.segments, SAMPLES_PER_RANGE, 1).unwrap_err();
+        assert!(err.contains("9 in base primes"), "{}", err);
+    }
+
+    #[test]
+    fn test_composite_at_a_segment_boundary_is_caught() {
+        // ~19,000 primes per segment, far more than are sampled
+        let (primes, metrics) = segmented_sieve_parallel(1_000_000, 4).unwrap();
+        let mut injected = 0;
+        for (i, &(low, high, count, _)) in metrics.segments.iter().enumerate() {
+            assert!(count > SAMPLES_PER_RANGE + 2);
+            let first = primes[primes.partition_point(|&p| p < low)];
+            let last = primes[primes.partition_point(|&p| p <= high) - 1];
+
+            // Just past the segment's first and last primes, as an off-by-one would leave it
+            for composite in [first - 1, last + 1].into_iter().filter(|n| (low..=high).contains(n)) {
+                assert!(!is_prime_mr(composite), "{}", composite);
+                let mut bad = primes.clone();
+                let at = bad.partition_point(|&p| p < composite);
+                bad.insert(at, composite);
+                injected += 1;
+                for seed in 0..5 {
+                    let err = self_check(&bad, &metrics.segments, SAMPLES_PER_RANGE, seed).unwrap_err();
+                    assert!(err.contains(&format!("{} in segment {} ", composite, i)), "{}", err);
+                }
+            }
+        }
+        assert!(injected >= metrics.segments.len(), "{}", injected);
+    }
+}
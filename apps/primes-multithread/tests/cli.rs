@@ -0,0 +1,269 @@
+//! End-to-end checks of the `primes-multithread` binary
+
+use assert_cmd::Command;
+
+/// Whether `text` contains any Unicode box-drawing character (U+2500-U+257F)
+fn has_box_drawing(text: &str) -> bool {
+    text.chars().any(|c| ('\u{2500}'..='\u{257F}').contains(&c))
+}
+
+#[test]
+fn test_quiet_prints_one_plain_line() {
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000", "--threads", "2", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!has_box_drawing(&stdout), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].starts_with("primes=168 time=") && lines[0].ends_with("ms"), "{}", stdout);
+}
+
+fn run_format(format: &str) -> String {
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000", "--threads", "2", "--format", format])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_every_format_is_parseable() {
+    let human = run_format("human");
+    assert!(human.lines().any(|line| line.split_whitespace().eq(["Primes", "found:", "168"])), "{}", human);
+
+    let csv = run_format("csv");
+    let (header, row) = csv.trim_end().split_once('\n').unwrap();
+    assert_eq!(header, "limit,threads,time_ms,prime_count,thread_ms,base_ms,sieve_ms");
+    let fields: Vec<&str> = row.split(',').collect();
+    assert_eq!(fields.len(), 7, "{}", csv);
+    assert_eq!((fields[0], fields[1], fields[3]), ("1000", "2", "168"));
+    assert!(fields[2].parse::<f64>().is_ok());
+    let phases_ms: f64 = fields[5..].iter().map(|ms| ms.parse::<f64>().unwrap()).sum();
+    assert!(phases_ms <= fields[2].parse::<f64>().unwrap() + 0.01, "{}", csv);
+
+    let json: serde_json::Value = serde_json::from_str(&run_format("json")).unwrap();
+    assert_eq!(json["prime_count"], 168);
+    assert!(json["base_ms"].as_f64().unwrap() >= 0.0 && json["sieve_ms"].as_f64().unwrap() >= 0.0);
+
+    let table = run_format("table");
+    let total = table.lines().last().unwrap().split_whitespace().collect::<Vec<_>>();
+    assert_eq!(total[..4], ["total", "2", "1000", "168"], "{}", table);
+}
+
+#[test]
+fn test_legacy_flag_with_format_errors() {
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000", "--csv", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'--csv' cannot be used with '--format <FORMAT>'"), "{}", stderr);
+
+    // On its own the legacy flag still works, with a deprecation warning
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000", "--csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--format csv"));
+
+    // ...and, being on the command line, it overrides PRIMES_FORMAT
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .env("PRIMES_FORMAT", "json")
+        .args(["--limit", "1000", "--csv", "--no-header"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("1000,"));
+}
+
+#[test]
+fn test_csv_header_unless_no_header() {
+    let run = |extra: &[&str]| {
+        let output = Command::cargo_bin("primes-multithread")
+            .unwrap()
+            .args(["--limit", "1000", "--threads", "2", "--format", "csv"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&[]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert_eq!(lines[0], "limit,threads,time_ms,prime_count,thread_ms,base_ms,sieve_ms");
+
+    // Just the data row, which starts like the one after the header
+    let bare = run(&["--no-header"]);
+    assert_eq!(bare.lines().count(), 1, "{}", bare);
+    assert_eq!(bare.split(',').next(), lines[1].split(',').next());
+}
+
+#[test]
+fn test_env_vars_apply_unless_flag_given() {
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("primes-multithread")
+            .unwrap()
+            .env("PRIMES_LIMIT", "1000")
+            .env("PRIMES_THREADS", "2")
+            .env("PRIMES_FORMAT", "csv")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let from_env = run(&["--no-header"]);
+    assert!(from_env.starts_with("1000,2,"), "{}", from_env);
+    assert!(from_env.contains(",168,"), "{}", from_env);
+
+    let from_flags = run(&["--limit", "100", "--threads", "1", "--format", "json"]);
+    let report: serde_json::Value = serde_json::from_str(from_flags.trim()).unwrap();
+    assert_eq!(report["limit"], 100);
+    assert_eq!(report["threads"], 1);
+    assert_eq!(report["prime_count"], 25);
+}
+
+#[test]
+fn test_single_thread_matches_sequential_sieve() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("primes.txt");
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "100000", "--threads", "1", "--format", "json", "--output"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let written: Vec<u64> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(written, primes_core::simple_sieve(100_000).unwrap());
+
+    // One segment covering everything above √limit
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let segments = report["segments"].as_array().unwrap();
+    assert_eq!(segments.len(), 1, "{}", report);
+    assert_eq!((&segments[0]["low"], &segments[0]["high"]), (&317.into(), &100_000.into()));
+}
+
+#[test]
+fn test_segments_decoupled_from_threads() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("primes.txt");
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "100000", "--threads", "4", "--segments", "16", "--format", "json", "--output"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let written: Vec<u64> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(written, primes_core::simple_sieve(100_000).unwrap());
+
+    // All 16 segments are reported, tiling (√limit, limit]
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let segments = report["segments"].as_array().unwrap();
+    assert_eq!(segments.len(), 16, "{}", report);
+    assert_eq!(segments[0]["low"], 317);
+    assert_eq!(segments[15]["high"], 100_000);
+    assert!(segments.windows(2).all(|pair| pair[0]["high"].as_u64().unwrap() + 1 == pair[1]["low"]));
+    let segment_primes: u64 = segments.iter().map(|s| s["prime_count"].as_u64().unwrap()).sum();
+    assert_eq!(segment_primes, 9592 - 65); // 65 base primes up to 316
+}
+
+#[test]
+fn test_seed_primes_file_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let seed = dir.path().join("seed.bin");
+    let run = |args: &[&str]| {
+        Command::cargo_bin("primes-multithread")
+            .unwrap()
+            .args(["--threads", "3", "--quiet"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let seed_path = seed.to_str().unwrap();
+
+    let dumped = run(&["--limit", "1000000", "--dump-seed-primes", seed_path]);
+    assert!(dumped.status.success(), "{:?}", dumped);
+    let seeded = run(&["--limit", "1000000", "--seed-primes-file", seed_path]);
+    assert!(seeded.status.success(), "{:?}", seeded);
+    assert!(String::from_utf8(seeded.stdout).unwrap().starts_with("primes=78498 "));
+
+    // Primes up to 1009 cannot seed a limit whose √ is 3162
+    let short = run(&["--limit", "10000000", "--seed-primes-file", seed_path]);
+    assert_eq!(short.status.code(), Some(1));
+    let stderr = String::from_utf8(short.stderr).unwrap();
+    assert!(stderr.contains("only covers primes up to 1009"), "{}", stderr);
+}
+
+#[test]
+fn test_threads_list_prints_a_row_per_run() {
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "100000", "--threads-list", "1,2,4", "--format", "csv"])
+        // Only a --threads flag conflicts with the list; the variable is overridden
+        .env("PRIMES_THREADS", "3")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "limit,threads,time_ms,prime_count,speedup");
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    for (line, threads) in lines[1..].iter().zip(["1", "2", "4"]) {
+        let fields: Vec<&str> = line.split(',').collect();
+        assert_eq!((fields[0], fields[1], fields[3]), ("100000", threads, "9592"), "{}", line);
+    }
+    assert!(lines[1].ends_with(",1.000"), "{}", stdout);
+}
+
+#[test]
+fn test_self_check_passes_on_a_real_run() {
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000000", "--threads", "4", "--self-check"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // 168 base primes below 1000 checked in full, 258 from each segment
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.lines().any(|line| line.split_whitespace().eq(["Self-check:", "1200", "primes", "re-tested,", "all", "prime"])),
+        "{}",
+        stdout
+    );
+
+    let output = Command::cargo_bin("primes-multithread")
+        .unwrap()
+        .args(["--limit", "1000", "--self-check", "--count-only"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,154 @@
+//! On-disk cache of sieve results for `--cache-dir`
+//!
+//! Each limit gets its own file, `primes-<limit>.cache`:
+//!
+//! ```text
+//! [magic: "PRMC"][version: u8][limit: u64][count: u64][has_list: u8]
+//! [primes: bin-delta prime list if has_list]
+//! ```
+//!
+//! All integers are little-endian. A `--count-only` run stores just the
+//! count; a full run also stores the prime list, in the compact
+//! `bin-delta` format of [`OutputFormat::BinDelta`].
+
+use primes_core::{read_bin_delta, write_primes, OutputFormat};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"PRMC";
+
+/// Version of the cache file layout; files with any other version are ignored
+pub const CACHE_VERSION: u8 = 2;
+
+/// Contents of a valid cache file
+#[derive(Debug, PartialEq)]
+pub struct CachedPrimes {
+    pub count: u64,
+    /// `None` when the file was written by a `--count-only` run
+    pub primes: Option<Vec<u64>>,
+}
+
+/// Path of the cache file for `limit` inside `dir`
+pub fn cache_path(dir: &Path, limit: u64) -> PathBuf {
+    dir.join(format!("primes-{}.cache", limit))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Read the cache file at `path`, checking that it was written for `limit`
+///
+/// Returns `Ok(None)` if there is no such file. A file with the wrong magic,
+/// version or limit, or a truncated one, is an [`io::ErrorKind::InvalidData`]
+/// error (or `UnexpectedEof`) so the caller can warn and recompute.
+pub fn read_cache(path: &Path, limit: u64) -> io::Result<Option<CachedPrimes>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(invalid_data("not a primes cache file".to_string()));
+    }
+    if header[4] != CACHE_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported cache version {} (expected {})",
+            header[4], CACHE_VERSION
+        )));
+    }
+
+    let cached_limit = read_u64(&mut reader)?;
+    if cached_limit != limit {
+        return Err(invalid_data(format!("cache was written for limit {}", cached_limit)));
+    }
+    let count = read_u64(&mut reader)?;
+
+    let mut has_list = [0u8; 1];
+    reader.read_exact(&mut has_list)?;
+    let primes = match has_list[0] {
+        0 => None,
+        1 => {
+            let primes = read_bin_delta(&mut reader)?;
+            if primes.len() as u64 != count {
+                return Err(invalid_data(format!("list holds {} primes, expected {}", primes.len(), count)));
+            }
+            Some(primes)
+        }
+        flag => return Err(invalid_data(format!("bad list flag {}", flag))),
+    };
+
+    Ok(Some(CachedPrimes { count, primes }))
+}
+
+/// Write a cache file for `limit`, with the prime list if one is given
+///
+/// The file is written next to its final path and renamed into place, so
+/// an interrupted run never leaves a truncated cache behind.
+pub fn write_cache(path: &Path, limit: u64, count: u64, primes: Option<&[u64]>) -> io::Result<()> {
+    let temp_path = path.with_extension("cache.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[CACHE_VERSION])?;
+        writer.write_all(&limit.to_le_bytes())?;
+        writer.write_all(&count.to_le_bytes())?;
+        writer.write_all(&[primes.is_some() as u8])?;
+        if let Some(primes) = primes {
+            write_primes(&mut writer, primes, OutputFormat::BinDelta)?;
+        }
+        writer.flush()?;
+    }
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = cache_path(dir.path(), 30);
+        assert_eq!(read_cache(&path, 30).unwrap(), None);
+
+        let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+        write_cache(&path, 30, 10, Some(&primes)).unwrap();
+        let cached = read_cache(&path, 30).unwrap().unwrap();
+        assert_eq!(cached.count, 10);
+        assert_eq!(cached.primes.as_deref(), Some(&primes[..]));
+
+        write_cache(&path, 30, 10, None).unwrap();
+        assert_eq!(read_cache(&path, 30).unwrap().unwrap().primes, None);
+    }
+
+    #[test]
+    fn test_invalid_cache_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = cache_path(dir.path(), 100);
+        write_cache(&path, 100, 25, None).unwrap();
+
+        // Same file looked up for a different limit
+        let err = read_cache(&path, 200).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4] = CACHE_VERSION + 1;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_cache(&path, 100).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, &bytes[..10]).unwrap();
+        assert!(read_cache(&path, 100).is_err());
+    }
+}
@@ -4,111 +4,332 @@
 //! the Sieve of Eratosthenes algorithm without any parallelization.
 //! Used as a baseline for performance comparison.
 
-use clap::Parser;
+mod cache;
+
+use clap::{Parser, ValueEnum};
+use primes_core::{
+    count_by_decade, count_primes, count_segment, count_twin_primes, find_nth_prime, is_prime_mr, li, max_prime_gap, odd_sieve, peak_rss_mb,
+    prime_count_fast, prime_gap_histogram, primes_from, sample_indices, sieve_segment, simple_sieve, simple_sieve_bytes, tail_primes, twin_primes,
+    wheel30_sieve, write_primes_file, OutputFormat, SieveError, FAST_COUNT_THRESHOLD, MAX_FAST_COUNT_LIMIT,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Instant;
 
+/// Full-range sieve implementations selectable with `--sieve-algo`
+///
+/// All of them return the primes in `[0, limit]` in ascending order.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum SieveAlgo {
+    /// One byte per number (the original implementation)
+    Classic,
+    /// Odd candidates only, bit-packed
+    Odd,
+    /// Candidates coprime to 30 only, bit-packed
+    Wheel,
+    /// One bit per number
+    Bitpacked,
+}
+
+impl std::fmt::Display for SieveAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SieveAlgo::Classic => write!(f, "classic"),
+            SieveAlgo::Odd => write!(f, "odd"),
+            SieveAlgo::Wheel => write!(f, "wheel"),
+            SieveAlgo::Bitpacked => write!(f, "bitpacked"),
+        }
+    }
+}
+
+impl SieveAlgo {
+    /// Human-readable description for the banner and JSON report
+    fn description(self) -> &'static str {
+        match self {
+            SieveAlgo::Classic => "byte per number",
+            SieveAlgo::Odd => "odd-only, bit-packed",
+            SieveAlgo::Wheel => "mod-30 wheel, bit-packed",
+            SieveAlgo::Bitpacked => "bit-packed",
+        }
+    }
+
+    /// All primes up to `limit` with this implementation
+    fn run(self, limit: u64) -> Result<Vec<u64>, SieveError> {
+        match self {
+            SieveAlgo::Classic => simple_sieve_bytes(limit),
+            SieveAlgo::Odd => odd_sieve(limit),
+            SieveAlgo::Wheel => wheel30_sieve(limit),
+            SieveAlgo::Bitpacked => simple_sieve(limit),
+        }
+    }
+}
+
 /// Sequential prime number calculator using Sieve of Eratosthenes
 #[derive(Parser, Debug)]
 #[command(name = "primes-sequential")]
 #[command(about = "Calculate prime numbers sequentially", long_about = None)]
 struct Args {
-    /// Upper limit of the range to search for primes (inclusive)
-    #[arg(short, long, default_value_t = 10_000_000)]
+    /// Upper limit of the range to search for primes (inclusive), e.g. 10000000, 10M or 1e7; the flag overrides PRIMES_LIMIT
+    #[arg(short, long, env = "PRIMES_LIMIT", default_value_t = 10_000_000, value_parser = primes_core::parse_limit)]
     limit: u64,
 
+    /// Lower bound of the range to search for primes (inclusive)
+    #[arg(short, long, default_value_t = 2)]
+    start: u64,
+
     /// Show the list of primes found (warning: can be very long)
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
 
+    /// With --verbose, list only every N-th prime (plus the last)
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), requires = "verbose")]
+    sample: usize,
+
     /// Output results in CSV format for benchmarking
     #[arg(long, default_value_t = false)]
     csv: bool,
+
+    /// Output results as a JSON object
+    #[arg(long, default_value_t = false, conflicts_with = "csv")]
+    json: bool,
+
+    /// Leave out the CSV header line, e.g. when appending to an existing file
+    #[arg(long, default_value_t = false)]
+    no_header: bool,
+
+    /// Skip the banners; without --csv/--json print one `primes=N time=Xms` line
+    #[arg(short, long, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Write the primes found to this file instead of the terminal
+    #[arg(short, long, conflicts_with = "count_only")]
+    output: Option<PathBuf>,
+
+    /// Format of the --output file: lines, comma, binary (little-endian u64), or bin-delta (varint gaps)
+    #[arg(long, default_value_t = OutputFormat::Lines, requires = "output")]
+    output_format: OutputFormat,
+
+    /// Use the original one-byte-per-number sieve instead of the bit-packed one
+    #[arg(long, default_value_t = false)]
+    byte_sieve: bool,
+
+    /// Only store odd candidates (halves memory, skips even multiples)
+    #[arg(long, default_value_t = false, conflicts_with = "byte_sieve")]
+    odd_only: bool,
+
+    /// Only store candidates coprime to 30 (mod-30 wheel factorization)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["byte_sieve", "odd_only"])]
+    wheel: bool,
+
+    /// Sieve implementation to run: classic, odd, wheel or bitpacked (default)
+    #[arg(long, value_enum, conflicts_with_all = ["byte_sieve", "odd_only", "wheel", "count_only", "nth", "check"])]
+    sieve_algo: Option<SieveAlgo>,
+
+    /// Report the peak memory (VmHWM) after sieving; N/A where it cannot be read
+    #[arg(long, default_value_t = false, conflicts_with_all = ["nth", "check"])]
+    report_mem: bool,
+
+    /// Only count the primes (π(limit)) without storing them; above 10^9 by Legendre's formula, without sieving
+    #[arg(long, default_value_t = false, conflicts_with_all = ["verbose", "byte_sieve", "odd_only", "wheel"])]
+    count_only: bool,
+
+    /// Find the N-th prime (1-based) instead of all primes up to --limit
+    #[arg(long, value_name = "N", conflicts_with_all = ["start", "verbose", "output", "count_only", "byte_sieve", "odd_only", "wheel"])]
+    nth: Option<usize>,
+
+    /// List the first --take primes greater than or equal to V instead of all primes up to --limit
+    #[arg(long, value_name = "V", requires = "take", conflicts_with_all = ["start", "verbose", "output", "count_only", "nth", "check", "twins", "gaps", "buckets", "byte_sieve", "odd_only", "wheel", "sieve_algo"])]
+    from: Option<u64>,
+
+    /// Number of primes --from lists
+    #[arg(long, value_name = "K", requires = "from", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    take: Option<usize>,
+
+    /// Print the largest N primes of the range, without --verbose and even with --count-only
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with_all = ["nth", "check", "from", "csv"])]
+    tail: Option<usize>,
+
+    /// Also count twin prime pairs (p, p + 2); --verbose lists them
+    #[arg(long, default_value_t = false, conflicts_with_all = ["count_only", "nth"])]
+    twins: bool,
+
+    /// Also print a histogram of the gaps between consecutive primes
+    #[arg(long, default_value_t = false, conflicts_with_all = ["count_only", "nth"])]
+    gaps: bool,
+
+    /// Also count the primes in each power-of-ten range [1, 10), [10, 100), ... (table or JSON)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["count_only", "nth", "csv"])]
+    buckets: bool,
+
+    /// Test whether N is prime (Miller-Rabin) instead of sieving a range
+    #[arg(long, value_name = "N", conflicts_with_all = ["start", "verbose", "output", "count_only", "nth", "twins", "gaps", "buckets", "byte_sieve", "odd_only", "wheel"])]
+    check: Option<u64>,
+
+    /// Run the sieve N times and report min/max/mean/stddev of the timings
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["nth", "check"])]
+    repeat: u32,
+
+    /// Load results for --limit from this directory if cached, else compute and store them
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["nth", "check"])]
+    cache_dir: Option<PathBuf>,
 }
 
-/// Sieve of Eratosthenes - Sequential Implementation
-///
-/// This function implements the classic Sieve of Eratosthenes algorithm
-/// to find all prime numbers up to a given limit.
-///
-/// # Algorithm Overview
-/// 1. Create a boolean array of size (limit + 1), initialized to true
-/// 2. Mark 0 and 1 as non-prime
-/// 3. For each number p starting from 2:
-///    - If p is still marked as prime, mark all multiples of p as non-prime
-///    - Only need to check up to sqrt(limit)
-/// 4. Collect all indices that are still marked as true
-///
-/// # Arguments
-/// * `limit` - The upper bound (inclusive) to search for primes
-///
-/// # Returns
-/// A vector containing all prime numbers up to the limit
-fn sieve_of_eratosthenes(limit: u64) -> Vec<u64> {
-    // Handle edge cases
-    if limit < 2 {
-        return vec![];
+/// Sieve selected by `--sieve-algo` or one of the older per-variant flags
+fn sieve_algo(args: &Args) -> SieveAlgo {
+    if let Some(algo) = args.sieve_algo {
+        algo
+    } else if args.byte_sieve {
+        SieveAlgo::Classic
+    } else if args.odd_only {
+        SieveAlgo::Odd
+    } else if args.wheel {
+        SieveAlgo::Wheel
+    } else {
+        SieveAlgo::Bitpacked
+    }
+}
+
+/// Human-readable name of the sieve variant selected on the command line
+fn algorithm_name(args: &Args) -> &'static str {
+    if counts_fast(args) {
+        "Legendre prime count"
+    } else if args.start > 2 {
+        "segmented window"
+    } else {
+        sieve_algo(args).description()
     }
+}
 
-    // Create a boolean vector where index represents the number
-    // true = potentially prime, false = composite
-    let mut is_prime = vec![true; (limit + 1) as usize];
+/// Run the selected sieve over `[start, limit]`
+///
+/// Ranges starting at 2 (or below) use the full sieve chosen on the command
+/// line. Any other window is sieved with `sieve_segment` using base primes
+/// up to sqrt(limit), the same way the multithread app sieves each segment.
+fn run_sieve(args: &Args) -> Result<Vec<u64>, SieveError> {
+    if args.start > 2 {
+        return sieve_window(args.start, args.limit);
+    }
 
-    // 0 and 1 are not prime by definition
-    is_prime[0] = false;
-    is_prime[1] = false;
+    sieve_algo(args).run(args.limit)
+}
 
-    // We only need to check up to sqrt(limit)
-    // Any composite number > sqrt(limit) will have a factor <= sqrt(limit)
-    let sqrt_limit = (limit as f64).sqrt() as u64;
+/// Whether `--count-only` uses Legendre's formula instead of a sieve
+fn counts_fast(args: &Args) -> bool {
+    args.count_only && args.limit > FAST_COUNT_THRESHOLD
+}
 
-    // Main sieve loop
-    for num in 2..=sqrt_limit {
-        // If num is still marked as prime
-        if is_prime[num as usize] {
-            // Mark all multiples of num as composite
-            // Start from num^2 because smaller multiples were already marked
-            // by smaller primes
-            let mut multiple = num * num;
-            while multiple <= limit {
-                is_prime[multiple as usize] = false;
-                multiple += num;
-            }
+/// Count the primes in `[start, limit]` without collecting them
+fn run_count(args: &Args) -> Result<usize, SieveError> {
+    if counts_fast(args) {
+        if args.limit > MAX_FAST_COUNT_LIMIT {
+            return Err(SieveError::LimitTooLarge {
+                requested: args.limit,
+                max_supported: MAX_FAST_COUNT_LIMIT,
+            });
         }
+        // π(limit) - π(start - 1) counts the window [start, limit]
+        let below_start = prime_count_fast(args.start.saturating_sub(1));
+        return Ok((prime_count_fast(args.limit) - below_start) as usize);
     }
 
-    // Collect all prime numbers
-    is_prime
-        .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| idx as u64)
-        .collect()
+    if args.start > 2 {
+        let sqrt_limit = (args.limit as f64).sqrt() as u64;
+        let base_primes = simple_sieve(sqrt_limit)?;
+        return count_segment(args.start, args.limit, &base_primes);
+    }
+
+    count_primes(args.limit)
 }
 
-/// Calculate basic statistics about the prime distribution
-fn calculate_statistics(primes: &[u64], limit: u64) -> PrimeStatistics {
-    let count = primes.len();
-    let largest = primes.last().copied().unwrap_or(0);
+/// One sieve run: the primes (empty when only counting), the count in
+/// `--count-only` mode, and whether the result came from `--cache-dir`
+type RunResult = (Vec<u64>, Option<usize>, bool);
 
-    // Prime density: ratio of primes to total numbers
-    let density = if limit > 0 {
-        count as f64 / limit as f64
+/// Run the sieve (or just count in `--count-only` mode), through the
+/// `--cache-dir` cache when one is given
+///
+/// A cache file that cannot be read is reported on stderr and replaced.
+fn run_once(args: &Args) -> Result<RunResult, String> {
+    let compute = || -> Result<(Vec<u64>, Option<usize>), String> {
+        let result = if args.count_only {
+            run_count(args).map(|count| (vec![], Some(count)))
+        } else {
+            run_sieve(args).map(|primes| (primes, None))
+        };
+        result.map_err(|e| e.to_string())
+    };
+
+    let Some(dir) = &args.cache_dir else {
+        return compute().map(|(primes, count)| (primes, count, false));
+    };
+
+    let path = cache::cache_path(dir, args.limit);
+    match cache::read_cache(&path, args.limit) {
+        Ok(Some(cached)) if args.count_only => return Ok((vec![], Some(cached.count as usize), true)),
+        Ok(Some(cache::CachedPrimes { primes: Some(primes), .. })) => return Ok((primes, None, true)),
+        // Missing, or written by --count-only without the list we need
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: ignoring cache file {}: {}", path.display(), e),
+    }
+
+    let (primes, count) = compute()?;
+    let list = (!args.count_only).then_some(&primes[..]);
+    cache::write_cache(&path, args.limit, count.unwrap_or(primes.len()) as u64, list)
+        .map_err(|e| format!("failed to write cache {}: {}", path.display(), e))?;
+    Ok((primes, count, false))
+}
+
+/// Find all primes in `[start, limit]` with a single segmented pass
+fn sieve_window(start: u64, limit: u64) -> Result<Vec<u64>, SieveError> {
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+    let base_primes = simple_sieve(sqrt_limit)?;
+    sieve_segment(start, limit, &base_primes)
+}
+
+/// Approximation of π(n) from the Prime Number Theorem: π(n) ≈ n / ln(n)
+fn pnt_estimate(n: u64) -> f64 {
+    if n > 1 {
+        n as f64 / (n as f64).ln()
     } else {
         0.0
-    };
+    }
+}
 
-    // According to Prime Number Theorem, π(n) ≈ n / ln(n)
-    let theoretical_count = if limit > 1 {
-        (limit as f64 / (limit as f64).ln()) as usize
+/// Calculate basic statistics about the prime distribution in `[start, limit]`
+fn calculate_statistics(primes: &[u64], start: u64, limit: u64) -> PrimeStatistics {
+    PrimeStatistics {
+        largest: primes.last().copied().unwrap_or(0),
+        ..count_statistics(primes.len(), start, limit)
+    }
+}
+
+/// Statistics that only need the prime count (used by `--count-only`)
+///
+/// `largest` is left at 0 because no primes were collected.
+fn count_statistics(count: usize, start: u64, limit: u64) -> PrimeStatistics {
+    // Prime density: ratio of primes to total numbers in the window
+    let window_size = (limit + 1).saturating_sub(start);
+    let density = if window_size > 0 {
+        count as f64 / window_size as f64
     } else {
-        0
+        0.0
     };
 
+    // According to Prime Number Theorem, π(n) ≈ n / ln(n), so the window
+    // holds roughly π(limit) - π(start - 1) primes
+    let theoretical_count =
+        (pnt_estimate(limit) - pnt_estimate(start.saturating_sub(1))).max(0.0) as usize;
+
+    // The logarithmic integral li(n) is a much closer estimate of π(n)
+    let theoretical_li = (li(limit as f64) - li(start.saturating_sub(1) as f64)).max(0.0);
+
     PrimeStatistics {
         count,
-        largest,
+        largest: 0,
         density,
         theoretical_count,
+        theoretical_li,
     }
 }
 
@@ -117,69 +338,635 @@ struct PrimeStatistics {
     largest: u64,
     density: f64,
     theoretical_count: usize,
+    theoretical_li: f64,
+}
+
+/// Twin prime pairs found by `--twins`
+struct TwinStatistics {
+    count: usize,
+    /// The pair with the largest members, if any
+    largest: Option<(u64, u64)>,
+}
+
+fn twin_statistics(primes: &[u64]) -> TwinStatistics {
+    TwinStatistics {
+        count: count_twin_primes(primes),
+        largest: twin_primes(primes).last(),
+    }
+}
+
+/// Gaps between consecutive primes found by `--gaps`
+struct GapStatistics {
+    /// Gap size -> number of occurrences
+    histogram: BTreeMap<u64, u64>,
+    /// Largest gap as (gap, low, high), if there are two or more primes
+    largest: Option<(u64, u64, u64)>,
+}
+
+fn gap_statistics(primes: &[u64]) -> GapStatistics {
+    GapStatistics {
+        histogram: prime_gap_histogram(primes),
+        largest: max_prime_gap(primes),
+    }
+}
+
+/// The optional analyses of the prime list, each present only if its flag was given
+#[derive(Default)]
+struct Analyses {
+    twins: Option<TwinStatistics>,
+    gaps: Option<GapStatistics>,
+    /// `--buckets`: (10^k, primes in [10^k, 10^(k+1)))
+    decades: Option<Vec<(u64, usize)>>,
+    /// `--tail`: the largest primes of the range, in ascending order
+    tail: Option<Vec<u64>>,
+}
+
+fn analyses(args: &Args, primes: &[u64]) -> Analyses {
+    Analyses {
+        twins: args.twins.then(|| twin_statistics(primes)),
+        gaps: args.gaps.then(|| gap_statistics(primes)),
+        decades: args.buckets.then(|| count_by_decade(primes)),
+        tail: None,
+    }
+}
+
+/// `--tail`: the largest `n` primes of the range
+///
+/// Taken off the end of `primes` when the run listed them; `--count-only`
+/// keeps no list, so the top of the range is sieved again, downward until
+/// `n` primes turn up. That stays cheap past 10^9, where the count itself
+/// comes from Legendre's formula.
+fn largest_primes(args: &Args, primes: &[u64], listed: bool, n: usize) -> Result<Vec<u64>, SieveError> {
+    if listed {
+        Ok(primes[primes.len().saturating_sub(n)..].to_vec())
+    } else {
+        tail_primes(args.start, args.limit, n)
+    }
+}
+
+/// Heading of the `--verbose` prime listing, which `--sample` thins out
+fn listing_title(sample: usize) -> String {
+    if sample > 1 {
+        format!("Prime numbers found (one in every {}, plus the last)", sample)
+    } else {
+        "Prime numbers found".to_string()
+    }
+}
+
+/// Share of the numbers in decade `[low, 10 * low)`, cut off after `limit`, that are prime
+fn decade_density(low: u64, count: usize, limit: u64) -> f64 {
+    let end = low.saturating_mul(10).min(limit.saturating_add(1));
+    if end > low {
+        count as f64 / (end - low) as f64
+    } else {
+        0.0
+    }
+}
+
+/// Spread of the wall times over the `--repeat` runs, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct TimingStats {
+    runs: usize,
+    min_ms: f64,
+    max_ms: f64,
+    mean_ms: f64,
+    /// Sample standard deviation (n - 1); 0 for a single run
+    stddev_ms: f64,
+}
+
+fn timing_stats(times_ms: &[f64]) -> TimingStats {
+    if times_ms.is_empty() {
+        return TimingStats {
+            runs: 0,
+            min_ms: 0.0,
+            max_ms: 0.0,
+            mean_ms: 0.0,
+            stddev_ms: 0.0,
+        };
+    }
+
+    let n = times_ms.len() as f64;
+    let mean_ms = times_ms.iter().sum::<f64>() / n;
+    let stddev_ms = if times_ms.len() > 1 {
+        let squares: f64 = times_ms.iter().map(|t| (t - mean_ms).powi(2)).sum();
+        (squares / (n - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+
+    TimingStats {
+        runs: times_ms.len(),
+        min_ms: times_ms.iter().copied().fold(f64::INFINITY, f64::min),
+        max_ms: times_ms.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        mean_ms,
+        stddev_ms,
+    }
+}
+
+/// The `max_gap` object of the JSON report
+#[derive(Serialize)]
+struct JsonGap {
+    gap: u64,
+    low: u64,
+    high: u64,
+}
+
+/// Result object emitted by `--json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    limit: u64,
+    start: u64,
+    threads: usize,
+    algorithm: &'static str,
+    /// Mean over all runs when `--repeat` is given
+    time_ms: f64,
+    /// Only present with `--repeat` greater than 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<TimingStats>,
+    prime_count: usize,
+    /// `null` in `--count-only` mode, where no primes are collected
+    largest: Option<u64>,
+    density: f64,
+    theoretical_count: usize,
+    theoretical_li: f64,
+    /// Only present with `--twins`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    twin_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    largest_twin: Option<(u64, u64)>,
+    /// Only present with `--cache-dir`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_hit: Option<bool>,
+    /// Only present with `--gaps`; keys are gap sizes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gap_histogram: Option<&'a BTreeMap<u64, u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_gap: Option<JsonGap>,
+    /// Only present with `--buckets`; keys are the decades' lower bounds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decade_counts: Option<BTreeMap<u64, usize>>,
+    /// Only present with `--report-mem`, and only where it can be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak_rss_mb: Option<f64>,
+    /// Only present with `--tail`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tail: Option<&'a [u64]>,
+}
+
+/// Render the results of a run as a single-line JSON object
+fn json_report(
+    args: &Args,
+    stats: &PrimeStatistics,
+    analyses: &Analyses,
+    timing: &TimingStats,
+    cache_hit: bool,
+    peak_rss_mb: Option<f64>,
+) -> String {
+    let report = JsonReport {
+        limit: args.limit,
+        start: args.start,
+        threads: 1,
+        algorithm: algorithm_name(args),
+        time_ms: timing.mean_ms,
+        timing: (timing.runs > 1).then_some(*timing),
+        prime_count: stats.count,
+        largest: (!args.count_only).then_some(stats.largest),
+        density: stats.density,
+        theoretical_count: stats.theoretical_count,
+        theoretical_li: stats.theoretical_li,
+        twin_count: analyses.twins.as_ref().map(|twins| twins.count),
+        largest_twin: analyses.twins.as_ref().and_then(|twins| twins.largest),
+        cache_hit: args.cache_dir.is_some().then_some(cache_hit),
+        gap_histogram: analyses.gaps.as_ref().map(|gaps| &gaps.histogram),
+        max_gap: analyses
+            .gaps
+            .as_ref()
+            .and_then(|gaps| gaps.largest)
+            .map(|(gap, low, high)| JsonGap { gap, low, high }),
+        decade_counts: analyses.decades.as_ref().map(|decades| decades.iter().copied().collect()),
+        peak_rss_mb,
+        tail: analyses.tail.as_deref(),
+    };
+    serde_json::to_string(&report).expect("JSON report serialization cannot fail")
+}
+
+/// Result object emitted by `--nth` together with `--json`
+#[derive(Serialize)]
+struct NthJsonReport {
+    n: usize,
+    prime: u64,
+    time_ms: f64,
+}
+
+/// Print a CSV header line unless `--no-header` was given
+fn print_csv_header(args: &Args, header: &str) {
+    if !args.no_header {
+        println!("{}", header);
+    }
+}
+
+/// CSV header for a normal run; the optional columns follow the flags
+fn csv_header(args: &Args) -> String {
+    let mut header = String::from("limit,threads,time_ms,prime_count");
+    if args.twins {
+        header += ",twin_count";
+    }
+    if args.gaps {
+        header += ",max_gap";
+    }
+    if args.repeat > 1 {
+        header += ",run";
+    }
+    if args.sieve_algo.is_some() {
+        header += ",sieve_algo";
+    }
+    if args.report_mem {
+        header += ",peak_rss_mb";
+    }
+    header
+}
+
+/// `--nth` mode: find and report the N-th prime, ignoring --limit
+fn run_nth(args: &Args, n: usize) {
+    if !args.csv && !args.json && !args.quiet {
+        println!("═══════════════════════════════════════════════════════════");
+        println!("       SEQUENTIAL PRIME NUMBER CALCULATOR");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("Configuration:");
+        println!("  Target: prime #{}", n);
+        println!("  Algorithm: Segmented Sieve of Eratosthenes (growing upward)");
+        println!("  Mode: Sequential (single-threaded)");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("\nSearching...\n");
+    }
+
+    let start_time = Instant::now();
+    let prime = match find_nth_prime(n) {
+        Ok(prime) => prime,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+
+    if args.csv {
+        print_csv_header(args, "n,time_ms,prime");
+        println!("{},{:.3},{}", n, time_ms, prime);
+    } else if args.json {
+        let report = NthJsonReport { n, prime, time_ms };
+        println!("{}", serde_json::to_string(&report).expect("JSON report serialization cannot fail"));
+    } else if args.quiet {
+        println!("n={} prime={} time={:.3}ms", n, prime, time_ms);
+    } else {
+        println!("═══════════════════════════════════════════════════════════");
+        println!("                      RESULTS");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("  N:                   {:>12}", n);
+        println!("  N-th prime:          {:>12}", prime);
+        println!("───────────────────────────────────────────────────────────");
+        println!("  Execution time:      {:>12.3} ms", time_ms);
+        println!("═══════════════════════════════════════════════════════════");
+    }
+}
+
+/// Result object emitted by `--from` together with `--json`
+#[derive(Serialize)]
+struct TakeJsonReport<'a> {
+    from: u64,
+    take: usize,
+    primes: &'a [u64],
+    time_ms: f64,
+}
+
+/// `--from`/`--take` mode: list the first `take` primes from `from` on, ignoring --limit
+fn run_take(args: &Args, from: u64, take: usize) {
+    if !args.csv && !args.json && !args.quiet {
+        println!("═══════════════════════════════════════════════════════════");
+        println!("       SEQUENTIAL PRIME NUMBER CALCULATOR");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("Configuration:");
+        println!("  Target: first {} primes from {}", take, from);
+        println!("  Algorithm: Segmented Sieve of Eratosthenes (growing upward)");
+        println!("  Mode: Sequential (single-threaded)");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("\nSearching...\n");
+    }
+
+    let start_time = Instant::now();
+    let primes = match primes_from(from, take) {
+        Ok(primes) => primes,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+
+    if args.csv {
+        // One row per prime, numbered from 1, so the table can be used as is
+        print_csv_header(args, "index,prime");
+        for (i, prime) in primes.iter().enumerate() {
+            println!("{},{}", i + 1, prime);
+        }
+    } else if args.json {
+        let report = TakeJsonReport {
+            from,
+            take,
+            primes: &primes,
+            time_ms,
+        };
+        println!("{}", serde_json::to_string(&report).expect("JSON report serialization cannot fail"));
+    } else if args.quiet {
+        let listed: Vec<String> = primes.iter().map(u64::to_string).collect();
+        println!("from={} primes={} time={:.3}ms", from, listed.join(","), time_ms);
+    } else {
+        println!("═══════════════════════════════════════════════════════════");
+        println!("                      RESULTS");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("  From:                {:>12}", from);
+        println!("  Primes listed:       {:>12}", primes.len());
+        println!("───────────────────────────────────────────────────────────");
+        println!("  Execution time:      {:>12.3} ms", time_ms);
+        println!("═══════════════════════════════════════════════════════════");
+
+        println!("\nPrime numbers found:");
+        for (i, prime) in primes.iter().enumerate() {
+            if i > 0 && i % 10 == 0 {
+                println!();
+            }
+            print!("{:>8} ", prime);
+        }
+        println!();
+    }
+}
+
+/// Result object emitted by `--check` together with `--json`
+#[derive(Serialize)]
+struct CheckJsonReport {
+    n: u64,
+    is_prime: bool,
+    time_ms: f64,
+}
+
+/// `--check` mode: report whether a single number is prime, without a sieve
+fn run_check(args: &Args, n: u64) {
+    let start_time = Instant::now();
+    let is_prime = is_prime_mr(n);
+    let time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+
+    if args.csv {
+        print_csv_header(args, "n,time_ms,is_prime");
+        println!("{},{:.3},{}", n, time_ms, is_prime);
+    } else if args.json {
+        let report = CheckJsonReport { n, is_prime, time_ms };
+        println!("{}", serde_json::to_string(&report).expect("JSON report serialization cannot fail"));
+    } else if args.quiet {
+        println!("n={} is_prime={} time={:.3}ms", n, is_prime, time_ms);
+    } else {
+        println!("═══════════════════════════════════════════════════════════");
+        println!("       SEQUENTIAL PRIME NUMBER CALCULATOR");
+        println!("═══════════════════════════════════════════════════════════");
+        println!("  N:                   {:>20}", n);
+        println!("  Prime:               {:>20}", if is_prime { "yes" } else { "no" });
+        println!("  Algorithm:           {:>20}", "Miller-Rabin");
+        println!("───────────────────────────────────────────────────────────");
+        println!("  Execution time:      {:>20.6} ms", time_ms);
+        println!("═══════════════════════════════════════════════════════════");
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
-    // Print configuration (unless CSV mode)
-    if !args.csv {
+    if let Some(n) = args.nth {
+        run_nth(&args, n);
+        return;
+    }
+
+    if let Some(n) = args.check {
+        run_check(&args, n);
+        return;
+    }
+
+    if let (Some(from), Some(take)) = (args.from, args.take) {
+        run_take(&args, from, take);
+        return;
+    }
+
+    if args.start > args.limit {
+        eprintln!(
+            "Error: --start ({}) must not be greater than --limit ({})",
+            args.start, args.limit
+        );
+        std::process::exit(1);
+    }
+
+    if args.cache_dir.is_some() && args.start > 2 {
+        eprintln!("Error: --cache-dir only caches ranges starting at 2");
+        std::process::exit(1);
+    }
+
+    // Print configuration (unless CSV or JSON mode)
+    if !args.csv && !args.json && !args.quiet {
         println!("═══════════════════════════════════════════════════════════");
         println!("       SEQUENTIAL PRIME NUMBER CALCULATOR");
         println!("═══════════════════════════════════════════════════════════");
         println!("Configuration:");
-        println!("  Range: 2 to {}", args.limit);
-        println!("  Algorithm: Sieve of Eratosthenes");
+        println!("  Range: {} to {}", args.start, args.limit);
+        if counts_fast(&args) {
+            println!("  Algorithm: Legendre's formula (prime count, no sieve)");
+        } else {
+            println!("  Algorithm: Sieve of Eratosthenes ({})", algorithm_name(&args));
+        }
         println!("  Mode: Sequential (single-threaded)");
+        if args.repeat > 1 {
+            println!("  Runs: {}", args.repeat);
+        }
         println!("═══════════════════════════════════════════════════════════");
         println!("\nCalculating primes...\n");
     }
 
-    // Start timing
-    let start_time = Instant::now();
+    // Run the sieve algorithm (or just count in --count-only mode) once per
+    // --repeat, timing each run; only the last run's result is kept
+    let mut times_ms = Vec::with_capacity(args.repeat as usize);
+    let mut last_result = None;
+    for run in 1..=args.repeat {
+        let start_time = Instant::now();
+        let result = run_once(&args);
+        times_ms.push(start_time.elapsed().as_secs_f64() * 1000.0);
 
-    // Run the sieve algorithm
-    let primes = sieve_of_eratosthenes(args.limit);
+        match result {
+            Ok(result) if run == args.repeat => last_result = Some(result),
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let (primes, count_only, cache_hit) = last_result.expect("--repeat is at least 1");
+    let timing = timing_stats(&times_ms);
+    let peak_rss = if args.report_mem { peak_rss_mb() } else { None };
 
-    // Stop timing
-    let elapsed = start_time.elapsed();
+    // Save the prime list outside the timed section
+    if let Some(path) = &args.output {
+        if let Err(e) = write_primes_file(path, &primes, args.output_format) {
+            eprintln!("Error: failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
 
     // Calculate statistics
-    let stats = calculate_statistics(&primes, args.limit);
+    let stats = match count_only {
+        Some(count) => count_statistics(count, args.start, args.limit),
+        None => calculate_statistics(&primes, args.start, args.limit),
+    };
+    let tail = match args.tail.map(|n| largest_primes(&args, &primes, count_only.is_none(), n)).transpose() {
+        Ok(tail) => tail,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let analyses = Analyses {
+        tail,
+        ..analyses(&args, &primes)
+    };
 
     // Output results
     if args.csv {
-        // CSV format: limit,threads,time_ms,prime_count
+        // With --repeat, one row per run with its own time and 1-based index
+        print_csv_header(&args, &csv_header(&args));
+        for (run, time_ms) in times_ms.iter().enumerate() {
+            print!(
+                "{},{},{:.3},{}",
+                args.limit,
+                1, // threads = 1 for sequential
+                time_ms,
+                stats.count
+            );
+            if let Some(twins) = &analyses.twins {
+                print!(",{}", twins.count);
+            }
+            if let Some(gaps) = &analyses.gaps {
+                print!(",{}", gaps.largest.map_or(0, |(gap, _, _)| gap));
+            }
+            if args.repeat > 1 {
+                print!(",{}", run + 1);
+            }
+            if let Some(algo) = args.sieve_algo {
+                print!(",{}", algo);
+            }
+            if args.report_mem {
+                // Left empty where the peak cannot be read
+                print!(",{}", peak_rss.map_or(String::new(), |mb| format!("{:.1}", mb)));
+            }
+            println!();
+        }
+    } else if args.json {
         println!(
-            "{},{},{:.3},{}",
-            args.limit,
-            1, // threads = 1 for sequential
-            elapsed.as_secs_f64() * 1000.0,
-            stats.count
+            "{}",
+            json_report(&args, &stats, &analyses, &timing, cache_hit, peak_rss)
         );
+    } else if args.quiet {
+        print!("primes={} time={:.3}ms", stats.count, timing.mean_ms);
+        if let Some(tail) = &analyses.tail {
+            let tail: Vec<String> = tail.iter().map(u64::to_string).collect();
+            print!(" tail={}", tail.join(","));
+        }
+        println!();
     } else {
         println!("═══════════════════════════════════════════════════════════");
         println!("                      RESULTS");
         println!("═══════════════════════════════════════════════════════════");
         println!("  Primes found:        {:>12}", stats.count);
-        println!("  Largest prime:       {:>12}", stats.largest);
+        if !args.count_only {
+            println!("  Largest prime:       {:>12}", stats.largest);
+        }
         println!("  Prime density:       {:>12.6}", stats.density);
         println!("  Theoretical count:   {:>12} (π(n) ≈ n/ln(n))", stats.theoretical_count);
+        println!("  Theoretical (Li):    {:>12.0} (π(n) ≈ li(n))", stats.theoretical_li);
+        if let Some(twins) = &analyses.twins {
+            println!("  Twin prime pairs:    {:>12}", twins.count);
+            if let Some((p, q)) = twins.largest {
+                println!("  Largest twin pair:   {:>12}", format!("({}, {})", p, q));
+            }
+        }
+        if let Some((gap, low, high)) = analyses.gaps.as_ref().and_then(|gaps| gaps.largest) {
+            println!("  Largest gap:         {:>12} (between {} and {})", gap, low, high);
+        }
         println!("───────────────────────────────────────────────────────────");
-        println!("  Execution time:      {:>12.3} ms", elapsed.as_secs_f64() * 1000.0);
-        println!("  Execution time:      {:>12.6} s", elapsed.as_secs_f64());
+        if timing.runs > 1 {
+            println!("  Runs:                {:>12}", timing.runs);
+            println!("  Min time:            {:>12.3} ms", timing.min_ms);
+            println!("  Max time:            {:>12.3} ms", timing.max_ms);
+            println!("  Mean time:           {:>12.3} ms", timing.mean_ms);
+            println!("  Std deviation:       {:>12.3} ms", timing.stddev_ms);
+        } else {
+            println!("  Execution time:      {:>12.3} ms", timing.mean_ms);
+            println!("  Execution time:      {:>12.6} s", timing.mean_ms / 1000.0);
+        }
+        if args.report_mem {
+            match peak_rss {
+                Some(mb) => println!("  Peak memory (RSS):   {:>12.1} MB", mb),
+                None => println!("  Peak memory (RSS):   {:>12}", "N/A"),
+            }
+        }
+        if let Some(dir) = &args.cache_dir {
+            let status = if cache_hit { "hit" } else { "miss, stored" };
+            println!("  Cache:               {} ({})", status, cache::cache_path(dir, args.limit).display());
+        }
+        if let Some(path) = &args.output {
+            println!("  Primes written to:   {} ({})", path.display(), args.output_format);
+        }
+        if let Some(gaps) = &analyses.gaps {
+            println!("───────────────────────────────────────────────────────────");
+            println!("  Gap histogram:");
+            println!("    {:>6}  {:>12}", "Gap", "Count");
+            for (gap, count) in &gaps.histogram {
+                println!("    {:>6}  {:>12}", gap, count);
+            }
+        }
+        if let Some(decades) = &analyses.decades {
+            println!("───────────────────────────────────────────────────────────");
+            println!("  Primes per decade:");
+            println!("    {:>20}  {:>12}  {:>9}", "From", "Count", "Density");
+            for &(low, count) in decades {
+                println!("    {:>20}  {:>12}  {:>9.6}", low, count, decade_density(low, count, args.limit));
+            }
+        }
+        if let Some(tail) = &analyses.tail {
+            println!("───────────────────────────────────────────────────────────");
+            println!("  Largest {} primes:", tail.len());
+            for row in tail.chunks(5) {
+                let row: Vec<String> = row.iter().map(|p| format!("{:>12}", p)).collect();
+                println!("  {}", row.join(" "));
+            }
+        }
         println!("═══════════════════════════════════════════════════════════");
 
         // Show primes if verbose mode
         if args.verbose {
-            println!("\nPrime numbers found:");
-            for (i, prime) in primes.iter().enumerate() {
+            println!("\n{}:", listing_title(args.sample));
+            for (i, prime) in sample_indices(primes.len(), args.sample).map(|i| primes[i]).enumerate() {
                 if i > 0 && i % 10 == 0 {
                     println!();
                 }
                 print!("{:>8} ", prime);
             }
             println!();
+
+            if args.twins {
+                println!("\nTwin prime pairs:");
+                for (i, (p, q)) in twin_primes(&primes).enumerate() {
+                    if i > 0 && i % 5 == 0 {
+                        println!();
+                    }
+                    print!("{:>17} ", format!("({}, {})", p, q));
+                }
+                println!();
+            }
         }
     }
 }
@@ -189,27 +976,211 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_small_primes() {
-        let primes = sieve_of_eratosthenes(30);
-        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    fn test_calculate_statistics() {
+        let primes = simple_sieve(100).unwrap();
+        let stats = calculate_statistics(&primes, 2, 100);
+        assert_eq!(stats.count, 25);
+        assert_eq!(stats.largest, 97);
+        assert!((stats.density - 25.0 / 99.0).abs() < f64::EPSILON);
+        assert_eq!(stats.theoretical_count, 21); // 100 / ln(100) ≈ 21.7
+        assert!((stats.theoretical_li - 30.13).abs() < 0.01); // li(100) ≈ 30.13
+    }
+
+    #[test]
+    fn test_calculate_statistics_empty() {
+        let stats = calculate_statistics(&[], 2, 1);
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.largest, 0);
+        assert_eq!(stats.theoretical_count, 0);
+    }
+
+    #[test]
+    fn test_sieve_window() {
+        assert_eq!(sieve_window(10, 30).unwrap(), vec![11, 13, 17, 19, 23, 29]);
+        assert_eq!(sieve_window(999_983, 1_000_000).unwrap(), vec![999_983]);
     }
 
     #[test]
-    fn test_prime_count_100() {
-        let primes = sieve_of_eratosthenes(100);
-        assert_eq!(primes.len(), 25); // There are 25 primes <= 100
+    fn test_windowed_statistics() {
+        let primes = sieve_window(10, 30).unwrap();
+        let stats = calculate_statistics(&primes, 10, 30);
+        assert_eq!(stats.count, 6);
+        assert_eq!(stats.largest, 29);
+        assert!((stats.density - 6.0 / 21.0).abs() < f64::EPSILON);
     }
 
     #[test]
-    fn test_prime_count_1000() {
-        let primes = sieve_of_eratosthenes(1000);
-        assert_eq!(primes.len(), 168); // There are 168 primes <= 1000
+    fn test_count_statistics_matches_full_statistics() {
+        let primes = simple_sieve(1000).unwrap();
+        let full = calculate_statistics(&primes, 2, 1000);
+        let counted = count_statistics(count_primes(1000).unwrap(), 2, 1000);
+        assert_eq!(counted.count, full.count);
+        assert_eq!(counted.density, full.density);
+        assert_eq!(counted.theoretical_count, full.theoretical_count);
+        assert_eq!(counted.theoretical_li, full.theoretical_li);
+    }
+
+    #[test]
+    fn test_json_report_round_trip() {
+        let args = Args::parse_from(["primes-sequential", "--limit", "100", "--json"]);
+        let primes = run_sieve(&args).unwrap();
+        let stats = calculate_statistics(&primes, args.start, args.limit);
+
+        let json: serde_json::Value = serde_json::from_str(&json_report(&args, &stats, &Analyses::default(), &timing_stats(&[1.5]), false, None)).unwrap();
+        assert_eq!(json["limit"], 100);
+        assert_eq!(json["threads"], 1);
+        assert_eq!(json["time_ms"], 1.5);
+        assert_eq!(json["prime_count"], 25);
+        assert_eq!(json["largest"], 97);
+        assert!((json["density"].as_f64().unwrap() - 25.0 / 99.0).abs() < 1e-12);
+        assert!(json.get("timing").is_none());
+    }
+
+    #[test]
+    fn test_every_sieve_algo_agrees() {
+        for &algo in SieveAlgo::value_variants() {
+            let args = Args::parse_from(["primes-sequential", "--limit", "100000", "--sieve-algo", &algo.to_string()]);
+            assert_eq!(sieve_algo(&args), algo);
+            assert_eq!(run_sieve(&args).unwrap().len(), 9592, "{}", algo);
+        }
+
+        let legacy = Args::parse_from(["primes-sequential", "--wheel"]);
+        assert_eq!(sieve_algo(&legacy), SieveAlgo::Wheel);
+        assert!(Args::try_parse_from(["primes-sequential", "--wheel", "--sieve-algo", "odd"]).is_err());
     }
 
     #[test]
-    fn test_edge_cases() {
-        assert_eq!(sieve_of_eratosthenes(0), vec![]);
-        assert_eq!(sieve_of_eratosthenes(1), vec![]);
-        assert_eq!(sieve_of_eratosthenes(2), vec![2]);
+    fn test_json_conflicts_with_csv() {
+        let result = Args::try_parse_from(["primes-sequential", "--json", "--csv"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_output_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let text_path = dir.path().join("primes.txt");
+        let primes = simple_sieve(100_000).unwrap();
+        write_primes_file(&text_path, &primes, OutputFormat::Lines).unwrap();
+        let text = std::fs::read_to_string(&text_path).unwrap();
+        assert_eq!(text.lines().count(), 9592); // π(100_000)
+
+        let bin_path = dir.path().join("primes.bin");
+        write_primes_file(&bin_path, &primes, OutputFormat::Binary).unwrap();
+        let bytes = std::fs::read(&bin_path).unwrap();
+        let decoded: Vec<u64> = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded, primes);
+    }
+
+    #[test]
+    fn test_nth_conflicts_with_start() {
+        assert!(Args::try_parse_from(["primes-sequential", "--nth", "10", "--start", "5"]).is_err());
+        let args = Args::try_parse_from(["primes-sequential", "--nth", "10001"]).unwrap();
+        assert_eq!(find_nth_prime(args.nth.unwrap()).unwrap(), 104_743);
+    }
+
+    #[test]
+    fn test_from_take_requires_both() {
+        let args = Args::parse_from(["primes-sequential", "--from", "10", "--take", "4"]);
+        assert_eq!(primes_from(args.from.unwrap(), args.take.unwrap()).unwrap(), vec![11, 13, 17, 19]);
+        let args = Args::parse_from(["primes-sequential", "--from", "13", "--take", "1"]);
+        assert_eq!(primes_from(args.from.unwrap(), args.take.unwrap()).unwrap(), vec![13]);
+
+        assert!(Args::try_parse_from(["primes-sequential", "--from", "10"]).is_err());
+        assert!(Args::try_parse_from(["primes-sequential", "--take", "4"]).is_err());
+        assert!(Args::try_parse_from(["primes-sequential", "--from", "10", "--take", "0"]).is_err());
+        assert!(Args::try_parse_from(["primes-sequential", "--from", "10", "--take", "4", "--nth", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_twin_statistics_in_json() {
+        let args = Args::parse_from(["primes-sequential", "--limit", "1000", "--twins", "--json"]);
+        let primes = run_sieve(&args).unwrap();
+        let stats = calculate_statistics(&primes, args.start, args.limit);
+        let analyses = analyses(&args, &primes);
+        let twins = analyses.twins.as_ref().unwrap();
+        assert_eq!(twins.count, 35);
+        assert_eq!(twins.largest, Some((881, 883)));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&json_report(&args, &stats, &analyses, &timing_stats(&[1.0]), false, None)).unwrap();
+        assert_eq!(json["twin_count"], 35);
+        assert_eq!(json["largest_twin"], serde_json::json!([881, 883]));
+        assert!(Args::try_parse_from(["primes-sequential", "--twins", "--count-only"]).is_err());
+    }
+
+    #[test]
+    fn test_gap_statistics_in_json() {
+        let args = Args::parse_from(["primes-sequential", "--limit", "100", "--gaps", "--json"]);
+        let primes = run_sieve(&args).unwrap();
+        let stats = calculate_statistics(&primes, args.start, args.limit);
+        let analyses = analyses(&args, &primes);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&json_report(&args, &stats, &analyses, &timing_stats(&[1.0]), false, None)).unwrap();
+        assert_eq!(json["gap_histogram"], serde_json::json!({"1": 1, "2": 8, "4": 7, "6": 7, "8": 1}));
+        assert_eq!(json["max_gap"], serde_json::json!({"gap": 8, "low": 89, "high": 97}));
+        assert!(json.get("twin_count").is_none());
+    }
+
+    #[test]
+    fn test_decade_counts_in_json() {
+        let args = Args::parse_from(["primes-sequential", "--limit", "1000", "--buckets", "--json"]);
+        let primes = run_sieve(&args).unwrap();
+        let stats = calculate_statistics(&primes, args.start, args.limit);
+        let analyses = analyses(&args, &primes);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&json_report(&args, &stats, &analyses, &timing_stats(&[1.0]), false, None)).unwrap();
+        assert_eq!(json["decade_counts"], serde_json::json!({"1": 4, "10": 21, "100": 143}));
+        assert!(json.get("gap_histogram").is_none());
+
+        // 143 primes among the 900 numbers 100..=999; the last decade is cut off at the limit
+        assert_eq!(decade_density(100, 143, 1000), 143.0 / 900.0);
+        assert_eq!(decade_density(1000, 1, 1000), 1.0);
+        assert!(Args::try_parse_from(["primes-sequential", "--buckets", "--csv"]).is_err());
+    }
+
+    #[test]
+    fn test_check_conflicts_with_range_modes() {
+        let args = Args::parse_from(["primes-sequential", "--check", "2305843009213693951"]);
+        assert_eq!(args.check, Some((1 << 61) - 1));
+        assert!(Args::try_parse_from(["primes-sequential", "--check", "7", "--nth", "3"]).is_err());
+        assert!(Args::try_parse_from(["primes-sequential", "--check", "7", "--count-only"]).is_err());
+    }
+
+    #[test]
+    fn test_timing_stats() {
+        let timing = timing_stats(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(timing.runs, 8);
+        assert_eq!((timing.min_ms, timing.max_ms, timing.mean_ms), (2.0, 9.0, 5.0));
+        // Sum of squared deviations is 32, over n - 1 = 7
+        assert!((timing.stddev_ms - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+
+        let single = timing_stats(&[3.5]);
+        assert_eq!((single.min_ms, single.max_ms, single.stddev_ms), (3.5, 3.5, 0.0));
+        assert!(Args::try_parse_from(["primes-sequential", "--repeat", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_cache_dir_second_run_hits() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_arg = dir.path().to_str().unwrap();
+
+        let args = Args::parse_from(["primes-sequential", "--limit", "10000", "--cache-dir", dir_arg]);
+        let (primes, _, hit) = run_once(&args).unwrap();
+        assert!(!hit);
+        assert_eq!(primes.len(), 1229);
+
+        let (cached, _, hit) = run_once(&args).unwrap();
+        assert!(hit);
+        assert_eq!(cached, primes);
+
+        // A full cache also answers --count-only
+        let args = Args::parse_from(["primes-sequential", "--limit", "10000", "--count-only", "--cache-dir", dir_arg]);
+        assert_eq!(run_once(&args).unwrap(), (vec![], Some(1229), true));
     }
 }
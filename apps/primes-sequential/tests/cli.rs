@@ -0,0 +1,178 @@
+//! End-to-end checks of the `primes-sequential` binary
+
+use assert_cmd::Command;
+
+/// Whether `text` contains any Unicode box-drawing character (U+2500-U+257F)
+fn has_box_drawing(text: &str) -> bool {
+    text.chars().any(|c| ('\u{2500}'..='\u{257F}').contains(&c))
+}
+
+#[test]
+fn test_quiet_prints_one_plain_line() {
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "1000", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!has_box_drawing(&stdout), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].starts_with("primes=168 time=") && lines[0].ends_with("ms"), "{}", stdout);
+}
+
+#[test]
+fn test_csv_header_unless_no_header() {
+    let run = |extra: &[&str]| {
+        let output = Command::cargo_bin("primes-sequential")
+            .unwrap()
+            .args(["--limit", "1000", "--csv"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&[]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert_eq!(lines[0], "limit,threads,time_ms,prime_count");
+
+    // Just the data row, which starts like the one after the header
+    let bare = run(&["--no-header"]);
+    assert_eq!(bare.lines().count(), 1, "{}", bare);
+    assert_eq!(bare.split(',').next(), lines[1].split(',').next());
+}
+
+#[test]
+fn test_report_mem_adds_csv_column() {
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "1000000", "--csv", "--report-mem"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "limit,threads,time_ms,prime_count,peak_rss_mb");
+    let peak = lines[1].rsplit(',').next().unwrap();
+    if cfg!(target_os = "linux") {
+        assert!(peak.parse::<f64>().unwrap() > 0.0, "{}", stdout);
+    }
+}
+
+#[test]
+fn test_count_only_above_threshold_uses_legendre() {
+    // 10^10 would need 1.25 GB of sieve flags
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "10000000000", "--count-only", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["prime_count"], 455_052_511);
+    assert_eq!(report["algorithm"], "Legendre prime count");
+
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "100000000000000", "--count-only"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("too large"));
+}
+
+#[test]
+fn test_limit_shorthand() {
+    for limit in ["1e3", "1k", "1.0K"] {
+        let output = Command::cargo_bin("primes-sequential")
+            .unwrap()
+            .args(["--limit", limit, "--quiet"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", limit);
+        assert!(String::from_utf8(output.stdout).unwrap().starts_with("primes=168 "), "{}", limit);
+    }
+
+    // The environment variable goes through the same parser
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .env("PRIMES_LIMIT", "0.001M")
+        .arg("--quiet")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("primes=168 "));
+
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "1.5"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("not a whole number"));
+}
+
+#[test]
+fn test_sample_thins_the_verbose_listing() {
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "100", "--verbose", "--sample", "10"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // 25 primes: indices 0, 10, 20 and the last, 24
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (_, listing) = stdout.split_once("Prime numbers found (one in every 10, plus the last):").unwrap();
+    let listed: Vec<&str> = listing.split_whitespace().collect();
+    assert_eq!(listed, ["2", "31", "73", "97"]);
+
+    let without_verbose = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "100", "--sample", "10"])
+        .output()
+        .unwrap();
+    assert!(!without_verbose.status.success());
+}
+
+#[test]
+fn test_tail_lists_the_largest_primes() {
+    let tail = |extra: &[&str]| {
+        let output = Command::cargo_bin("primes-sequential")
+            .unwrap()
+            .args(["--limit", "100", "--quiet"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let (_, tail) = stdout.trim_end().split_once(" tail=").unwrap();
+        tail.split(',').map(|p| p.parse::<u64>().unwrap()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(tail(&["--tail", "3"]), [83, 89, 97]);
+    // --count-only keeps no list, yet finds the same three
+    assert_eq!(tail(&["--tail", "3", "--count-only"]), [83, 89, 97]);
+
+    // The same as the end of the full list of all 25
+    let all = tail(&["--tail", "1000"]);
+    assert_eq!(all.len(), 25);
+    assert_eq!(all[..4], [2, 3, 5, 7]);
+    assert_eq!(all[all.len() - 3..], [83, 89, 97]);
+
+    // Past 10^9 the count is Legendre's and only the top of the range is sieved
+    let output = Command::cargo_bin("primes-sequential")
+        .unwrap()
+        .args(["--limit", "1e10", "--count-only", "--quiet", "--tail", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("primes=455052511 "), "{}", stdout);
+    assert!(stdout.trim_end().ends_with(" tail=9999999943,9999999967"), "{}", stdout);
+}
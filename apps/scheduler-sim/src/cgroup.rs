@@ -0,0 +1,73 @@
+//! CPU quota of the enclosing cgroup v2 (`--respect-cgroup`)
+//!
+//! In a container the scheduler may see every CPU of the host while the
+//! cgroup only grants a fraction of their time. `cpu.max` holds that
+//! budget as `<quota> <period>` in microseconds, or `max <period>` when
+//! there is no limit; quota / period is the number of CPUs' worth of time
+//! the group may use.
+
+use std::path::Path;
+
+/// `cpu.max` of the cgroup this process runs in, as seen from inside it
+pub const CPU_MAX_PATH: &str = "/sys/fs/cgroup/cpu.max";
+
+/// CPUs' worth of time allowed by the contents of a `cpu.max` file, or
+/// `None` if it says `max` (no limit)
+fn parse_cpu_max(text: &str) -> Result<Option<f64>, String> {
+    let mut fields = text.split_whitespace();
+    let (Some(quota), Some(period), None) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(format!("expected `<quota> <period>`, got {:?}", text.trim()));
+    };
+
+    let period: u64 = period.parse().map_err(|e| format!("bad period {:?}: {}", period, e))?;
+    if period == 0 {
+        return Err("period is 0".to_string());
+    }
+    if quota == "max" {
+        return Ok(None);
+    }
+    let quota: u64 = quota.parse().map_err(|e| format!("bad quota {:?}: {}", quota, e))?;
+    Ok(Some(quota as f64 / period as f64))
+}
+
+/// CPU budget set by the `cpu.max` file at `path`
+///
+/// `None` if there is no limit, or no readable cgroup v2 file to say so.
+pub fn cpu_quota(path: &Path) -> Option<f64> {
+    let text = std::fs::read_to_string(path).ok()?;
+    parse_cpu_max(&text).ok().flatten()
+}
+
+/// Threads that fit in a budget of `cpus`: rounded up, and at least one
+pub fn quota_threads(cpus: f64) -> usize {
+    (cpus.ceil() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_max() {
+        assert_eq!(parse_cpu_max("max 100000\n"), Ok(None));
+        assert_eq!(parse_cpu_max("50000 100000\n"), Ok(Some(0.5)));
+        assert_eq!(parse_cpu_max("250000 100000"), Ok(Some(2.5)));
+        assert!(parse_cpu_max("").is_err());
+        assert!(parse_cpu_max("50000").is_err());
+        assert!(parse_cpu_max("50000 0").is_err());
+        assert!(parse_cpu_max("half 100000").is_err());
+    }
+
+    #[test]
+    fn test_quota_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cpu.max");
+        assert_eq!(cpu_quota(&path), None);
+
+        std::fs::write(&path, "150000 100000\n").unwrap();
+        assert_eq!(cpu_quota(&path), Some(1.5));
+        assert_eq!(quota_threads(1.5), 2);
+        assert_eq!(quota_threads(0.5), 1);
+        assert_eq!(quota_threads(4.0), 4);
+    }
+}
@@ -0,0 +1,90 @@
+//! ANSI color for the human-readable output (`--color`)
+//!
+//! Color is opt-out: `auto` (the default) colors only when stdout is a
+//! terminal and `NO_COLOR` is unset or empty, see <https://no-color.org>.
+//! `always` and `never` override both.
+
+use clap::ValueEnum;
+use std::io::IsTerminal;
+
+/// When to emit ANSI color codes
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
+impl std::fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorChoice::Auto => write!(f, "auto"),
+            ColorChoice::Always => write!(f, "always"),
+            ColorChoice::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Whether to color, given the `NO_COLOR` value and whether stdout is a tty
+fn should_color(choice: ColorChoice, no_color: Option<&str>, is_tty: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => no_color.is_none_or(str::is_empty) && is_tty,
+    }
+}
+
+/// Applies (or skips) ANSI styles according to a [`ColorChoice`]
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    /// Resolve `choice` against the environment and stdout
+    pub fn new(choice: ColorChoice) -> Self {
+        let no_color = std::env::var("NO_COLOR").ok();
+        Palette {
+            enabled: should_color(choice, no_color.as_deref(), std::io::stdout().is_terminal()),
+        }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Bold green, for the best result in a comparison
+    pub fn highlight(&self, text: &str) -> String {
+        self.paint("1;32", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_color() {
+        assert!(should_color(ColorChoice::Auto, None, true));
+        assert!(should_color(ColorChoice::Auto, Some(""), true));
+        assert!(!should_color(ColorChoice::Auto, Some("1"), true));
+        assert!(!should_color(ColorChoice::Auto, None, false));
+        assert!(should_color(ColorChoice::Always, Some("1"), false));
+        assert!(!should_color(ColorChoice::Never, None, true));
+    }
+
+    #[test]
+    fn test_palette_never_is_plain() {
+        let palette = Palette { enabled: false };
+        assert_eq!(palette.highlight("SCHED_RR"), "SCHED_RR");
+        let palette = Palette { enabled: true };
+        assert_eq!(palette.highlight("SCHED_RR"), "\x1b[1;32mSCHED_RR\x1b[0m");
+    }
+}
@@ -0,0 +1,52 @@
+//! Run settings from a TOML file (`--config`)
+//!
+//! The keys mirror the long flag names:
+//!
+//! ```toml
+//! policy = "fifo"
+//! threads = 8
+//! priority = 80
+//! limit = 10000000
+//! iterations = 5
+//! ```
+//!
+//! Every key is optional. Values from the file replace the built-in
+//! defaults, but a flag given on the command line always wins.
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::SchedulingPolicy;
+
+/// Contents of a `--config` file
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub policy: Option<SchedulingPolicy>,
+    pub threads: Option<usize>,
+    pub priority: Option<i32>,
+    pub limit: Option<u64>,
+    pub iterations: Option<u32>,
+}
+
+/// Read and parse the TOML file at `path`
+pub fn load(path: &Path) -> Result<FileConfig, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sim.toml");
+        std::fs::write(&path, "policy = \"rr\"\nthread = 4\n").unwrap();
+
+        let err = load(&path).unwrap_err();
+        assert!(err.contains("thread"), "{}", err);
+        assert!(load(&dir.path().join("missing.toml")).unwrap_err().contains("Failed to read"));
+    }
+}
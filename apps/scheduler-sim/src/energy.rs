@@ -0,0 +1,85 @@
+//! Package energy from Intel RAPL counters (`--energy`)
+//!
+//! The kernel's powercap driver exposes a running microjoule counter for
+//! CPU package 0 in sysfs. It covers the whole package, so anything else
+//! running at the same time is counted too. The counter goes from 0 up to
+//! `max_energy_range_uj` and then wraps back to 0.
+
+use std::path::{Path, PathBuf};
+
+/// sysfs directory of the package-0 RAPL domain
+pub const RAPL_DIR: &str = "/sys/class/powercap/intel-rapl:0";
+
+/// An opened RAPL energy counter
+#[derive(Debug)]
+pub struct RaplCounter {
+    energy_path: PathBuf,
+    max_energy_uj: u64,
+}
+
+fn read_u64(path: &Path) -> Result<u64, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    text.trim()
+        .parse()
+        .map_err(|e| format!("unexpected contents in {}: {}", path.display(), e))
+}
+
+impl RaplCounter {
+    /// Open the counter in `dir`, checking that it can be read
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let counter = RaplCounter {
+            energy_path: dir.join("energy_uj"),
+            max_energy_uj: read_u64(&dir.join("max_energy_range_uj"))?,
+        };
+        counter.read()?;
+        Ok(counter)
+    }
+
+    /// Current counter value in microjoules
+    pub fn read(&self) -> Result<u64, String> {
+        read_u64(&self.energy_path)
+    }
+
+    /// Joules consumed between two readings of this counter
+    pub fn joules_between(&self, before: u64, after: u64) -> f64 {
+        energy_delta_uj(before, after, self.max_energy_uj) as f64 / 1e6
+    }
+}
+
+/// Microjoules between two readings of a counter that wraps after `max`
+///
+/// Assumes the counter wrapped at most once in between.
+fn energy_delta_uj(before: u64, after: u64, max: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else {
+        // Up to max, one step from max to 0, then up to `after`
+        (max - before) + 1 + after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_delta_wraps() {
+        let max = 262_143_328_850;
+        assert_eq!(energy_delta_uj(1_000, 5_000, max), 4_000);
+        assert_eq!(energy_delta_uj(max - 10, 5, max), 16);
+        assert_eq!(energy_delta_uj(max, 0, max), 1);
+        assert_eq!(energy_delta_uj(7, 7, max), 0);
+    }
+
+    #[test]
+    fn test_open_reads_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(RaplCounter::open(dir.path()).unwrap_err().contains("max_energy_range_uj"));
+
+        std::fs::write(dir.path().join("max_energy_range_uj"), "1000\n").unwrap();
+        std::fs::write(dir.path().join("energy_uj"), "990\n").unwrap();
+        let counter = RaplCounter::open(dir.path()).unwrap();
+        assert_eq!(counter.read().unwrap(), 990);
+        assert_eq!(counter.joules_between(990, 9), 20.0 / 1e6);
+    }
+}
@@ -0,0 +1,48 @@
+//! Per-thread problem sizes varied by `--jitter`
+//!
+//! With every thread given the same `--limit`, all tasks are the same
+//! length and a policy's fairness hardly shows. `--jitter P` gives thread
+//! `i` a limit drawn uniformly from `limit ± P%`. The draws come from a
+//! [`SplitMix64`] generator seeded with `--seed`, so a run can be repeated
+//! exactly, and every policy of one run sees the same limits.
+
+use primes_core::SplitMix64;
+
+/// Limit of each of `threads` threads: `limit` moved by up to ±`percent`%
+///
+/// The same `seed` always gives the same limits. With `percent` 0 every
+/// thread gets `limit`.
+pub fn jittered_limits(limit: u64, threads: usize, percent: u32, seed: u64) -> Vec<u64> {
+    let mut rng = SplitMix64::new(seed);
+    let spread = limit as f64 * f64::from(percent) / 100.0;
+    (0..threads)
+        .map(|_| {
+            // Rounded toward `limit`, so the result stays inside the band
+            let offset = (spread * (2.0 * rng.next_f64() - 1.0)).trunc();
+            (limit as f64 + offset).max(0.0) as u64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_are_deterministic_and_in_band() {
+        let limits = jittered_limits(1_000_000, 16, 20, 42);
+        assert_eq!(limits, jittered_limits(1_000_000, 16, 20, 42));
+        assert_ne!(limits, jittered_limits(1_000_000, 16, 20, 43));
+
+        assert!(limits.iter().all(|&l| (800_000..=1_200_000).contains(&l)), "{:?}", limits);
+        // The draws actually vary, on both sides of the limit
+        assert!(limits.iter().any(|&l| l < 1_000_000) && limits.iter().any(|&l| l > 1_000_000));
+    }
+
+    #[test]
+    fn test_zero_jitter_keeps_the_limit() {
+        assert_eq!(jittered_limits(5000, 3, 0, 7), vec![5000; 3]);
+        assert!(jittered_limits(10, 100, 100, 1).iter().all(|&l| l <= 20));
+        assert!(jittered_limits(0, 4, 50, 1).iter().all(|&l| l == 0));
+    }
+}
@@ -0,0 +1,1166 @@
+//! Scheduling policy runs behind the `scheduler-sim` binary
+//!
+//! [`run_with_policy`] starts [`RunOptions::num_threads`] threads under one
+//! policy, has each run its `--workload`, and returns their measurements as
+//! a [`PolicyMetrics`]. The binary only parses flags and prints what comes
+//! back, so tests and other tools can call it directly:
+//!
+//! ```no_run
+//! use scheduler_sim::{run_with_policy, RunOptions, SchedulingPolicy};
+//!
+//! let options = RunOptions {
+//!     num_threads: 2,
+//!     limit: 100_000,
+//!     ..RunOptions::default()
+//! };
+//! let metrics = run_with_policy(SchedulingPolicy::Other, &options)?;
+//! println!("{} threads, {:.0} primes/s", metrics.total_threads, metrics.throughput);
+//! # Ok::<(), String>(())
+//! ```
+
+pub mod energy;
+pub mod jitter;
+pub mod workload;
+
+use clap::ValueEnum;
+use energy::RaplCounter;
+use serde::Deserialize;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use workload::WorkloadKind;
+
+/// Scheduling policy options
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulingPolicy {
+    /// Default Linux CFS scheduler (SCHED_OTHER)
+    Other,
+    /// Real-time FIFO scheduler (SCHED_FIFO)
+    Fifo,
+    /// Real-time Round-Robin scheduler (SCHED_RR)
+    Rr,
+    /// Earliest Deadline First scheduler (SCHED_DEADLINE); needs --runtime-ns, --deadline-ns and --period-ns
+    Deadline,
+    /// Run all policies for comparison
+    All,
+}
+
+impl std::fmt::Display for SchedulingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulingPolicy::Other => write!(f, "SCHED_OTHER"),
+            SchedulingPolicy::Fifo => write!(f, "SCHED_FIFO"),
+            SchedulingPolicy::Rr => write!(f, "SCHED_RR"),
+            SchedulingPolicy::Deadline => write!(f, "SCHED_DEADLINE"),
+            SchedulingPolicy::All => write!(f, "ALL"),
+        }
+    }
+}
+
+/// The `--priority` to use for `policy`, or its default if none was given
+///
+/// FIFO and RR take a real-time priority in 1..=99 (default 50); OTHER
+/// takes a nice value in -20..=19 (default 0). DEADLINE ignores it.
+pub fn resolve_priority(policy: SchedulingPolicy, requested: Option<i32>) -> Result<i32, String> {
+    let (range, default, meaning) = match policy {
+        SchedulingPolicy::Fifo | SchedulingPolicy::Rr => (1..=99, 50, "a real-time priority"),
+        SchedulingPolicy::Other => (-20..=19, 0, "a nice value"),
+        SchedulingPolicy::Deadline | SchedulingPolicy::All => return Ok(requested.unwrap_or(0)),
+    };
+
+    match requested {
+        None => Ok(default),
+        Some(priority) if range.contains(&priority) => Ok(priority),
+        Some(priority) => Err(format!(
+            "--priority {} is out of range for {}, which takes {} from {} to {}",
+            priority,
+            policy,
+            meaning,
+            range.start(),
+            range.end()
+        )),
+    }
+}
+
+/// Settings shared by every policy run
+#[derive(Debug, Clone, Copy)]
+pub struct RunOptions {
+    pub num_threads: usize,
+    /// `--priority` as given; resolved per policy by [`resolve_priority`]
+    pub priority: Option<i32>,
+    pub limit: u64,
+    pub workload: WorkloadKind,
+    pub iterations: u32,
+    pub warmup: u32,
+    /// Yield after every this many timed iterations; 0 never yields
+    pub yield_every: u32,
+    /// `--jitter` percent and the seed its per-thread limits are drawn with
+    pub jitter: u32,
+    pub seed: u64,
+    pub deadline: Option<DeadlineParams>,
+    pub affinity: bool,
+    pub verbose: bool,
+}
+
+impl Default for RunOptions {
+    /// The command line's defaults, on a single thread
+    fn default() -> Self {
+        RunOptions {
+            num_threads: 1,
+            priority: None,
+            limit: 5_000_000,
+            workload: WorkloadKind::Primes,
+            iterations: 3,
+            warmup: 0,
+            yield_every: 1,
+            jitter: 0,
+            seed: 0,
+            deadline: None,
+            affinity: false,
+            verbose: false,
+        }
+    }
+}
+
+/// Budget for SCHED_DEADLINE, all values in nanoseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadlineParams {
+    pub runtime_ns: u64,
+    pub deadline_ns: u64,
+    pub period_ns: u64,
+}
+
+/// Policy number of SCHED_DEADLINE (not exported by the libc crate)
+#[cfg(target_os = "linux")]
+const SCHED_DEADLINE: u32 = 6;
+
+/// Mirror of the kernel's `struct sched_attr` (include/uapi/linux/sched/types.h)
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SchedAttr {
+    size: u32,
+    sched_policy: u32,
+    sched_flags: u64,
+    sched_nice: i32,
+    sched_priority: u32,
+    sched_runtime: u64,
+    sched_deadline: u64,
+    sched_period: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl SchedAttr {
+    fn deadline(params: DeadlineParams) -> Self {
+        SchedAttr {
+            size: std::mem::size_of::<SchedAttr>() as u32,
+            sched_policy: SCHED_DEADLINE,
+            sched_runtime: params.runtime_ns,
+            sched_deadline: params.deadline_ns,
+            sched_period: params.period_ns,
+            ..Default::default()
+        }
+    }
+}
+
+/// Switch the current thread to SCHED_DEADLINE
+///
+/// glibc has no `sched_setattr` wrapper, so the syscall is invoked directly.
+#[cfg(target_os = "linux")]
+fn set_deadline_scheduling(params: DeadlineParams) -> Result<(), String> {
+    let attr = SchedAttr::deadline(params);
+
+    // 0 means current thread; the trailing 0 is the (unused) flags argument
+    let result = unsafe { libc::syscall(libc::SYS_sched_setattr, 0, &attr as *const SchedAttr, 0) };
+
+    if result == -1 {
+        let errno = std::io::Error::last_os_error();
+        return Err(format!(
+            "Failed to set SCHED_DEADLINE: {} (try running with sudo)",
+            errno
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_deadline_scheduling(_params: DeadlineParams) -> Result<(), String> {
+    Err("Scheduling policies are only supported on Linux".to_string())
+}
+
+/// Metrics collected for each thread
+#[derive(Debug, Clone, Default)]
+pub struct ThreadMetrics {
+    pub thread_id: usize,
+    pub policy: String,
+    pub priority: i32,
+    /// Problem size this thread ran: `--limit`, varied by `--jitter`
+    pub limit: u64,
+    /// Time from thread creation to first execution
+    pub wait_time: Duration,
+    /// Time spent in --warmup iterations, and then waiting for the other
+    /// threads to finish theirs (excluded from the other times)
+    pub warmup_time: Duration,
+    /// Time to complete all timed work
+    pub execution_time: Duration,
+    /// CPU time the thread consumed during the timed work
+    pub cpu_time: Duration,
+    /// Total time from creation to completion, minus warmup
+    pub turnaround_time: Duration,
+    /// Units of work done by a single iteration (π(limit) for primes)
+    pub work_units: usize,
+    /// Core the thread was pinned to with --affinity, otherwise the core
+    /// it was running on when it finished
+    pub cpu: usize,
+    /// Why pinning to `cpu` failed, if --affinity was requested and failed
+    pub affinity_error: Option<String>,
+    /// Voluntary context switches during the work loop (blocking, yielding)
+    pub voluntary_ctxt: u64,
+    /// Involuntary context switches during the work loop (preemption)
+    pub involuntary_ctxt: u64,
+    /// Duration of each timed iteration, in order
+    pub iteration_times: Vec<Duration>,
+    /// Percentiles of `iteration_times`
+    pub latency: Percentiles,
+    /// Times the thread called `thread::yield_now` (see `--yield-every`)
+    pub yields: u32,
+}
+
+/// Latency percentiles of a set of iteration times, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Percentiles {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl Percentiles {
+    /// Percentiles of `samples`, in any order; all zero if there are none
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let ms = |p| percentile(&sorted, p).as_secs_f64() * 1000.0;
+        Percentiles {
+            p50_ms: ms(50.0),
+            p95_ms: ms(95.0),
+            p99_ms: ms(99.0),
+        }
+    }
+}
+
+/// The `p`-th percentile (0-100] of `sorted`, by the nearest-rank method
+///
+/// Returns the smallest sample that at least `p` percent of the samples
+/// are less than or equal to, or zero for an empty slice.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Smallest, median and largest of one per-thread time, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spread {
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+}
+
+impl Spread {
+    /// Spread of `time` across `threads`; all zero if there are none
+    ///
+    /// With an even number of threads the median is the mean of the two
+    /// middle values.
+    pub fn across(threads: &[ThreadMetrics], time: impl Fn(&ThreadMetrics) -> Duration) -> Self {
+        let mut ms: Vec<f64> = threads.iter().map(|m| time(m).as_secs_f64() * 1000.0).collect();
+        ms.sort_unstable_by(f64::total_cmp);
+
+        let (Some(&min_ms), Some(&max_ms)) = (ms.first(), ms.last()) else {
+            return Spread::default();
+        };
+        let mid = ms.len() / 2;
+        let median_ms = if ms.len().is_multiple_of(2) {
+            (ms[mid - 1] + ms[mid]) / 2.0
+        } else {
+            ms[mid]
+        };
+        Spread {
+            min_ms,
+            median_ms,
+            max_ms,
+        }
+    }
+}
+
+/// Aggregated metrics for a scheduling policy run
+#[derive(Debug)]
+pub struct PolicyMetrics {
+    pub policy: String,
+    /// Resolved `--priority` the threads were started with
+    pub priority: i32,
+    pub total_threads: usize,
+    pub avg_wait_time_ms: f64,
+    pub avg_execution_time_ms: f64,
+    pub avg_turnaround_time_ms: f64,
+    /// Min/median/max across threads behind each of the averages above
+    pub wait: Spread,
+    pub execution: Spread,
+    pub turnaround: Spread,
+    pub avg_cpu_time_ms: f64,
+    /// Total CPU time over total execution time: 1.0 when every thread
+    /// ran uninterrupted, lower when they had to share cores
+    pub cpu_efficiency: f64,
+    /// Units of work done per wall-clock second:
+    /// (threads × iterations × units_per_run) / wall_clock_secs
+    pub throughput: f64,
+    pub wall_clock_time_ms: f64,
+    /// Percentiles over every timed iteration of every thread
+    pub latency: Percentiles,
+    /// Context switches summed over all threads
+    pub total_voluntary_ctxt: u64,
+    pub total_involuntary_ctxt: u64,
+    /// Package energy used over the timed window, with --energy
+    pub energy_j: Option<f64>,
+    /// Threads that could not switch to the policy and ran as SCHED_OTHER
+    pub policy_errors: usize,
+    /// Whether any thread ended up under a policy other than the one
+    /// requested, so `policy` does not describe what was measured
+    pub downgraded: bool,
+    /// Threads that panicked and left no measurements
+    pub panicked_threads: usize,
+    /// Raw per-thread measurements, in completion order
+    pub threads: Vec<ThreadMetrics>,
+}
+
+impl PolicyMetrics {
+    /// Microjoules per unit of work, with --energy
+    pub fn energy_per_unit_uj(&self) -> Option<f64> {
+        let total_units = self.throughput * self.wall_clock_time_ms / 1000.0;
+        self.energy_j
+            .filter(|_| total_units > 0.0)
+            .map(|joules| joules * 1e6 / total_units)
+    }
+}
+
+/// Set the scheduling policy for the current thread
+///
+/// # Safety
+///
+/// This function uses unsafe libc calls to modify thread scheduling.
+/// It requires appropriate privileges for real-time policies.
+fn set_thread_scheduling(policy: SchedulingPolicy, priority: i32) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        use libc::{
+            sched_param, sched_setscheduler, SCHED_FIFO, SCHED_OTHER, SCHED_RR,
+        };
+
+        let linux_policy = match policy {
+            SchedulingPolicy::Other => SCHED_OTHER,
+            SchedulingPolicy::Fifo => SCHED_FIFO,
+            SchedulingPolicy::Rr => SCHED_RR,
+            // Needs sched_setattr, see set_deadline_scheduling
+            SchedulingPolicy::Deadline => {
+                return Err("SCHED_DEADLINE requires runtime/deadline/period parameters".to_string())
+            }
+            SchedulingPolicy::All => return Ok(()), // No-op for "all"
+        };
+
+        // For SCHED_OTHER, priority must be 0
+        // For RT policies, priority is 1-99 (checked by resolve_priority)
+        let sched_priority = match policy {
+            SchedulingPolicy::Other => 0,
+            SchedulingPolicy::Fifo | SchedulingPolicy::Rr => priority,
+            SchedulingPolicy::Deadline | SchedulingPolicy::All => 0,
+        };
+
+        let param = sched_param { sched_priority };
+
+        // 0 means current process/thread
+        let result = unsafe { sched_setscheduler(0, linux_policy, &param) };
+
+        if result == -1 {
+            let errno = std::io::Error::last_os_error();
+            return Err(format!(
+                "Failed to set scheduling policy: {} (try running with sudo)",
+                errno
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (policy, priority);
+        Err("Scheduling policies are only supported on Linux".to_string())
+    }
+}
+
+/// Set nice value for SCHED_OTHER policy
+#[cfg(target_os = "linux")]
+fn set_nice_value(nice: i32) -> Result<(), String> {
+    use libc::{setpriority, PRIO_PROCESS};
+
+    let nice_value = nice.clamp(-20, 19);
+
+    let result = unsafe { setpriority(PRIO_PROCESS, 0, nice_value) };
+
+    if result == -1 {
+        let errno = std::io::Error::last_os_error();
+        // Nice values above 0 don't require privileges
+        if nice_value < 0 {
+            return Err(format!(
+                "Failed to set nice value: {} (negative nice requires sudo)",
+                errno
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_nice_value(_nice: i32) -> Result<(), String> {
+    Ok(())
+}
+
+/// Get current scheduling policy as string
+#[cfg(target_os = "linux")]
+fn get_current_policy() -> String {
+    use libc::{sched_getscheduler, SCHED_FIFO, SCHED_OTHER, SCHED_RR};
+
+    let policy = unsafe { sched_getscheduler(0) };
+
+    match policy {
+        x if x == SCHED_OTHER => "SCHED_OTHER".to_string(),
+        x if x == SCHED_FIFO => "SCHED_FIFO".to_string(),
+        x if x == SCHED_RR => "SCHED_RR".to_string(),
+        x if x == SCHED_DEADLINE as i32 => "SCHED_DEADLINE".to_string(),
+        _ => format!("UNKNOWN({})", policy),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_current_policy() -> String {
+    "N/A".to_string()
+}
+
+/// Threads that ran under a different policy than `requested`
+fn downgraded_threads(requested: SchedulingPolicy, threads: &[ThreadMetrics]) -> usize {
+    let requested = requested.to_string();
+    threads.iter().filter(|m| m.policy != requested).count()
+}
+
+/// Warning to show when a run was downgraded: how many threads actually
+/// ran under which policy
+pub fn downgrade_warning(metrics: &PolicyMetrics) -> Option<String> {
+    if !metrics.downgraded {
+        return None;
+    }
+    let mut achieved: Vec<(&str, usize)> = Vec::new();
+    for m in &metrics.threads {
+        match achieved.iter_mut().find(|(policy, _)| *policy == m.policy) {
+            Some((_, count)) => *count += 1,
+            None => achieved.push((&m.policy, 1)),
+        }
+    }
+    let achieved: Vec<String> = achieved
+        .into_iter()
+        .map(|(policy, count)| format!("{} × {}", count, policy))
+        .collect();
+    Some(format!(
+        "{} was requested but the threads ran as {}; its results do not measure {}",
+        metrics.policy,
+        achieved.join(", "),
+        metrics.policy
+    ))
+}
+
+/// Units of work done per wall-clock second across all threads
+///
+/// Each thread runs `iterations` workloads and records the per-run unit
+/// count in `work_units`, so the total work is
+/// `threads × iterations × units_per_run`.
+fn calculate_throughput(metrics: &[ThreadMetrics], iterations: u32, wall_clock_secs: f64) -> f64 {
+    if wall_clock_secs <= 0.0 {
+        return 0.0;
+    }
+
+    let total_units: f64 = metrics
+        .iter()
+        .map(|m| m.work_units as f64 * iterations as f64)
+        .sum();
+
+    total_units / wall_clock_secs
+}
+
+/// Build a CPU set containing only `cpu`
+#[cfg(target_os = "linux")]
+fn cpu_set_for(cpu: usize) -> libc::cpu_set_t {
+    // cpu_set_t is a plain bitmask, so all-zero is the empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+    }
+    set
+}
+
+/// Pin the current thread to a single CPU
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) -> Result<(), String> {
+    let set = cpu_set_for(cpu);
+
+    // 0 means current thread
+    let result = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+
+    if result == -1 {
+        let errno = std::io::Error::last_os_error();
+        return Err(format!("Failed to pin to CPU {}: {}", cpu, errno));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_cpu: usize) -> Result<(), String> {
+    Err("CPU affinity is only supported on Linux".to_string())
+}
+
+/// CPU the current thread is running on
+#[cfg(target_os = "linux")]
+fn current_cpu() -> usize {
+    let cpu = unsafe { libc::sched_getcpu() };
+    cpu.max(0) as usize
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_cpu() -> usize {
+    0
+}
+
+/// Extract (voluntary, involuntary) context switches from a /proc status file
+fn parse_ctxt_switches(status: &str) -> (u64, u64) {
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0)
+    };
+    (
+        field("voluntary_ctxt_switches:"),
+        field("nonvoluntary_ctxt_switches:"),
+    )
+}
+
+/// Capability number of CAP_SYS_NICE (linux/capability.h)
+const CAP_SYS_NICE: u32 = 23;
+
+/// Effective capability mask (the hex `CapEff:` field) from a /proc status file
+fn parse_cap_eff(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|value| u64::from_str_radix(value.trim(), 16).ok())
+}
+
+/// Whether this process may switch its threads to a real-time policy
+///
+/// True with CAP_SYS_NICE in the effective set (root, or `setcap`), or
+/// with a non-zero RLIMIT_RTPRIO from limits.conf. The rlimit covers
+/// SCHED_FIFO and SCHED_RR only; SCHED_DEADLINE always needs the
+/// capability, and fails late without it as before.
+#[cfg(target_os = "linux")]
+pub fn check_rt_capability() -> bool {
+    let cap_sys_nice = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| parse_cap_eff(&status))
+        .is_some_and(|caps| caps & (1 << CAP_SYS_NICE) != 0);
+
+    let mut rtprio = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let rtprio_allowed = unsafe { libc::getrlimit(libc::RLIMIT_RTPRIO, &mut rtprio) } == 0 && rtprio.rlim_cur > 0;
+
+    cap_sys_nice || rtprio_allowed
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn check_rt_capability() -> bool {
+    false
+}
+
+/// CPU time consumed by the current thread so far
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+/// Context switches of the current thread so far, from /proc/self/task/<tid>/status
+///
+/// Returns (0, 0) if the file cannot be read.
+#[cfg(target_os = "linux")]
+fn read_ctxt_switches() -> (u64, u64) {
+    let tid = unsafe { libc::syscall(libc::SYS_gettid) };
+    std::fs::read_to_string(format!("/proc/self/task/{}/status", tid))
+        .map(|status| parse_ctxt_switches(&status))
+        .unwrap_or((0, 0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_ctxt_switches() -> (u64, u64) {
+    (0, 0)
+}
+
+/// Waits on the start barrier when dropped, so a thread that panics
+/// during warmup still lets the others (and the timer) start
+struct StartSignal(Option<Arc<Barrier>>);
+
+impl StartSignal {
+    fn wait(&mut self) {
+        if let Some(barrier) = self.0.take() {
+            barrier.wait();
+        }
+    }
+}
+
+impl Drop for StartSignal {
+    fn drop(&mut self) {
+        self.wait();
+    }
+}
+
+/// Run workers with a specific scheduling policy
+pub fn run_with_policy(policy: SchedulingPolicy, options: &RunOptions) -> Result<PolicyMetrics, String> {
+    run_with_policy_metered(policy, options, None)
+}
+
+/// [`run_with_policy`], also reading `rapl` around the timed window
+///
+/// The counter is read once every thread has finished its warmup and
+/// again after the last one finishes, the same window as
+/// `wall_clock_time_ms`, and the difference is returned as `energy_j`.
+pub fn run_with_policy_metered(
+    policy: SchedulingPolicy,
+    options: &RunOptions,
+    rapl: Option<&RaplCounter>,
+) -> Result<PolicyMetrics, String> {
+    let RunOptions {
+        num_threads,
+        priority,
+        limit,
+        workload,
+        iterations,
+        warmup,
+        yield_every,
+        jitter,
+        seed,
+        deadline,
+        affinity,
+        verbose,
+    } = *options;
+
+    let priority = resolve_priority(policy, priority)?;
+    // One workload per distinct limit; without --jitter all threads share one
+    let limits = jitter::jittered_limits(limit, num_threads, jitter, seed);
+    let mut workloads = BTreeMap::new();
+    for &limit in &limits {
+        if let Entry::Vacant(entry) = workloads.entry(limit) {
+            entry.insert(workload.build(limit)?);
+        }
+    }
+
+    let num_cpus = thread::available_parallelism().map_or(1, |n| n.get());
+
+    // Barrier to synchronize thread start
+    let barrier = Arc::new(Barrier::new(num_threads + 1)); // +1 for main thread
+    // Second rendezvous once every thread has warmed up; the timed window starts there
+    let started = Arc::new(Barrier::new(num_threads + 1));
+
+    // Shared storage for metrics
+    let metrics: Arc<Mutex<Vec<ThreadMetrics>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let mut handles = vec![];
+
+    // Spawn worker threads
+    for (thread_id, &limit) in limits.iter().enumerate() {
+        let barrier = Arc::clone(&barrier);
+        let mut started = StartSignal(Some(Arc::clone(&started)));
+        let metrics = Arc::clone(&metrics);
+        let workload = Arc::clone(&workloads[&limit]);
+        let thread_creation = Instant::now();
+
+        let handle = thread::spawn(move || {
+            // Record time waiting for barrier
+            let wait_start = thread_creation;
+
+            // Pin before changing policy so the thread never runs RT on the wrong core
+            let pinned_cpu = thread_id % num_cpus;
+            let affinity_error = if affinity {
+                pin_to_cpu(pinned_cpu).err()
+            } else {
+                None
+            };
+
+            // Try to set scheduling policy
+            let policy_result = match (policy, deadline) {
+                (SchedulingPolicy::Deadline, Some(params)) => set_deadline_scheduling(params),
+                _ => set_thread_scheduling(policy, priority),
+            };
+            let actual_policy = get_current_policy();
+
+            // For SCHED_OTHER, the priority is the thread's nice value
+            if policy == SchedulingPolicy::Other && priority != 0 {
+                let _ = set_nice_value(priority);
+            }
+
+            // Wait for all threads to be ready
+            barrier.wait();
+
+            let wait_time = wait_start.elapsed();
+
+            // Un-timed warmup: results are discarded
+            let warmup_start = Instant::now();
+            for _ in 0..warmup {
+                workload.run();
+            }
+            started.wait();
+            let warmup_time = warmup_start.elapsed();
+
+            let ctxt_before = read_ctxt_switches();
+            let cpu_start = thread_cpu_time();
+            let exec_start = Instant::now();
+
+            // Do the actual work
+            let mut units_per_run = 0;
+            let mut iteration_times = Vec::with_capacity(iterations as usize);
+            let mut yields = 0;
+            for done in 1..=iterations {
+                let iteration_start = Instant::now();
+                units_per_run = workload.run();
+                iteration_times.push(iteration_start.elapsed());
+
+                // Give up the CPU voluntarily, if asked to
+                if yield_every > 0 && done.is_multiple_of(yield_every) {
+                    thread::yield_now();
+                    yields += 1;
+                }
+            }
+
+            let execution_time = exec_start.elapsed();
+            let cpu_time = thread_cpu_time().saturating_sub(cpu_start);
+            let turnaround_time = thread_creation.elapsed().saturating_sub(warmup_time);
+            let ctxt_after = read_ctxt_switches();
+            let cpu = if affinity { pinned_cpu } else { current_cpu() };
+
+            // Store metrics
+            let thread_metrics = ThreadMetrics {
+                thread_id,
+                policy: actual_policy,
+                priority,
+                limit,
+                wait_time,
+                warmup_time,
+                execution_time,
+                cpu_time,
+                turnaround_time,
+                work_units: units_per_run,
+                cpu,
+                affinity_error,
+                voluntary_ctxt: ctxt_after.0.saturating_sub(ctxt_before.0),
+                involuntary_ctxt: ctxt_after.1.saturating_sub(ctxt_before.1),
+                latency: Percentiles::from_samples(&iteration_times),
+                iteration_times,
+                yields,
+            };
+
+            let mut guard = metrics.lock().unwrap();
+            guard.push(thread_metrics);
+
+            policy_result
+        });
+
+        handles.push(handle);
+    }
+
+    // Release all threads simultaneously, then start timing once they have all warmed up
+    barrier.wait();
+    started.wait();
+    let parallel_start = Instant::now();
+    let energy_before = rapl.and_then(|counter| counter.read().ok());
+
+    // Wait for all threads and collect any errors
+    let mut errors = vec![];
+    let mut panicked_threads = 0;
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => errors.push(e),
+            Err(_) => {
+                errors.push("Thread panicked".to_string());
+                panicked_threads += 1;
+            }
+        }
+    }
+
+    let wall_clock_time = parallel_start.elapsed();
+    let energy_j = rapl
+        .zip(energy_before)
+        .and_then(|(counter, before)| counter.read().ok().map(|after| counter.joules_between(before, after)));
+
+    // Report errors but continue
+    if !errors.is_empty() && verbose {
+        eprintln!("Warning: {}", errors[0]);
+    }
+
+    // Calculate aggregate metrics
+    let metrics_guard = metrics.lock().unwrap();
+
+    if metrics_guard.is_empty() {
+        return Err(errors
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "No metrics collected".to_string()));
+    }
+
+    let total_threads = metrics_guard.len();
+
+    let avg_wait_time_ms: f64 = metrics_guard
+        .iter()
+        .map(|m| m.wait_time.as_secs_f64() * 1000.0)
+        .sum::<f64>()
+        / total_threads as f64;
+
+    let avg_execution_time_ms: f64 = metrics_guard
+        .iter()
+        .map(|m| m.execution_time.as_secs_f64() * 1000.0)
+        .sum::<f64>()
+        / total_threads as f64;
+
+    let avg_turnaround_time_ms: f64 = metrics_guard
+        .iter()
+        .map(|m| m.turnaround_time.as_secs_f64() * 1000.0)
+        .sum::<f64>()
+        / total_threads as f64;
+
+    let total_cpu_secs: f64 = metrics_guard.iter().map(|m| m.cpu_time.as_secs_f64()).sum();
+    let total_execution_secs: f64 = metrics_guard.iter().map(|m| m.execution_time.as_secs_f64()).sum();
+    let avg_cpu_time_ms = total_cpu_secs * 1000.0 / total_threads as f64;
+    let cpu_efficiency = if total_execution_secs > 0.0 {
+        total_cpu_secs / total_execution_secs
+    } else {
+        0.0
+    };
+
+    let wall_clock_secs = wall_clock_time.as_secs_f64();
+    let throughput = calculate_throughput(&metrics_guard, iterations, wall_clock_secs);
+    let all_iterations: Vec<Duration> = metrics_guard.iter().flat_map(|m| m.iteration_times.iter().copied()).collect();
+
+    let policy_metrics = PolicyMetrics {
+        policy: policy.to_string(),
+        priority,
+        total_threads,
+        avg_wait_time_ms,
+        avg_execution_time_ms,
+        avg_turnaround_time_ms,
+        wait: Spread::across(&metrics_guard, |m| m.wait_time),
+        execution: Spread::across(&metrics_guard, |m| m.execution_time),
+        turnaround: Spread::across(&metrics_guard, |m| m.turnaround_time),
+        avg_cpu_time_ms,
+        cpu_efficiency,
+        throughput,
+        wall_clock_time_ms: wall_clock_time.as_secs_f64() * 1000.0,
+        latency: Percentiles::from_samples(&all_iterations),
+        total_voluntary_ctxt: metrics_guard.iter().map(|m| m.voluntary_ctxt).sum(),
+        total_involuntary_ctxt: metrics_guard.iter().map(|m| m.involuntary_ctxt).sum(),
+        energy_j,
+        policy_errors: errors.len() - panicked_threads,
+        downgraded: downgraded_threads(policy, &metrics_guard) > 0,
+        panicked_threads,
+        threads: metrics_guard.clone(),
+    };
+
+    Ok(policy_metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(num_threads: usize, limit: u64, iterations: u32) -> RunOptions {
+        RunOptions {
+            num_threads,
+            priority: None,
+            limit,
+            workload: WorkloadKind::Primes,
+            iterations,
+            warmup: 0,
+            yield_every: 1,
+            jitter: 0,
+            seed: 0,
+            deadline: None,
+            affinity: false,
+            verbose: false,
+        }
+    }
+
+    #[test]
+    fn test_scheduling_policy_display() {
+        assert_eq!(format!("{}", SchedulingPolicy::Other), "SCHED_OTHER");
+        assert_eq!(format!("{}", SchedulingPolicy::Fifo), "SCHED_FIFO");
+        assert_eq!(format!("{}", SchedulingPolicy::Rr), "SCHED_RR");
+    }
+
+    #[test]
+    fn test_run_with_default_policy() {
+        // This should always work without privileges
+        let result = run_with_policy(SchedulingPolicy::Other, &options(2, 10000, 1));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_throughput_counts_each_run_once() {
+        // π(10_000) = 1229, 2 threads × 2 iterations
+        let metrics = run_with_policy(SchedulingPolicy::Other, &options(2, 10_000, 2)).unwrap();
+        let expected = (2.0 * 2.0 * 1229.0) / (metrics.wall_clock_time_ms / 1000.0);
+        let relative_error = (metrics.throughput - expected).abs() / expected;
+        assert!(
+            relative_error < 1e-6,
+            "throughput {} != expected {}",
+            metrics.throughput,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 95.0), Duration::from_millis(95));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&[], 95.0), Duration::ZERO);
+
+        // Unsorted input is sorted first; a single sample is every percentile
+        let latency = Percentiles::from_samples(&[Duration::from_millis(3), Duration::from_millis(1)]);
+        assert_eq!((latency.p50_ms, latency.p99_ms), (1.0, 3.0));
+        assert_eq!(Percentiles::from_samples(&[Duration::from_millis(7)]).p50_ms, 7.0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_thread_cpu_time_increases() {
+        let before = thread_cpu_time();
+        let mut x = 0u64;
+        for i in 0..5_000_000u64 {
+            x = std::hint::black_box(x.wrapping_add(i));
+        }
+        let after = thread_cpu_time();
+        assert!(after > before, "{:?} -> {:?}", before, after);
+        assert!(thread_cpu_time() >= after);
+    }
+
+    #[test]
+    fn test_priority_validated_per_policy() {
+        assert_eq!(resolve_priority(SchedulingPolicy::Fifo, Some(99)), Ok(99));
+        assert_eq!(resolve_priority(SchedulingPolicy::Rr, None), Ok(50));
+        assert_eq!(resolve_priority(SchedulingPolicy::Other, Some(-20)), Ok(-20));
+        assert_eq!(resolve_priority(SchedulingPolicy::Other, None), Ok(0));
+
+        let err = resolve_priority(SchedulingPolicy::Fifo, Some(0)).unwrap_err();
+        assert!(err.contains("SCHED_FIFO") && err.contains("1 to 99"), "{}", err);
+        let err = resolve_priority(SchedulingPolicy::Other, Some(50)).unwrap_err();
+        assert!(err.contains("-20 to 19"), "{}", err);
+
+        // --policy all checks each policy as it runs
+        let options = RunOptions {
+            priority: Some(50),
+            ..options(1, 1000, 1)
+        };
+        assert!(run_with_policy(SchedulingPolicy::Other, &options).is_err());
+    }
+
+    #[test]
+    fn test_parse_cap_eff() {
+        let root = "Name:\tscheduler-sim\nCapInh:\t0000000000000000\nCapPrm:\t000001ffffffffff\n\
+                    CapEff:\t000001ffffffffff\nCapBnd:\t000001ffffffffff\n";
+        let caps = parse_cap_eff(root).unwrap();
+        assert_ne!(caps & (1 << CAP_SYS_NICE), 0);
+
+        let user = "CapPrm:\t0000000000000000\nCapEff:\t0000000000000000\n";
+        assert_eq!(parse_cap_eff(user), Some(0));
+        assert_eq!(parse_cap_eff("CapEff:\t0000000000800000\n"), Some(1 << CAP_SYS_NICE));
+        assert_eq!(parse_cap_eff("Name:\tfoo\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sched_attr_layout() {
+        let params = DeadlineParams {
+            runtime_ns: 10_000_000,
+            deadline_ns: 30_000_000,
+            period_ns: 100_000_000,
+        };
+        let attr = SchedAttr::deadline(params);
+        // SCHED_ATTR_SIZE_VER0 in the kernel headers
+        assert_eq!(attr.size, 48);
+        assert_eq!(attr.sched_policy, 6);
+        assert_eq!(attr.sched_runtime, 10_000_000);
+        assert_eq!(attr.sched_deadline, 30_000_000);
+        assert_eq!(attr.sched_period, 100_000_000);
+        assert_eq!((attr.sched_flags, attr.sched_nice, attr.sched_priority), (0, 0, 0));
+
+        // Usually fails without CAP_SYS_NICE; either way it must not crash
+        let _ = thread::spawn(move || set_deadline_scheduling(params)).join();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cpu_set_for() {
+        let set = cpu_set_for(3);
+        for cpu in 0..64 {
+            assert_eq!(unsafe { libc::CPU_ISSET(cpu, &set) }, cpu == 3, "cpu {}", cpu);
+        }
+        assert_eq!(unsafe { libc::CPU_COUNT(&set) }, 1);
+    }
+
+    #[test]
+    fn test_parse_ctxt_switches() {
+        let status = "Name:\tscheduler-sim\nState:\tR (running)\n\
+                      voluntary_ctxt_switches:\t12\nnonvoluntary_ctxt_switches:\t3\n";
+        assert_eq!(parse_ctxt_switches(status), (12, 3));
+        assert_eq!(parse_ctxt_switches("Name:\tfoo\n"), (0, 0));
+    }
+
+    #[test]
+    fn test_yield_every() {
+        let run = |yield_every| {
+            let options = RunOptions {
+                yield_every,
+                ..options(2, 10_000, 6)
+            };
+            run_with_policy(SchedulingPolicy::Other, &options).unwrap()
+        };
+
+        // 0 skips yield_now altogether and still counts π(10_000) = 1229
+        for (yield_every, expected_yields) in [(0, 0), (1, 6), (4, 1), (6, 1), (7, 0)] {
+            let metrics = run(yield_every);
+            for thread in &metrics.threads {
+                assert_eq!(thread.yields, expected_yields, "--yield-every {}", yield_every);
+                assert_eq!(thread.work_units, 1229);
+            }
+        }
+    }
+
+    #[test]
+    fn test_jitter_sets_each_threads_limit() {
+        let options = RunOptions {
+            jitter: 50,
+            seed: 9,
+            ..options(4, 10_000, 1)
+        };
+        let mut metrics = run_with_policy(SchedulingPolicy::Other, &options).unwrap();
+        metrics.threads.sort_by_key(|m| m.thread_id);
+
+        let limits: Vec<u64> = metrics.threads.iter().map(|m| m.limit).collect();
+        assert_eq!(limits, jitter::jittered_limits(10_000, 4, 50, 9));
+        for m in &metrics.threads {
+            assert_eq!(m.work_units, primes_core::count_primes(m.limit).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_downgrade_detection() {
+        let thread = |policy: &str| ThreadMetrics {
+            policy: policy.to_string(),
+            ..ThreadMetrics::default()
+        };
+        let fifo = [thread("SCHED_FIFO"), thread("SCHED_FIFO")];
+        let mixed = [thread("SCHED_FIFO"), thread("SCHED_OTHER"), thread("SCHED_OTHER")];
+
+        assert_eq!(downgraded_threads(SchedulingPolicy::Fifo, &fifo), 0);
+        assert_eq!(downgraded_threads(SchedulingPolicy::Fifo, &mixed), 2);
+        assert_eq!(downgraded_threads(SchedulingPolicy::Rr, &fifo), 2);
+        assert_eq!(downgraded_threads(SchedulingPolicy::Other, &[thread("SCHED_OTHER")]), 0);
+
+        // SCHED_OTHER always succeeds, so it is never downgraded
+        let metrics = run_with_policy(SchedulingPolicy::Other, &options(2, 10_000, 1)).unwrap();
+        assert!(!metrics.downgraded);
+        assert_eq!(downgrade_warning(&metrics), None);
+
+        let metrics = PolicyMetrics {
+            policy: "SCHED_FIFO".to_string(),
+            downgraded: true,
+            threads: mixed.to_vec(),
+            ..metrics
+        };
+        assert_eq!(
+            downgrade_warning(&metrics).unwrap(),
+            "SCHED_FIFO was requested but the threads ran as 1 × SCHED_FIFO, 2 × SCHED_OTHER; \
+             its results do not measure SCHED_FIFO"
+        );
+    }
+
+    #[test]
+    fn test_spread_across_threads() {
+        let thread = |wait_ms, exec_ms| ThreadMetrics {
+            wait_time: Duration::from_millis(wait_ms),
+            execution_time: Duration::from_millis(exec_ms),
+            ..ThreadMetrics::default()
+        };
+
+        // Odd count: the middle value, whatever the input order
+        let threads = [thread(5, 40), thread(1, 10), thread(3, 30)];
+        let wait = Spread::across(&threads, |m| m.wait_time);
+        assert_eq!((wait.min_ms, wait.median_ms, wait.max_ms), (1.0, 3.0, 5.0));
+
+        // Even count: the mean of the two middle values
+        let threads = [thread(4, 40), thread(1, 10), thread(2, 20), thread(8, 30)];
+        let execution = Spread::across(&threads, |m| m.execution_time);
+        assert_eq!((execution.min_ms, execution.median_ms, execution.max_ms), (10.0, 25.0, 40.0));
+        assert_eq!(Spread::across(&threads, |m| m.wait_time).median_ms, 3.0);
+
+        assert_eq!(Spread::across(&[], |m| m.wait_time), Spread::default());
+    }
+
+    #[test]
+    fn test_warmup_is_not_timed() {
+        let cold_options = options(1, 1_000_000, 1);
+        let warm_options = RunOptions {
+            warmup: 4,
+            ..cold_options
+        };
+        let metrics = run_with_policy(SchedulingPolicy::Other, &warm_options).unwrap();
+        let thread = &metrics.threads[0];
+        assert_eq!(thread.work_units, 78_498); // π(1_000_000)
+        assert_eq!(thread.iteration_times.len(), 1);
+        // One timed sieve against four warmup sieves
+        assert!(thread.execution_time < thread.warmup_time);
+        assert!(thread.turnaround_time < thread.warmup_time + thread.execution_time + thread.wait_time);
+
+        // The run-wide clock starts after the warmup too, so it and the
+        // throughput it gives match a run without warmup, not one five sieves long
+        let wall_ms = metrics.wall_clock_time_ms;
+        assert!(wall_ms < thread.warmup_time.as_secs_f64() * 1000.0, "{:?}", metrics);
+        let cold = run_with_policy(SchedulingPolicy::Other, &cold_options).unwrap();
+        assert!(wall_ms < 2.0 * cold.wall_clock_time_ms, "{} vs {}", wall_ms, cold.wall_clock_time_ms);
+        assert!(metrics.throughput > cold.throughput / 2.0, "{} vs {}", metrics.throughput, cold.throughput);
+    }
+}
@@ -1,7 +1,8 @@
 //! Process Scheduling Simulator
 //!
 //! This application demonstrates different CPU scheduling algorithms
-//! available in Linux. It runs prime calculation tasks under various
+//! available in Linux. It runs prime calculation tasks (or another
+//! `--workload`) under various
 //! scheduling policies and measures performance metrics.
 //!
 //! # Supported Scheduling Policies
@@ -9,407 +10,290 @@
 //! - SCHED_OTHER: Default Linux time-sharing scheduler (CFS)
 //! - SCHED_FIFO: Real-time First-In-First-Out scheduler
 //! - SCHED_RR: Real-time Round-Robin scheduler
+//! - SCHED_DEADLINE: Earliest Deadline First with runtime/deadline/period budgets
 //!
 //! # Requirements
 //!
-//! Real-time policies (SCHED_FIFO, SCHED_RR) require either:
+//! Real-time policies (SCHED_FIFO, SCHED_RR, SCHED_DEADLINE) require either:
 //! - Root privileges (sudo)
 //! - CAP_SYS_NICE capability
 //! - Proper limits in /etc/security/limits.conf
-
-use clap::{Parser, ValueEnum};
-use std::sync::{Arc, Barrier, Mutex};
-use std::thread;
-use std::time::{Duration, Instant};
-
-/// Scheduling policy options
-#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
-enum SchedulingPolicy {
-    /// Default Linux CFS scheduler (SCHED_OTHER)
-    Other,
-    /// Real-time FIFO scheduler (SCHED_FIFO)
-    Fifo,
-    /// Real-time Round-Robin scheduler (SCHED_RR)
-    Rr,
-    /// Run all policies for comparison
-    All,
+//!
+//! The runs themselves live in the `scheduler_sim` library; this binary
+//! parses the flags and prints what [`run_with_policy_metered`] returns.
+
+mod cgroup;
+mod color;
+mod config;
+
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use color::{ColorChoice, Palette};
+use primes_core::{clock_seed, physical_cores, resolve_threads};
+use scheduler_sim::energy::{self, RaplCounter};
+use scheduler_sim::workload::WorkloadKind;
+use scheduler_sim::{
+    check_rt_capability, downgrade_warning, resolve_priority, run_with_policy_metered, DeadlineParams, PolicyMetrics,
+    RunOptions, SchedulingPolicy,
+};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Process exit codes, listed in `--help`
+mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// A real-time policy could not be set (missing CAP_SYS_NICE)
+    pub const PRIVILEGE_DENIED: i32 = 2;
+    /// A worker thread panicked, or a policy run produced no results
+    pub const WORKER_FAILURE: i32 = 3;
+    /// Bad command line, config file or flag combination
+    pub const INVALID_ARGS: i32 = 4;
+
+    pub const HELP: &str = "Exit codes:\n  \
+        0  success\n  \
+        2  privilege denied: a real-time policy could not be set\n  \
+        3  worker failure: a thread panicked or a policy run failed\n  \
+        4  invalid arguments";
 }
 
-impl std::fmt::Display for SchedulingPolicy {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SchedulingPolicy::Other => write!(f, "SCHED_OTHER"),
-            SchedulingPolicy::Fifo => write!(f, "SCHED_FIFO"),
-            SchedulingPolicy::Rr => write!(f, "SCHED_RR"),
-            SchedulingPolicy::All => write!(f, "ALL"),
-        }
-    }
+/// Print a clap error (or help/version) and exit with the matching code
+fn exit_on_clap_error(e: clap::Error) -> ! {
+    let _ = e.print();
+    std::process::exit(if e.use_stderr() {
+        exit_code::INVALID_ARGS
+    } else {
+        exit_code::SUCCESS
+    })
 }
 
 /// Scheduler simulation for prime calculation
 #[derive(Parser, Debug)]
 #[command(name = "scheduler-sim")]
 #[command(about = "Demonstrate CPU scheduling algorithms", long_about = None)]
+#[command(after_help = exit_code::HELP)]
 struct Args {
     /// Scheduling policy to use
     #[arg(short, long, value_enum, default_value_t = SchedulingPolicy::Other)]
     policy: SchedulingPolicy,
 
-    /// Number of worker threads
-    #[arg(short, long, default_value_t = 4)]
-    threads: usize,
+    /// Number of worker threads (default: available parallelism, or the cgroup CPU quota with --respect-cgroup)
+    #[arg(short, long)]
+    threads: Option<usize>,
 
-    /// Priority level (1-99 for RT policies, -20 to 19 for SCHED_OTHER nice)
-    #[arg(short = 'P', long, default_value_t = 50)]
-    priority: i32,
+    /// Priority level: 1-99 for FIFO/RR (default 50), nice -20 to 19 for OTHER (default 0)
+    #[arg(short = 'P', long, allow_negative_numbers = true)]
+    priority: Option<i32>,
 
-    /// Upper limit for prime calculation
+    /// Upper limit for prime calculation (the problem size for other workloads)
     #[arg(short, long, default_value_t = 5_000_000)]
     limit: u64,
 
+    /// Work each thread runs: primes, fib (CPU-bound), matrix (memory-bound) or sleep (I/O-like)
+    #[arg(short, long, value_enum, default_value_t = WorkloadKind::Primes)]
+    workload: WorkloadKind,
+
     /// Number of iterations per thread
     #[arg(short, long, default_value_t = 3)]
     iterations: u32,
 
-    /// Output in CSV format
-    #[arg(long, default_value_t = false)]
-    csv: bool,
+    /// Un-timed iterations per thread before the timed loop (warms caches and the allocator)
+    #[arg(long, default_value_t = 0)]
+    warmup: u32,
 
-    /// Verbose output with per-thread details
-    #[arg(short, long, default_value_t = false)]
-    verbose: bool,
-}
-
-/// Metrics collected for each thread
-#[derive(Debug, Clone)]
-struct ThreadMetrics {
-    thread_id: usize,
-    policy: String,
-    priority: i32,
-    /// Time from thread creation to first execution
-    wait_time: Duration,
-    /// Time to complete all work
-    execution_time: Duration,
-    /// Total time from creation to completion
-    turnaround_time: Duration,
-    /// Number of primes found
-    primes_found: usize,
-}
+    /// Call sched_yield after every N timed iterations; 0 never yields, as FIFO/RR threads would not
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    yield_every: u32,
 
-/// Aggregated metrics for a scheduling policy run
-#[derive(Debug)]
-struct PolicyMetrics {
-    policy: String,
-    total_threads: usize,
-    avg_wait_time_ms: f64,
-    avg_execution_time_ms: f64,
-    avg_turnaround_time_ms: f64,
-    throughput: f64, // primes per second
-    wall_clock_time_ms: f64,
-}
+    /// Vary each thread's --limit by up to ±PERCENT, so tasks differ in length
+    #[arg(long, value_name = "PERCENT", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=100))]
+    jitter: u32,
 
-/// Simple sieve for calculating primes
-fn calculate_primes(limit: u64) -> Vec<u64> {
-    if limit < 2 {
-        return vec![];
-    }
+    /// Seed for --jitter, to repeat a run's per-thread limits (default: from the clock)
+    #[arg(long, requires = "jitter")]
+    seed: Option<u64>,
 
-    let mut is_prime = vec![true; (limit + 1) as usize];
-    is_prime[0] = false;
-    is_prime[1] = false;
-
-    let sqrt_limit = (limit as f64).sqrt() as u64;
+    /// Output in CSV format
+    #[arg(long, default_value_t = false)]
+    csv: bool,
 
-    for num in 2..=sqrt_limit {
-        if is_prime[num as usize] {
-            let mut multiple = num * num;
-            while multiple <= limit {
-                is_prime[multiple as usize] = false;
-                multiple += num;
-            }
-        }
-    }
+    /// Leave out the CSV header line, e.g. when appending to an existing file
+    #[arg(long, default_value_t = false)]
+    no_header: bool,
 
-    is_prime
-        .iter()
-        .enumerate()
-        .filter(|(_, &prime)| prime)
-        .map(|(idx, _)| idx as u64)
-        .collect()
-}
+    /// Skip the banners; without --csv print one `policy=P units=N time=Xms` line per policy
+    #[arg(short, long, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
 
-/// Set the scheduling policy for the current thread
-///
-/// # Safety
-///
-/// This function uses unsafe libc calls to modify thread scheduling.
-/// It requires appropriate privileges for real-time policies.
-fn set_thread_scheduling(policy: SchedulingPolicy, priority: i32) -> Result<(), String> {
-    #[cfg(target_os = "linux")]
-    {
-        use libc::{
-            sched_param, sched_setscheduler, SCHED_FIFO, SCHED_OTHER, SCHED_RR,
-        };
+    /// Color the comparison highlights: auto (tty and no NO_COLOR), always, or never
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 
-        let linux_policy = match policy {
-            SchedulingPolicy::Other => SCHED_OTHER,
-            SchedulingPolicy::Fifo => SCHED_FIFO,
-            SchedulingPolicy::Rr => SCHED_RR,
-            SchedulingPolicy::All => return Ok(()), // No-op for "all"
-        };
+    /// Verbose output with per-thread details
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
 
-        // For SCHED_OTHER, priority must be 0
-        // For RT policies, priority must be 1-99
-        let sched_priority = match policy {
-            SchedulingPolicy::Other => 0,
-            SchedulingPolicy::Fifo | SchedulingPolicy::Rr => priority.clamp(1, 99),
-            SchedulingPolicy::All => 0,
-        };
+    /// CPU time each thread may use per period, in ns (SCHED_DEADLINE only)
+    #[arg(long)]
+    runtime_ns: Option<u64>,
 
-        let param = sched_param {
-            sched_priority: sched_priority,
-        };
+    /// Relative deadline for each period's runtime, in ns (SCHED_DEADLINE only)
+    #[arg(long)]
+    deadline_ns: Option<u64>,
 
-        // 0 means current process/thread
-        let result = unsafe { sched_setscheduler(0, linux_policy, &param) };
+    /// Length of each period, in ns (SCHED_DEADLINE only)
+    #[arg(long)]
+    period_ns: Option<u64>,
 
-        if result == -1 {
-            let errno = std::io::Error::last_os_error();
-            return Err(format!(
-                "Failed to set scheduling policy: {} (try running with sudo)",
-                errno
-            ));
-        }
+    /// Pin worker thread i to CPU (i % number of CPUs)
+    #[arg(long, default_value_t = false)]
+    affinity: bool,
 
-        Ok(())
-    }
+    /// Fail up front if a real-time policy is requested without the privilege to set it
+    #[arg(long, default_value_t = false)]
+    strict: bool,
 
-    #[cfg(not(target_os = "linux"))]
-    {
-        let _ = (policy, priority);
-        Err("Scheduling policies are only supported on Linux".to_string())
-    }
-}
+    /// Report the CPU package energy used by each policy run (Intel RAPL)
+    #[arg(long, default_value_t = false)]
+    energy: bool,
 
-/// Set nice value for SCHED_OTHER policy
-#[cfg(target_os = "linux")]
-fn set_nice_value(nice: i32) -> Result<(), String> {
-    use libc::{setpriority, PRIO_PROCESS};
+    /// TOML file with policy, threads, priority, limit and iterations; flags override it
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-    let nice_value = nice.clamp(-20, 19);
+    /// Append one row per policy run to this CSV file (created with a header if absent), for plotting
+    #[arg(long, value_name = "PATH")]
+    out_csv: Option<PathBuf>,
 
-    let result = unsafe { setpriority(PRIO_PROCESS, 0, nice_value) };
+    /// Without --threads, start no more threads than the cgroup v2 CPU quota (cpu.max) allows
+    #[arg(long, default_value_t = false)]
+    respect_cgroup: bool,
+}
 
-    if result == -1 {
-        let errno = std::io::Error::last_os_error();
-        // Nice values above 0 don't require privileges
-        if nice_value < 0 {
-            return Err(format!(
-                "Failed to set nice value: {} (negative nice requires sudo)",
-                errno
-            ));
+/// Parse the command line, filling in flags it leaves out from `--config`
+fn parse_args_from<I, T>(itr: I) -> Result<Args, String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = Args::command()
+        .try_get_matches_from(itr)
+        .unwrap_or_else(|e| exit_on_clap_error(e));
+    let mut args = Args::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+
+    if let Some(path) = &args.config {
+        let file = config::load(path)?;
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+        if let Some(policy) = file.policy.filter(|_| unset("policy")) {
+            args.policy = policy;
+        }
+        if let Some(threads) = file.threads.filter(|_| unset("threads")) {
+            args.threads = Some(threads);
+        }
+        if let Some(priority) = file.priority.filter(|_| unset("priority")) {
+            args.priority = Some(priority);
+        }
+        if let Some(limit) = file.limit.filter(|_| unset("limit")) {
+            args.limit = limit;
+        }
+        if let Some(iterations) = file.iterations.filter(|_| unset("iterations")) {
+            args.iterations = iterations;
         }
     }
 
-    Ok(())
-}
-
-#[cfg(not(target_os = "linux"))]
-fn set_nice_value(_nice: i32) -> Result<(), String> {
-    Ok(())
+    Ok(args)
 }
 
-/// Get current scheduling policy as string
-#[cfg(target_os = "linux")]
-fn get_current_policy() -> String {
-    use libc::{sched_getscheduler, SCHED_FIFO, SCHED_OTHER, SCHED_RR};
-
-    let policy = unsafe { sched_getscheduler(0) };
-
-    match policy {
-        x if x == SCHED_OTHER => "SCHED_OTHER".to_string(),
-        x if x == SCHED_FIFO => "SCHED_FIFO".to_string(),
-        x if x == SCHED_RR => "SCHED_RR".to_string(),
-        _ => format!("UNKNOWN({})", policy),
+/// Collect the SCHED_DEADLINE flags, checking runtime <= deadline <= period
+///
+/// Returns `Ok(None)` for every other policy.
+fn deadline_params(args: &Args) -> Result<Option<DeadlineParams>, String> {
+    if args.policy != SchedulingPolicy::Deadline {
+        return Ok(None);
     }
-}
-
-#[cfg(not(target_os = "linux"))]
-fn get_current_policy() -> String {
-    "N/A".to_string()
-}
-
-/// Run workers with a specific scheduling policy
-fn run_with_policy(
-    policy: SchedulingPolicy,
-    num_threads: usize,
-    priority: i32,
-    limit: u64,
-    iterations: u32,
-    verbose: bool,
-) -> Result<PolicyMetrics, String> {
-    // Barrier to synchronize thread start
-    let barrier = Arc::new(Barrier::new(num_threads + 1)); // +1 for main thread
-
-    // Shared storage for metrics
-    let metrics: Arc<Mutex<Vec<ThreadMetrics>>> = Arc::new(Mutex::new(Vec::new()));
-
-    let mut handles = vec![];
-
-    // Spawn worker threads
-    for thread_id in 0..num_threads {
-        let barrier = Arc::clone(&barrier);
-        let metrics = Arc::clone(&metrics);
-        let thread_creation = Instant::now();
-
-        let handle = thread::spawn(move || {
-            // Record time waiting for barrier
-            let wait_start = thread_creation;
-
-            // Try to set scheduling policy
-            let policy_result = set_thread_scheduling(policy, priority);
-            let actual_policy = get_current_policy();
-
-            // For SCHED_OTHER, also try to set nice value
-            if policy == SchedulingPolicy::Other && priority != 0 {
-                // Map priority 1-99 to nice -20 to 19
-                let nice = ((priority as f64 / 99.0) * 39.0 - 20.0) as i32;
-                let _ = set_nice_value(nice);
-            }
-
-            // Wait for all threads to be ready
-            barrier.wait();
-
-            let wait_time = wait_start.elapsed();
-            let exec_start = Instant::now();
-
-            // Do the actual work
-            let mut total_primes = 0;
-            for _ in 0..iterations {
-                let primes = calculate_primes(limit);
-                total_primes = primes.len();
-
-                // Small yield to allow context switches
-                thread::yield_now();
-            }
 
-            let execution_time = exec_start.elapsed();
-            let turnaround_time = thread_creation.elapsed();
-
-            // Store metrics
-            let thread_metrics = ThreadMetrics {
-                thread_id,
-                policy: actual_policy,
-                priority,
-                wait_time,
-                execution_time,
-                turnaround_time,
-                primes_found: total_primes,
-            };
-
-            let mut guard = metrics.lock().unwrap();
-            guard.push(thread_metrics);
-
-            policy_result
-        });
+    let (Some(runtime_ns), Some(deadline_ns), Some(period_ns)) =
+        (args.runtime_ns, args.deadline_ns, args.period_ns)
+    else {
+        return Err("--policy deadline requires --runtime-ns, --deadline-ns and --period-ns".to_string());
+    };
 
-        handles.push(handle);
+    if runtime_ns == 0 || runtime_ns > deadline_ns || deadline_ns > period_ns {
+        return Err(format!(
+            "SCHED_DEADLINE needs 0 < runtime ({}) <= deadline ({}) <= period ({})",
+            runtime_ns, deadline_ns, period_ns
+        ));
     }
 
-    // Release all threads simultaneously
-    barrier.wait();
-    let parallel_start = Instant::now();
-
-    // Wait for all threads and collect any errors
-    let mut errors = vec![];
-    for handle in handles {
-        match handle.join() {
-            Ok(Ok(())) => {}
-            Ok(Err(e)) => errors.push(e),
-            Err(_) => errors.push("Thread panicked".to_string()),
-        }
-    }
-
-    let wall_clock_time = parallel_start.elapsed();
-
-    // Report errors but continue
-    if !errors.is_empty() && verbose {
-        eprintln!("Warning: {}", errors[0]);
-    }
+    Ok(Some(DeadlineParams {
+        runtime_ns,
+        deadline_ns,
+        period_ns,
+    }))
+}
 
-    // Calculate aggregate metrics
-    let metrics_guard = metrics.lock().unwrap();
+/// Print the per-thread table shown with --verbose
+fn print_thread_details(metrics: &PolicyMetrics) {
+    println!("\n  Per-thread metrics:");
+    println!(
+        "  {:>4} {:>14} {:>10} {:>5} {:>12} {:>12} {:>12} {:>12} {:>10} {:>8} {:>8} {:>7}",
+        "ID", "Policy", "Priority", "CPU", "Limit", "Wait(ms)", "Exec(ms)", "Turnaround(ms)", "p99(ms)", "VolCS", "InvolCS", "Yields"
+    );
+    println!("  {}", "─".repeat(126));
 
-    if metrics_guard.is_empty() {
-        return Err("No metrics collected".to_string());
+    for m in &metrics.threads {
+        println!(
+            "  {:>4} {:>14} {:>10} {:>5} {:>12} {:>12.3} {:>12.3} {:>12.3} {:>10.3} {:>8} {:>8} {:>7}",
+            m.thread_id,
+            m.policy,
+            m.priority,
+            m.cpu,
+            m.limit,
+            m.wait_time.as_secs_f64() * 1000.0,
+            m.execution_time.as_secs_f64() * 1000.0,
+            m.turnaround_time.as_secs_f64() * 1000.0,
+            m.latency.p99_ms,
+            m.voluntary_ctxt,
+            m.involuntary_ctxt,
+            m.yields,
+        );
     }
 
-    let total_threads = metrics_guard.len();
-
-    let avg_wait_time_ms: f64 = metrics_guard
-        .iter()
-        .map(|m| m.wait_time.as_secs_f64() * 1000.0)
-        .sum::<f64>()
-        / total_threads as f64;
-
-    let avg_execution_time_ms: f64 = metrics_guard
-        .iter()
-        .map(|m| m.execution_time.as_secs_f64() * 1000.0)
-        .sum::<f64>()
-        / total_threads as f64;
-
-    let avg_turnaround_time_ms: f64 = metrics_guard
-        .iter()
-        .map(|m| m.turnaround_time.as_secs_f64() * 1000.0)
-        .sum::<f64>()
-        / total_threads as f64;
-
-    let total_primes: usize = metrics_guard.iter().map(|m| m.primes_found).sum();
-
-    let wall_clock_secs = wall_clock_time.as_secs_f64();
-    let throughput = if wall_clock_secs > 0.0 {
-        (total_primes as f64 * iterations as f64) / wall_clock_secs
-    } else {
-        0.0
-    };
-
-    // Print per-thread details if verbose
-    if verbose {
-        println!("\n  Per-thread metrics:");
+    // Warmup runs are excluded from every column above
+    let warmup_ms: f64 = metrics.threads.iter().map(|m| m.warmup_time.as_secs_f64() * 1000.0).sum();
+    if warmup_ms > 0.0 {
         println!(
-            "  {:>4} {:>14} {:>10} {:>12} {:>12} {:>12}",
-            "ID", "Policy", "Priority", "Wait(ms)", "Exec(ms)", "Turnaround(ms)"
+            "  Warmup (not timed): {:.3} ms avg per thread",
+            warmup_ms / metrics.threads.len() as f64
         );
-        println!("  {}", "─".repeat(70));
+    }
 
-        for m in metrics_guard.iter() {
-            println!(
-                "  {:>4} {:>14} {:>10} {:>12.3} {:>12.3} {:>12.3}",
-                m.thread_id,
-                m.policy,
-                m.priority,
-                m.wait_time.as_secs_f64() * 1000.0,
-                m.execution_time.as_secs_f64() * 1000.0,
-                m.turnaround_time.as_secs_f64() * 1000.0,
-            );
+    // Threads that could not be pinned ran wherever the kernel put them
+    for m in &metrics.threads {
+        if let Some(e) = &m.affinity_error {
+            println!("  Thread {} not pinned: {}", m.thread_id, e);
         }
     }
+}
 
-    Ok(PolicyMetrics {
-        policy: policy.to_string(),
-        total_threads,
-        avg_wait_time_ms,
-        avg_execution_time_ms,
-        avg_turnaround_time_ms,
-        throughput,
-        wall_clock_time_ms: wall_clock_time.as_secs_f64() * 1000.0,
-    })
+/// One-line summary of a policy run for --quiet
+fn quiet_line(metrics: &PolicyMetrics) -> String {
+    let units = metrics.threads.first().map_or(0, |m| m.work_units);
+    format!(
+        "policy={} units={} time={:.3}ms",
+        metrics.policy, units, metrics.wall_clock_time_ms
+    )
 }
 
 /// Print results in human-readable format
-fn print_results(metrics: &PolicyMetrics) {
+fn print_results(metrics: &PolicyMetrics, unit: &str) {
     println!("\n  ┌─────────────────────────────────────────────────────────┐");
-    println!("  │ Policy: {:^47} │", metrics.policy);
+    let label = if metrics.downgraded {
+        format!("{} (DOWNGRADED)", metrics.policy)
+    } else {
+        metrics.policy.clone()
+    };
+    println!("  │ Policy: {:^47} │", label);
     println!("  ├─────────────────────────────────────────────────────────┤");
     println!(
         "  │ Threads:              {:>32} │",
@@ -427,55 +311,244 @@ fn print_results(metrics: &PolicyMetrics) {
         "  │ Avg Turnaround Time:  {:>29.3} ms │",
         metrics.avg_turnaround_time_ms
     );
+    for (label, spread) in [
+        ("Wait", metrics.wait),
+        ("Execution", metrics.execution),
+        ("Turnaround", metrics.turnaround),
+    ] {
+        let values = format!("{:.3} / {:.3} / {:.3}", spread.min_ms, spread.median_ms, spread.max_ms);
+        println!("  │   {:<10} min/med/max: {:>25} ms │", label, values);
+    }
+    println!(
+        "  │ Avg CPU Time:         {:>29.3} ms │",
+        metrics.avg_cpu_time_ms
+    );
+    println!(
+        "  │ CPU Efficiency:       {:>30.1} % │",
+        metrics.cpu_efficiency * 100.0
+    );
     println!(
         "  │ Wall Clock Time:      {:>29.3} ms │",
         metrics.wall_clock_time_ms
     );
     println!(
-        "  │ Throughput:           {:>25.0} primes/s │",
-        metrics.throughput
+        "  │ Latency p50:          {:>29.3} ms │",
+        metrics.latency.p50_ms
+    );
+    println!(
+        "  │ Latency p95:          {:>29.3} ms │",
+        metrics.latency.p95_ms
     );
+    println!(
+        "  │ Latency p99:          {:>29.3} ms │",
+        metrics.latency.p99_ms
+    );
+    println!(
+        "  │ Throughput:           {:>23.0} {:>6}/s │",
+        metrics.throughput, unit
+    );
+    println!(
+        "  │ Context Switches:     {:>11} vol / {:>8} invol │",
+        metrics.total_voluntary_ctxt, metrics.total_involuntary_ctxt
+    );
+    if let (Some(joules), Some(per_unit)) = (metrics.energy_j, metrics.energy_per_unit_uj()) {
+        println!("  │ Energy:               {:>30.3} J │", joules);
+        println!("  │ Energy per unit:      {:>22.3} µJ/{:<6} │", per_unit, unit);
+    }
     println!("  └─────────────────────────────────────────────────────────┘");
 }
 
-/// Print CSV header
-fn print_csv_header() {
-    println!(
-        "policy,threads,priority,avg_wait_ms,avg_exec_ms,avg_turnaround_ms,wall_clock_ms,throughput"
+/// Print CSV header; `energy` adds the --energy columns
+fn print_csv_header(energy: bool) {
+    print!(
+        "policy,threads,priority,avg_wait_ms,avg_exec_ms,avg_turnaround_ms,wall_clock_ms,throughput,p50_ms,p95_ms,p99_ms,avg_cpu_ms,cpu_efficiency,\
+         min_wait_ms,median_wait_ms,max_wait_ms,min_exec_ms,median_exec_ms,max_exec_ms,\
+         min_turnaround_ms,median_turnaround_ms,max_turnaround_ms,downgraded"
     );
+    if energy {
+        print!(",energy_j,uj_per_unit");
+    }
+    println!();
 }
 
-/// Print results in CSV format
-fn print_csv_results(metrics: &PolicyMetrics, priority: i32) {
-    println!(
-        "{},{},{},{:.3},{:.3},{:.3},{:.3},{:.0}",
+/// Print results in CSV format; `energy` adds the --energy columns, left
+/// empty if the counter could not be read for this run
+fn print_csv_results(metrics: &PolicyMetrics, energy: bool) {
+    print!(
+        "{},{},{},{:.3},{:.3},{:.3},{:.3},{:.0},{:.3},{:.3},{:.3},{:.3},{:.3}",
         metrics.policy,
         metrics.total_threads,
-        priority,
+        metrics.priority,
         metrics.avg_wait_time_ms,
         metrics.avg_execution_time_ms,
         metrics.avg_turnaround_time_ms,
         metrics.wall_clock_time_ms,
-        metrics.throughput
+        metrics.throughput,
+        metrics.latency.p50_ms,
+        metrics.latency.p95_ms,
+        metrics.latency.p99_ms,
+        metrics.avg_cpu_time_ms,
+        metrics.cpu_efficiency
     );
+    for spread in [metrics.wait, metrics.execution, metrics.turnaround] {
+        print!(",{:.3},{:.3},{:.3}", spread.min_ms, spread.median_ms, spread.max_ms);
+    }
+    print!(",{}", metrics.downgraded);
+    if energy {
+        let joules = metrics.energy_j.map_or(String::new(), |joules| format!("{:.6}", joules));
+        let per_unit = metrics.energy_per_unit_uj().map_or(String::new(), |uj| format!("{:.3}", uj));
+        print!(",{},{}", joules, per_unit);
+    }
+    println!();
+}
+
+/// Header of the `--out-csv` file
+const OUT_CSV_HEADER: &str =
+    "policy,threads,priority,avg_wait_ms,avg_exec_ms,avg_turnaround_ms,throughput,wall_clock_ms";
+
+/// Append one `--out-csv` row per run to `path`, writing the header first
+/// if the file is new or empty
+fn append_out_csv(path: &Path, runs: &[PolicyMetrics]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let mut text = String::new();
+    if file.metadata()?.len() == 0 {
+        text.push_str(OUT_CSV_HEADER);
+        text.push('\n');
+    }
+    for metrics in runs {
+        text.push_str(&format!(
+            "{},{},{},{:.3},{:.3},{:.3},{:.0},{:.3}\n",
+            metrics.policy,
+            metrics.total_threads,
+            metrics.priority,
+            metrics.avg_wait_time_ms,
+            metrics.avg_execution_time_ms,
+            metrics.avg_turnaround_time_ms,
+            metrics.throughput,
+            metrics.wall_clock_time_ms
+        ));
+    }
+    file.write_all(text.as_bytes())
 }
 
 fn main() {
-    let args = Args::parse();
+    let args = match parse_args_from(std::env::args_os()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(exit_code::INVALID_ARGS);
+        }
+    };
+
+    // A container's CPU quota can be well below the CPUs it sees
+    let cpu_quota = cgroup::cpu_quota(Path::new(cgroup::CPU_MAX_PATH));
+    let num_threads = match (args.threads, cpu_quota) {
+        (None, Some(cpus)) if args.respect_cgroup => resolve_threads(None).min(cgroup::quota_threads(cpus)),
+        _ => resolve_threads(args.threads),
+    };
+    if let Some(cpus) = cpu_quota.filter(|&cpus| num_threads as f64 > cpus) {
+        eprintln!(
+            "Warning: {} threads exceed the cgroup CPU quota of {:.2} CPUs; they will be throttled and skew the comparison",
+            num_threads, cpus
+        );
+    }
+    if let Some(cores) = physical_cores().filter(|&cores| args.threads.is_some_and(|threads| threads > cores)) {
+        eprintln!(
+            "Warning: {} threads exceed the {} physical core(s); threads sharing a core (SMT siblings) skew the comparison (try --threads {})",
+            num_threads, cores, cores
+        );
+    }
+
+    let seed = args.seed.unwrap_or_else(clock_seed);
+
+    let deadline = match deadline_params(&args) {
+        Ok(deadline) => deadline,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(exit_code::INVALID_ARGS);
+        }
+    };
 
-    if !args.csv {
+    // With --policy all, each policy checks the priority as it runs
+    if args.policy != SchedulingPolicy::All {
+        if let Err(e) = resolve_priority(args.policy, args.priority) {
+            eprintln!("Error: {}", e);
+            std::process::exit(exit_code::INVALID_ARGS);
+        }
+    }
+
+    // Say so before spawning anything, rather than once per failing thread
+    if args.policy != SchedulingPolicy::Other && !check_rt_capability() {
+        let message = format!(
+            "{} needs CAP_SYS_NICE (try running with sudo) or an rtprio limit",
+            args.policy
+        );
+        if args.strict {
+            eprintln!("Error: {}", message);
+            std::process::exit(exit_code::PRIVILEGE_DENIED);
+        }
+        eprintln!("Warning: {}; real-time runs will fall back to SCHED_OTHER", message);
+    }
+
+    // The boxed banners and summaries only appear in the default output
+    let decorated = !args.csv && !args.quiet;
+
+    if decorated {
         println!("═══════════════════════════════════════════════════════════");
         println!("           CPU SCHEDULING POLICY SIMULATOR");
         println!("═══════════════════════════════════════════════════════════");
         println!("\nConfiguration:");
-        println!("  Threads: {}", args.threads);
-        println!("  Priority: {}", args.priority);
-        println!("  Prime limit: {}", args.limit);
+        println!("  Threads: {}", num_threads);
+        if let Some(cpus) = cpu_quota {
+            println!("  CPU quota: {:.2} CPUs (cgroup cpu.max)", cpus);
+        }
+        match args.priority {
+            Some(priority) => println!("  Priority: {}", priority),
+            None => println!("  Priority: policy default"),
+        }
+        println!("  Workload: {} (limit {})", args.workload, args.limit);
         println!("  Iterations per thread: {}", args.iterations);
+        if args.warmup > 0 {
+            println!("  Warmup iterations: {}", args.warmup);
+        }
+        match args.yield_every {
+            0 => println!("  Yield: never"),
+            1 => println!("  Yield: after every iteration"),
+            n => println!("  Yield: after every {} iterations", n),
+        }
+        if args.jitter > 0 {
+            println!("  Jitter: limit ±{}% per thread (--seed {})", args.jitter, seed);
+        }
         println!("  Policy: {}", args.policy);
+        if args.affinity {
+            println!("  Affinity: thread i pinned to CPU (i % CPUs)");
+        }
+        if let Some(params) = deadline {
+            println!(
+                "  Deadline budget: runtime {} ns / deadline {} ns / period {} ns",
+                params.runtime_ns, params.deadline_ns, params.period_ns
+            );
+        }
         println!("\n───────────────────────────────────────────────────────────");
     }
 
+    let options = RunOptions {
+        num_threads,
+        priority: args.priority,
+        limit: args.limit,
+        workload: args.workload,
+        iterations: args.iterations,
+        warmup: args.warmup,
+        yield_every: args.yield_every,
+        jitter: args.jitter,
+        seed,
+        deadline,
+        affinity: args.affinity,
+        verbose: args.verbose,
+    };
+
     let policies = if args.policy == SchedulingPolicy::All {
         vec![
             SchedulingPolicy::Other,
@@ -486,30 +559,71 @@ fn main() {
         vec![args.policy]
     };
 
-    if args.csv {
-        print_csv_header();
+    // Without a readable counter the run goes ahead, just unmetered
+    let rapl = if args.energy {
+        match RaplCounter::open(Path::new(energy::RAPL_DIR)) {
+            Ok(counter) => Some(counter),
+            Err(e) => {
+                eprintln!("Warning: --energy unavailable, {} (needs an Intel RAPL powercap driver, usually root)", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if args.csv && !args.no_header {
+        print_csv_header(rapl.is_some());
     }
 
     let mut all_metrics = vec![];
+    // The first failure decides the exit code; later runs still go ahead
+    let mut exit_status = exit_code::SUCCESS;
+    let mut fail = |code| {
+        if exit_status == exit_code::SUCCESS {
+            exit_status = code;
+        }
+    };
 
     for policy in policies {
-        if !args.csv {
+        if decorated {
             println!("\n▶ Running with policy: {}", policy);
         }
 
-        match run_with_policy(
-            policy,
-            args.threads,
-            args.priority,
-            args.limit,
-            args.iterations,
-            args.verbose,
-        ) {
+        if let Err(e) = resolve_priority(policy, args.priority) {
+            if args.csv {
+                eprintln!("# Error for {}: {}", policy, e);
+            } else {
+                eprintln!("  Error: {}", e);
+            }
+            fail(exit_code::INVALID_ARGS);
+            continue;
+        }
+
+        match run_with_policy_metered(policy, &options, rapl.as_ref()) {
             Ok(metrics) => {
+                if args.verbose {
+                    print_thread_details(&metrics);
+                }
+                if metrics.panicked_threads > 0 {
+                    fail(exit_code::WORKER_FAILURE);
+                } else if metrics.policy_errors > 0 || metrics.downgraded {
+                    fail(exit_code::PRIVILEGE_DENIED);
+                }
+                if let Some(warning) = downgrade_warning(&metrics) {
+                    if args.strict {
+                        eprintln!("Error: {}", warning);
+                        std::process::exit(exit_code::PRIVILEGE_DENIED);
+                    }
+                    eprintln!("Warning: {}", warning);
+                }
+
                 if args.csv {
-                    print_csv_results(&metrics, args.priority);
+                    print_csv_results(&metrics, rapl.is_some());
+                } else if args.quiet {
+                    println!("{}", quiet_line(&metrics));
                 } else {
-                    print_results(&metrics);
+                    print_results(&metrics, args.workload.unit());
                 }
                 all_metrics.push(metrics);
             }
@@ -519,12 +633,21 @@ fn main() {
                 } else {
                     eprintln!("  Error: {}", e);
                 }
+                fail(exit_code::WORKER_FAILURE);
             }
         }
     }
 
+    if let Some(path) = &args.out_csv {
+        if let Err(e) = append_out_csv(path, &all_metrics) {
+            eprintln!("Error: cannot write {}: {}", path.display(), e);
+            fail(exit_code::INVALID_ARGS);
+        }
+    }
+
     // Print comparison if running all policies
-    if args.policy == SchedulingPolicy::All && !args.csv && all_metrics.len() > 1 {
+    if args.policy == SchedulingPolicy::All && decorated && all_metrics.len() > 1 {
+        let palette = Palette::new(args.color);
         println!("\n═══════════════════════════════════════════════════════════");
         println!("                    COMPARISON SUMMARY");
         println!("═══════════════════════════════════════════════════════════");
@@ -553,7 +676,8 @@ fn main() {
         }) {
             println!(
                 "\n✓ Lowest wait time: {} ({:.3} ms)",
-                best_wait.policy, best_wait.avg_wait_time_ms
+                palette.highlight(&best_wait.policy),
+                best_wait.avg_wait_time_ms
             );
         }
 
@@ -562,38 +686,87 @@ fn main() {
             .max_by(|a, b| a.throughput.partial_cmp(&b.throughput).unwrap())
         {
             println!(
-                "✓ Highest throughput: {} ({:.0} primes/s)",
-                best_throughput.policy, best_throughput.throughput
+                "✓ Highest throughput: {} ({:.0} {}/s)",
+                palette.highlight(&best_throughput.policy),
+                best_throughput.throughput,
+                args.workload.unit()
             );
         }
     }
 
-    if !args.csv {
+    if decorated {
         println!("\n═══════════════════════════════════════════════════════════");
     }
+
+    if all_metrics.is_empty() {
+        fail(exit_code::WORKER_FAILURE);
+    }
+    std::process::exit(exit_status);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use scheduler_sim::run_with_policy;
 
     #[test]
-    fn test_calculate_primes() {
-        let primes = calculate_primes(100);
-        assert_eq!(primes.len(), 25);
+    fn test_config_file_with_cli_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sim.toml");
+        std::fs::write(&path, "policy = \"rr\"\nthreads = 3\npriority = 70\nlimit = 20000\niterations = 7\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = parse_args_from(["scheduler-sim", "--config", path]).unwrap();
+        assert_eq!(args.policy, SchedulingPolicy::Rr);
+        assert_eq!(args.threads, Some(3));
+        assert_eq!((args.priority, args.limit, args.iterations), (Some(70), 20_000, 7));
+
+        // Flags on the command line beat the file, even when equal to the default
+        let args = parse_args_from(["scheduler-sim", "--config", path, "--limit", "500", "-i", "3", "-p", "other"]).unwrap();
+        assert_eq!(args.policy, SchedulingPolicy::Other);
+        assert_eq!((args.limit, args.iterations), (500, 3));
+        assert_eq!((args.threads, args.priority), (Some(3), Some(70)));
     }
 
     #[test]
-    fn test_scheduling_policy_display() {
-        assert_eq!(format!("{}", SchedulingPolicy::Other), "SCHED_OTHER");
-        assert_eq!(format!("{}", SchedulingPolicy::Fifo), "SCHED_FIFO");
-        assert_eq!(format!("{}", SchedulingPolicy::Rr), "SCHED_RR");
+    fn test_deadline_params_required() {
+        let args = Args::parse_from(["scheduler-sim", "--policy", "deadline", "--runtime-ns", "1000000"]);
+        assert!(deadline_params(&args).is_err());
+
+        let args = Args::parse_from(["scheduler-sim", "--policy", "other", "--runtime-ns", "1000000"]);
+        assert_eq!(deadline_params(&args), Ok(None));
     }
 
     #[test]
-    fn test_run_with_default_policy() {
-        // This should always work without privileges
-        let result = run_with_policy(SchedulingPolicy::Other, 2, 0, 10000, 1, false);
-        assert!(result.is_ok());
+    fn test_negative_priority_parses() {
+        let args = Args::parse_from(["scheduler-sim", "-p", "other", "-P", "-5"]);
+        assert_eq!(args.priority, Some(-5));
+    }
+
+    #[test]
+    fn test_out_csv_appends_rows_under_one_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs.csv");
+        let options = RunOptions {
+            limit: 10_000,
+            iterations: 1,
+            ..RunOptions::default()
+        };
+        let runs = [
+            run_with_policy(SchedulingPolicy::Other, &options).unwrap(),
+            run_with_policy(SchedulingPolicy::Other, &options).unwrap(),
+        ];
+
+        append_out_csv(&path, &runs[..1]).unwrap();
+        append_out_csv(&path, &runs).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], OUT_CSV_HEADER);
+        assert_eq!(lines.len(), 4, "{}", text);
+        for row in &lines[1..] {
+            assert!(row.starts_with("SCHED_OTHER,1,0,"), "{}", row);
+            assert_eq!(row.split(',').count(), OUT_CSV_HEADER.split(',').count());
+        }
     }
 }
@@ -0,0 +1,178 @@
+//! What each worker thread runs (`--workload`)
+//!
+//! The prime sieve is memory-bound, so on its own it says little about how
+//! a policy treats other kinds of work. Every workload is sized by
+//! `--limit` and reports how many units of work one run performed, which
+//! is what the throughput figures count.
+
+use clap::ValueEnum;
+use primes_core::{simple_sieve, SieveError, MAX_SIEVE_LIMIT};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// One run's worth of work, repeated `--iterations` times per thread
+pub trait Workload: Send + Sync {
+    /// Do the work once and return the units of work done
+    fn run(&self) -> usize;
+}
+
+/// Workload selected on the command line
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum WorkloadKind {
+    /// Sieve of Eratosthenes up to --limit; units are primes found (memory-bound)
+    Primes,
+    /// --limit Fibonacci steps in u64 arithmetic; units are steps (CPU-bound)
+    Fib,
+    /// Multiply two n×n matrices with n = ∛limit; units are multiply-adds (memory-bound)
+    Matrix,
+    /// Naps of 100 µs, one per 100 000 of --limit; units are naps (I/O-like)
+    Sleep,
+}
+
+impl std::fmt::Display for WorkloadKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkloadKind::Primes => write!(f, "primes"),
+            WorkloadKind::Fib => write!(f, "fib"),
+            WorkloadKind::Matrix => write!(f, "matrix"),
+            WorkloadKind::Sleep => write!(f, "sleep"),
+        }
+    }
+}
+
+impl WorkloadKind {
+    /// Name of the unit returned by [`Workload::run`], for throughput labels
+    pub fn unit(self) -> &'static str {
+        match self {
+            WorkloadKind::Primes => "primes",
+            WorkloadKind::Fib => "steps",
+            WorkloadKind::Matrix => "madds",
+            WorkloadKind::Sleep => "naps",
+        }
+    }
+
+    /// The workload of this kind sized by `limit`
+    pub fn build(self, limit: u64) -> Result<Arc<dyn Workload>, String> {
+        Ok(match self {
+            WorkloadKind::Primes => {
+                if limit > MAX_SIEVE_LIMIT {
+                    let error = SieveError::LimitTooLarge {
+                        requested: limit,
+                        max_supported: MAX_SIEVE_LIMIT,
+                    };
+                    return Err(error.to_string());
+                }
+                Arc::new(Primes { limit })
+            }
+            WorkloadKind::Fib => Arc::new(Fib { steps: limit }),
+            WorkloadKind::Matrix => Arc::new(Matrix::new((limit as f64).cbrt().round().max(1.0) as usize)),
+            WorkloadKind::Sleep => Arc::new(Sleep {
+                naps: (limit / 100_000).max(1),
+            }),
+        })
+    }
+}
+
+/// The original workload: sieve all primes up to `limit`
+struct Primes {
+    limit: u64,
+}
+
+impl Workload for Primes {
+    fn run(&self) -> usize {
+        // Too-large limits are rejected by `build`; this only fails if
+        // the allocation does
+        simple_sieve(self.limit).expect("prime sieve failed").len()
+    }
+}
+
+/// Iterative Fibonacci, wrapping at 2^64
+struct Fib {
+    steps: u64,
+}
+
+impl Workload for Fib {
+    fn run(&self) -> usize {
+        let (mut a, mut b) = (0u64, 1u64);
+        for _ in 0..black_box(self.steps) {
+            (a, b) = (b, a.wrapping_add(b));
+        }
+        black_box(a);
+        self.steps as usize
+    }
+}
+
+/// Naive n×n matrix product over fixed inputs
+struct Matrix {
+    n: usize,
+    lhs: Vec<f64>,
+    rhs: Vec<f64>,
+}
+
+impl Matrix {
+    fn new(n: usize) -> Self {
+        let lhs = (0..n * n).map(|i| (i % 7) as f64).collect();
+        let rhs = (0..n * n).map(|i| (i % 5) as f64).collect();
+        Matrix { n, lhs, rhs }
+    }
+}
+
+impl Workload for Matrix {
+    fn run(&self) -> usize {
+        let n = self.n;
+        let mut product = vec![0.0; n * n];
+        for i in 0..n {
+            for k in 0..n {
+                let a = self.lhs[i * n + k];
+                for j in 0..n {
+                    product[i * n + j] += a * self.rhs[k * n + j];
+                }
+            }
+        }
+        black_box(&product);
+        n * n * n
+    }
+}
+
+/// Short sleeps, yielding the CPU like a thread waiting on I/O
+struct Sleep {
+    naps: u64,
+}
+
+impl Workload for Sleep {
+    fn run(&self) -> usize {
+        for _ in 0..self.naps {
+            thread::sleep(Duration::from_micros(100));
+        }
+        self.naps as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workloads_are_positive_and_deterministic() {
+        let expected = [
+            (WorkloadKind::Primes, 168),
+            (WorkloadKind::Fib, 1000),
+            (WorkloadKind::Matrix, 1000),
+            (WorkloadKind::Sleep, 1),
+        ];
+        for (kind, units) in expected {
+            let workload = kind.build(1000).unwrap();
+            assert_eq!(workload.run(), units, "{}", kind);
+            assert_eq!(workload.run(), units, "{}", kind);
+        }
+        assert_eq!(WorkloadKind::Sleep.build(300_000).unwrap().run(), 3);
+    }
+
+    #[test]
+    fn test_primes_limit_checked_up_front() {
+        assert!(WorkloadKind::Primes.build(u64::MAX).is_err());
+        assert!(WorkloadKind::Fib.build(u64::MAX).is_ok());
+    }
+}
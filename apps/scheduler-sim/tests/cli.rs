@@ -0,0 +1,124 @@
+//! End-to-end checks of the `scheduler-sim` binary
+
+use assert_cmd::Command;
+
+/// Whether `text` contains any Unicode box-drawing character (U+2500-U+257F)
+fn has_box_drawing(text: &str) -> bool {
+    text.chars().any(|c| ('\u{2500}'..='\u{257F}').contains(&c))
+}
+
+#[test]
+fn test_quiet_prints_one_plain_line() {
+    let output = Command::cargo_bin("scheduler-sim")
+        .unwrap()
+        .args(["--limit", "1000", "--threads", "2", "--iterations", "1", "--quiet"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!has_box_drawing(&stdout), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(lines[0].starts_with("policy=SCHED_OTHER units=168 time=") && lines[0].ends_with("ms"), "{}", stdout);
+}
+
+#[test]
+fn test_color_never_has_no_escape_sequences() {
+    // Real-time policies fail without privileges; SCHED_OTHER still runs
+    let output = Command::cargo_bin("scheduler-sim")
+        .unwrap()
+        .args(["--limit", "1000", "--threads", "2", "--iterations", "1", "--policy", "all", "--color", "never"])
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    // Exit code 2 when the real-time policies were refused
+    assert!(matches!(output.status.code(), Some(0 | 2)), "{:?}", output.status);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains('\x1b'), "{:?}", stdout);
+}
+
+#[test]
+fn test_invalid_arguments_exit_4() {
+    let run = |args: &[&str]| Command::cargo_bin("scheduler-sim").unwrap().args(args).output().unwrap();
+    assert_eq!(run(&["--no-such-flag"]).status.code(), Some(4));
+    assert_eq!(run(&["--policy", "fifo", "--priority", "0"]).status.code(), Some(4));
+    assert_eq!(run(&["--help"]).status.code(), Some(0));
+}
+
+#[test]
+fn test_strict_without_rt_privilege_exits_2() {
+    // Root (or an rtprio limit) has the privilege this test needs to lack
+    let mut rtprio = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let has_rtprio = unsafe { libc::getrlimit(libc::RLIMIT_RTPRIO, &mut rtprio) } == 0 && rtprio.rlim_cur > 0;
+    if unsafe { libc::geteuid() } == 0 || has_rtprio {
+        eprintln!("skipping: running with real-time privileges");
+        return;
+    }
+
+    let output = Command::cargo_bin("scheduler-sim")
+        .unwrap()
+        .args(["--policy", "fifo", "--strict", "--limit", "1000", "--iterations", "1"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("CAP_SYS_NICE"));
+}
+
+#[test]
+fn test_csv_header_unless_no_header() {
+    let run = |extra: &[&str]| {
+        let output = Command::cargo_bin("scheduler-sim")
+            .unwrap()
+            .args(["--policy", "other", "--threads", "1", "--limit", "1000", "--iterations", "1", "--csv"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&[]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert!(lines[0].starts_with("policy,threads,priority,"), "{}", stdout);
+
+    // Just the data row, which starts like the one after the header
+    let bare = run(&["--no-header"]);
+    assert_eq!(bare.lines().count(), 1, "{}", bare);
+    assert_eq!(bare.split(',').next(), lines[1].split(',').next());
+}
+
+#[test]
+fn test_out_csv_has_one_row_per_policy_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("results.csv");
+    let output = Command::cargo_bin("scheduler-sim")
+        .unwrap()
+        .args(["--limit", "1000", "--threads", "1", "--iterations", "1", "--policy", "all", "--quiet"])
+        .arg("--out-csv")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(matches!(output.status.code(), Some(0 | 2)), "{:?}", output.status);
+
+    // --quiet prints one line per policy that ran
+    let runs: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split(' ').next().unwrap().trim_start_matches("policy=").to_string())
+        .collect();
+    assert!(!runs.is_empty());
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "policy,threads,priority,avg_wait_ms,avg_exec_ms,avg_turnaround_ms,throughput,wall_clock_ms");
+    assert_eq!(lines.len(), runs.len() + 1, "{}", text);
+    for (row, policy) in lines[1..].iter().zip(&runs) {
+        assert!(row.starts_with(&format!("{},", policy)), "{}", row);
+    }
+}
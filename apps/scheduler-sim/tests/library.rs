@@ -0,0 +1,33 @@
+//! The `scheduler_sim` library called directly, without the binary
+
+use scheduler_sim::workload::WorkloadKind;
+use scheduler_sim::{run_with_policy, RunOptions, SchedulingPolicy};
+
+#[test]
+fn test_run_with_policy_returns_metrics() {
+    let options = RunOptions {
+        num_threads: 3,
+        limit: 10_000,
+        iterations: 2,
+        workload: WorkloadKind::Primes,
+        ..RunOptions::default()
+    };
+    let metrics = run_with_policy(SchedulingPolicy::Other, &options).unwrap();
+
+    assert_eq!(metrics.policy, "SCHED_OTHER");
+    assert_eq!(metrics.total_threads, 3);
+    assert_eq!((metrics.policy_errors, metrics.panicked_threads), (0, 0));
+    assert!(!metrics.downgraded);
+    assert!(metrics.throughput > 0.0);
+    assert!(metrics.execution.min_ms <= metrics.execution.max_ms);
+
+    // Every thread sieved π(10_000) = 1229 primes, twice
+    let mut ids: Vec<usize> = metrics.threads.iter().map(|m| m.thread_id).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, [0, 1, 2]);
+    for thread in &metrics.threads {
+        assert_eq!(thread.work_units, 1229);
+        assert_eq!(thread.iteration_times.len(), 2);
+        assert_eq!(thread.limit, 10_000);
+    }
+}
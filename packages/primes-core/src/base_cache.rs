@@ -0,0 +1,169 @@
+//! Base primes shared by runs at several limits
+//!
+//! Every segmented sieve first finds the base primes up to √limit. A sweep
+//! over several limits would find the same small primes again for each
+//! one; a [`BasePrimeCache`] sieves them once, up to the largest √limit
+//! asked for so far, and hands out prefixes of that list.
+//!
+//! A cache can also start from a list saved by an earlier run (see
+//! [`BasePrimeCache::seed_primes`]), so a large run need not sieve them
+//! at all.
+
+use crate::{is_prime_mr, simple_sieve, SieveError};
+
+/// Base primes up to the largest √limit requested so far
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{segmented_sieve_parallel_cached, simple_sieve, BasePrimeCache};
+///
+/// let mut cache = BasePrimeCache::new();
+/// for limit in [1_000_000, 10_000] {
+///     let (primes, _) = segmented_sieve_parallel_cached(limit, 4, &mut cache)?;
+///     assert_eq!(primes, simple_sieve(limit)?);
+/// }
+/// assert_eq!(cache.sieved_to(), 1000);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct BasePrimeCache {
+    primes: Vec<u64>,
+    sieved_to: u64,
+}
+
+impl BasePrimeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache holding `primes`, as written out by [`seed_primes`](Self::seed_primes)
+    ///
+    /// The list is taken to be every prime up to its last one, so that is
+    /// what [`sieved_to`](Self::sieved_to) reports. It must be non-empty,
+    /// start at 2 and be strictly increasing primes; gaps in it cannot be
+    /// detected without sieving, which is what this avoids.
+    pub fn from_primes(primes: Vec<u64>) -> Result<Self, String> {
+        if primes.first() != Some(&2) {
+            return Err("seed primes must start at 2".to_string());
+        }
+        if let Some(pair) = primes.windows(2).find(|pair| pair[0] >= pair[1]) {
+            return Err(format!("seed primes are not increasing: {} then {}", pair[0], pair[1]));
+        }
+        if let Some(&composite) = primes.iter().find(|&&n| !is_prime_mr(n)) {
+            return Err(format!("seed primes contain {}, which is not prime", composite));
+        }
+
+        let sieved_to = *primes.last().unwrap_or(&0);
+        Ok(BasePrimeCache { primes, sieved_to })
+    }
+
+    /// The base primes for `limit` followed by the first prime above √limit
+    ///
+    /// That extra prime marks how far the list is complete, so
+    /// [`from_primes`](Self::from_primes) can tell it covers √limit.
+    ///
+    /// # Errors
+    /// Whatever [`simple_sieve`] returns for the bound searched.
+    pub fn seed_primes(limit: u64) -> Result<Vec<u64>, SieveError> {
+        let sqrt_limit = (limit as f64).sqrt() as u64;
+        // Bertrand's postulate: there is a prime in (n, 2n]
+        let mut primes = simple_sieve(2 * sqrt_limit.max(1))?;
+        let len = primes.partition_point(|&p| p <= sqrt_limit) + 1;
+        primes.truncate(len);
+        Ok(primes)
+    }
+
+    /// The primes up to √limit, sieving further only if √limit is beyond
+    /// everything requested so far
+    ///
+    /// # Errors
+    /// Whatever [`simple_sieve`] returns for √limit.
+    pub fn base_primes(&mut self, limit: u64) -> Result<&[u64], SieveError> {
+        let sqrt_limit = (limit as f64).sqrt() as u64;
+        if sqrt_limit > self.sieved_to {
+            self.primes = simple_sieve(sqrt_limit)?;
+            self.sieved_to = sqrt_limit;
+        }
+        let len = self.primes.partition_point(|&p| p <= sqrt_limit);
+        Ok(&self.primes[..len])
+    }
+
+    /// Bound up to which the cached primes are complete
+    pub fn sieved_to(&self) -> u64 {
+        self.sieved_to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::SIMPLE_SIEVE_CALLS;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_smaller_limit_reuses_cached_prefix() {
+        let mut cache = BasePrimeCache::new();
+        SIMPLE_SIEVE_CALLS.with(|calls| calls.set(0));
+
+        assert_eq!(cache.base_primes(1_000_000).unwrap(), simple_sieve(1000).unwrap());
+        assert_eq!(SIMPLE_SIEVE_CALLS.with(Cell::get), 2); // the cache's sieve and ours
+
+        let small = cache.base_primes(10_000).unwrap().to_vec();
+        assert_eq!(SIMPLE_SIEVE_CALLS.with(Cell::get), 2);
+        assert_eq!(small, simple_sieve(100).unwrap());
+        assert_eq!(small.len(), 25);
+        assert_eq!(cache.sieved_to(), 1000);
+
+        // Primes exactly at √limit are included: √121 = 11
+        assert_eq!(cache.base_primes(121).unwrap().last(), Some(&11));
+    }
+
+    #[test]
+    fn test_seed_primes_round_trip() {
+        let limit = 100_000_000;
+        let seed = BasePrimeCache::seed_primes(limit).unwrap();
+        assert_eq!(seed.len(), 1230); // π(10_000) = 1229, then 10_007
+        assert_eq!(seed.last(), Some(&10_007));
+
+        let mut path = std::env::temp_dir();
+        path.push(format!("seed-primes-{}.bin", std::process::id()));
+        crate::write_primes_file(&path, &seed, crate::OutputFormat::BinDelta).unwrap();
+        let loaded = crate::read_bin_delta(&mut std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, simple_sieve(10_007).unwrap());
+
+        // The loaded primes stand in for the base sieve: π(10^8) = 5_761_455
+        let mut cache = BasePrimeCache::from_primes(loaded).unwrap();
+        assert_eq!(cache.sieved_to(), 10_007);
+        SIMPLE_SIEVE_CALLS.with(|calls| calls.set(0));
+        let options = crate::ParallelOptions::default();
+        let (count, _) = crate::segmented_count_parallel_cached_with(limit, 4, &options, &mut cache).unwrap();
+        assert_eq!(SIMPLE_SIEVE_CALLS.with(Cell::get), 0);
+        assert_eq!(count, 5_761_455);
+        assert_eq!(
+            crate::segmented_sieve_parallel_cached(1_000_000, 4, &mut cache).unwrap().0,
+            simple_sieve(1_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bad_seed_primes_are_rejected() {
+        assert!(BasePrimeCache::from_primes(vec![]).unwrap_err().contains("start at 2"));
+        assert!(BasePrimeCache::from_primes(vec![3, 5]).unwrap_err().contains("start at 2"));
+        assert!(BasePrimeCache::from_primes(vec![2, 5, 3]).unwrap_err().contains("5 then 3"));
+        assert!(BasePrimeCache::from_primes(vec![2, 3, 9]).unwrap_err().contains("9"));
+        assert_eq!(BasePrimeCache::seed_primes(0).unwrap(), vec![2]);
+        assert_eq!(BasePrimeCache::seed_primes(121).unwrap(), vec![2, 3, 5, 7, 11, 13]);
+    }
+
+    #[test]
+    fn test_larger_limit_extends_cache() {
+        let mut cache = BasePrimeCache::new();
+        assert!(cache.base_primes(1).unwrap().is_empty());
+        assert_eq!(cache.base_primes(10_000).unwrap().len(), 25);
+        assert_eq!(cache.base_primes(100_000_000).unwrap().len(), 1229);
+        assert_eq!(cache.sieved_to(), 10_000);
+    }
+}
@@ -0,0 +1,74 @@
+//! Size of the CPU's L2 cache, for `--cache-blocking`
+//!
+//! On Linux each cache level of a CPU is described under
+//! `/sys/devices/system/cpu/cpu0/cache/index<N>/`, with its `level`, its
+//! `type` (`Data`, `Instruction` or `Unified`) and its `size` (`2048K`).
+//! Elsewhere, or if that cannot be read, [`DEFAULT_L2_CACHE_BYTES`] is used.
+
+use std::path::Path;
+
+/// L2 size assumed where it cannot be read: small enough for any recent CPU
+pub const DEFAULT_L2_CACHE_BYTES: u64 = 256 << 10;
+
+/// sysfs directory listing cpu0's caches
+const CPU0_CACHE_DIR: &str = "/sys/devices/system/cpu/cpu0/cache";
+
+/// Bytes of L2 cache per core, or [`DEFAULT_L2_CACHE_BYTES`] if unknown
+pub fn l2_cache_bytes() -> u64 {
+    if cfg!(target_os = "linux") {
+        l2_in(Path::new(CPU0_CACHE_DIR)).unwrap_or(DEFAULT_L2_CACHE_BYTES)
+    } else {
+        DEFAULT_L2_CACHE_BYTES
+    }
+}
+
+/// Size of the level-2 data (or unified) cache described under `dir`
+fn l2_in(dir: &Path) -> Option<u64> {
+    let read = |index: &Path, name: &str| std::fs::read_to_string(index.join(name)).ok();
+
+    std::fs::read_dir(dir).ok()?.flatten().find_map(|entry| {
+        let index = entry.path();
+        let is_l2 = read(&index, "level")?.trim() == "2";
+        let holds_data = matches!(read(&index, "type")?.trim(), "Data" | "Unified");
+        if is_l2 && holds_data {
+            parse_cache_size(&read(&index, "size")?)
+        } else {
+            None
+        }
+    })
+}
+
+/// A sysfs cache `size` such as `2048K`, `1M` or `512`, in bytes
+fn parse_cache_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (digits, shift) = match text.as_bytes().last()? {
+        b'K' => (&text[..text.len() - 1], 10),
+        b'M' => (&text[..text.len() - 1], 20),
+        b'G' => (&text[..text.len() - 1], 30),
+        _ => (text, 0),
+    };
+    let size: u64 = digits.parse().ok()?;
+    size.checked_mul(1 << shift).filter(|&bytes| bytes > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache_size() {
+        assert_eq!(parse_cache_size("2048K\n"), Some(2 << 20));
+        assert_eq!(parse_cache_size("1M"), Some(1 << 20));
+        assert_eq!(parse_cache_size("512"), Some(512));
+        assert_eq!(parse_cache_size("0K"), None);
+        assert_eq!(parse_cache_size("K"), None);
+        assert_eq!(parse_cache_size("big"), None);
+        assert_eq!(parse_cache_size(""), None);
+    }
+
+    #[test]
+    fn test_l2_cache_bytes_is_usable() {
+        assert!(l2_cache_bytes() >= 1024);
+        assert_eq!(l2_in(Path::new("/nonexistent")), None);
+    }
+}
@@ -0,0 +1,117 @@
+//! Work-stealing segmented sieve built on rayon
+//!
+//! [`segmented_sieve_parallel`](crate::segmented_sieve_parallel) gives each
+//! thread one equal slice of the range. Higher slices contain fewer primes
+//! and finish sooner, so some cores sit idle at the end. Here the range is
+//! cut into many small fixed-size chunks instead, and rayon's scheduler
+//! hands them out to whichever worker is free.
+
+use rayon::prelude::*;
+use std::time::Instant;
+
+use crate::{sieve_segment, simple_sieve, split_range, SieveError, ThreadMetrics};
+
+/// Numbers per chunk (32 KiB of `bool` flags, small enough to stay in L1)
+pub const RAYON_CHUNK_SIZE: u64 = 1 << 15;
+
+/// Segmented Sieve of Eratosthenes - rayon Implementation
+///
+/// # Parallelization Strategy
+///
+/// 1. Calculate base primes [2, √limit] sequentially
+/// 2. Cut (√limit, limit] into chunks of [`RAYON_CHUNK_SIZE`] numbers
+/// 3. Sieve the chunks with `par_iter()` on a pool of `num_threads` workers
+/// 4. Concatenate the chunk results, which rayon keeps in order
+///
+/// The returned metrics hold one entry per chunk.
+///
+/// # Errors
+/// Returns the first [`SieveError`] raised by the base-prime sieve or by
+/// any chunk.
+///
+/// # Panics
+/// Panics if the rayon thread pool cannot be created.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{segmented_sieve_rayon, simple_sieve};
+///
+/// let (primes, _metrics) = segmented_sieve_rayon(100_000, 4)?;
+/// assert_eq!(primes, simple_sieve(100_000)?);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn segmented_sieve_rayon(
+    limit: u64,
+    num_threads: usize,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    if limit < 2 {
+        return Ok((vec![], ThreadMetrics::default()));
+    }
+
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+    let base_started = Instant::now();
+    let base_primes = simple_sieve(sqrt_limit)?;
+    let base_ms = base_started.elapsed().as_secs_f64() * 1000.0;
+    let sieve_started = Instant::now();
+
+    if sqrt_limit >= limit {
+        return Ok((base_primes, ThreadMetrics { base_ms, ..ThreadMetrics::default() }));
+    }
+
+    let num_chunks = (limit - sqrt_limit).div_ceil(RAYON_CHUNK_SIZE) as usize;
+    let chunks = split_range(sqrt_limit + 1, limit, num_chunks);
+
+    // A dedicated pool so --threads is honoured instead of the global default
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads.max(1))
+        .build()
+        .expect("Failed to build rayon thread pool");
+
+    let chunk_primes = pool.install(|| {
+        chunks
+            .par_iter()
+            .map(|&(low, high)| {
+                let started = Instant::now();
+                let primes = sieve_segment(low, high, &base_primes)?;
+                Ok((primes, started.elapsed().as_secs_f64() * 1000.0))
+            })
+            .collect::<Result<Vec<(Vec<u64>, f64)>, SieveError>>()
+    })?;
+
+    let segments = chunks
+        .iter()
+        .zip(&chunk_primes)
+        .map(|(&(low, high), (primes, time_ms))| (low, high, primes.len(), *time_ms))
+        .collect();
+
+    let mut all_primes = base_primes;
+    all_primes.extend(chunk_primes.into_iter().flat_map(|(primes, _)| primes));
+
+    let metrics = ThreadMetrics {
+        segments,
+        base_ms,
+        sieve_ms: sieve_started.elapsed().as_secs_f64() * 1000.0,
+    };
+    Ok((all_primes, metrics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rayon_matches_sequential() {
+        let (primes, metrics) = segmented_sieve_rayon(1_000_000, 4).unwrap();
+        assert_eq!(primes, simple_sieve(1_000_000).unwrap());
+        assert!(metrics.segments.len() > 4);
+    }
+
+    #[test]
+    fn test_rayon_small_limits() {
+        for limit in [0, 1, 2, 3, 4, 10, 100] {
+            let (primes, _) = segmented_sieve_rayon(limit, 2).unwrap();
+            assert_eq!(primes, simple_sieve(limit).unwrap(), "limit {}", limit);
+        }
+    }
+}
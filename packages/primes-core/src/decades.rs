@@ -0,0 +1,56 @@
+//! How primes spread over powers of ten
+
+/// Number of primes in each decade `[10^k, 10^(k+1))`, as `(10^k, count)`
+///
+/// `primes` must be sorted ascending (the output of any sieve). Every
+/// decade from `[1, 10)` up to the one holding the largest prime is
+/// listed, including empty ones below the first prime.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{count_by_decade, simple_sieve};
+///
+/// // 2, 3, 5, 7 | 11 ... 97 | 101 ... 997
+/// assert_eq!(count_by_decade(&simple_sieve(1000)?), vec![(1, 4), (10, 21), (100, 143)]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn count_by_decade(primes: &[u64]) -> Vec<(u64, usize)> {
+    let mut counts = Vec::new();
+    let mut low = 1u64;
+    let mut rest = primes;
+    while !rest.is_empty() {
+        // The decade starting at 10^19 runs to the end of u64
+        let high = low.checked_mul(10);
+        let in_decade = high.map_or(rest.len(), |high| rest.partition_point(|&p| p < high));
+        counts.push((low, in_decade));
+        rest = &rest[in_decade..];
+        low = high.unwrap_or(low);
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_sieve;
+
+    #[test]
+    fn test_decades_below_10000() {
+        let counts = count_by_decade(&simple_sieve(10_000).unwrap());
+        assert_eq!(counts, vec![(1, 4), (10, 21), (100, 143), (1000, 1061)]);
+        assert_eq!(counts.iter().map(|&(_, count)| count).sum::<usize>(), 1229);
+    }
+
+    #[test]
+    fn test_empty_and_extreme_decades() {
+        assert!(count_by_decade(&[]).is_empty());
+        assert_eq!(count_by_decade(&[101, 103]), vec![(1, 0), (10, 0), (100, 2)]);
+
+        let largest_u64_prime = 18_446_744_073_709_551_557;
+        let counts = count_by_decade(&[2, largest_u64_prime]);
+        assert_eq!(counts.len(), 20);
+        assert_eq!(counts[0], (1, 1));
+        assert_eq!(counts[19], (10_000_000_000_000_000_000, 1));
+    }
+}
@@ -0,0 +1,94 @@
+//! Error type shared by all sieve implementations
+
+use std::fmt;
+
+/// Largest `limit` the sieves will attempt
+///
+/// That is 64 GiB of flags for the byte-per-number sieve and 8 GiB for the
+/// bit-packed one. This is deliberately conservative: it turns a request
+/// that could never fit in memory into a clean error instead of an OOM kill.
+///
+/// On 32-bit targets the effective cap is lower: a sieve indexes `limit + 1`
+/// flags with `usize`, so any `limit` of `usize::MAX` or more is rejected
+/// with [`SieveError::LimitTooLarge`] rather than truncated.
+pub const MAX_SIEVE_LIMIT: u64 = 1 << 36;
+
+/// Largest `limit` this target can sieve: [`MAX_SIEVE_LIMIT`], or less
+/// where `limit + 1` would not fit in a `usize`
+fn max_supported_limit() -> u64 {
+    MAX_SIEVE_LIMIT.min((usize::MAX as u64).saturating_sub(1))
+}
+
+/// Check that a sieve over `limit` numbers is within [`MAX_SIEVE_LIMIT`]
+/// and addressable on this target
+pub(crate) fn check_limit(limit: u64) -> Result<(), SieveError> {
+    let max_supported = max_supported_limit();
+    if limit > max_supported {
+        return Err(SieveError::LimitTooLarge {
+            requested: limit,
+            max_supported,
+        });
+    }
+    Ok(())
+}
+
+/// Errors returned by the sieve functions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SieveError {
+    /// The requested limit (or segment size) exceeds what the sieve supports
+    LimitTooLarge { requested: u64, max_supported: u64 },
+    /// The sieve buffer could not be allocated
+    AllocationFailed { bytes: u64 },
+    /// A 1-based prime index of 0 was requested (e.g. the "0th prime")
+    ZeroIndex,
+    /// A sieve thread panicked instead of returning its segment
+    WorkerPanicked { thread_id: usize },
+    /// The base primes for a segment stop short of √high: `missing` is
+    /// the first prime they leave out
+    IncompleteBasePrimes { missing: u64, high: u64 },
+}
+
+impl fmt::Display for SieveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SieveError::LimitTooLarge {
+                requested,
+                max_supported,
+            } => write!(
+                f,
+                "limit {} is too large (maximum supported is {})",
+                requested, max_supported
+            ),
+            SieveError::AllocationFailed { bytes } => {
+                write!(f, "failed to allocate {} bytes for the sieve", bytes)
+            }
+            SieveError::ZeroIndex => write!(f, "prime indices start at 1 (the 1st prime is 2)"),
+            SieveError::WorkerPanicked { thread_id } => write!(f, "sieve thread {} panicked", thread_id),
+            SieveError::IncompleteBasePrimes { missing, high } => write!(
+                f,
+                "base primes are incomplete: {} is missing, and every prime up to √{} is needed",
+                missing, high
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SieveError {}
+
+/// Allocate a sieve buffer of `len` elements, all set to `value`
+///
+/// Uses `try_reserve_exact` so an impossible allocation surfaces as
+/// [`SieveError::AllocationFailed`] instead of aborting the process.
+///
+/// A `len` that does not fit in a `usize` (only possible on 32-bit targets)
+/// fails the same way instead of being truncated.
+pub(crate) fn alloc_filled<T: Clone>(len: u64, value: T) -> Result<Vec<T>, SieveError> {
+    let bytes = len.saturating_mul(std::mem::size_of::<T>() as u64);
+    let len = usize::try_from(len).map_err(|_| SieveError::AllocationFailed { bytes })?;
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(len)
+        .map_err(|_| SieveError::AllocationFailed { bytes })?;
+    buffer.resize(len, value);
+    Ok(buffer)
+}
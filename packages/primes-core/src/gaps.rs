@@ -0,0 +1,63 @@
+//! Gaps between consecutive primes
+
+use std::collections::BTreeMap;
+
+/// Number of times each gap `primes[i + 1] - primes[i]` occurs
+///
+/// `primes` must be sorted and consecutive (the output of any sieve). The
+/// map is ordered by gap size.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{prime_gap_histogram, simple_sieve};
+///
+/// let histogram = prime_gap_histogram(&simple_sieve(30)?);
+/// // 2→3, then 3→5, 5→7, 11→13, 17→19 and the rest
+/// assert_eq!(histogram[&1], 1);
+/// assert_eq!(histogram[&2], 4);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn prime_gap_histogram(primes: &[u64]) -> BTreeMap<u64, u64> {
+    let mut histogram = BTreeMap::new();
+    for pair in primes.windows(2) {
+        *histogram.entry(pair[1] - pair[0]).or_insert(0) += 1;
+    }
+    histogram
+}
+
+/// The largest gap and the primes on either side of it, as `(gap, low, high)`
+///
+/// Ties go to the first occurrence. `None` for fewer than two primes.
+pub fn max_prime_gap(primes: &[u64]) -> Option<(u64, u64, u64)> {
+    primes
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0], pair[0], pair[1]))
+        .reduce(|best, gap| if gap.0 > best.0 { gap } else { best })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_sieve;
+
+    #[test]
+    fn test_gap_histogram_below_100() {
+        let primes = simple_sieve(100).unwrap();
+        let histogram = prime_gap_histogram(&primes);
+
+        let expected: BTreeMap<u64, u64> = [(1, 1), (2, 8), (4, 7), (6, 7), (8, 1)].into_iter().collect();
+        assert_eq!(histogram, expected);
+        // One gap between each pair of neighbours
+        assert_eq!(histogram.values().sum::<u64>(), primes.len() as u64 - 1);
+    }
+
+    #[test]
+    fn test_max_prime_gap() {
+        assert_eq!(max_prime_gap(&simple_sieve(100).unwrap()), Some((8, 89, 97)));
+        // The first gap of 14 follows 113
+        assert_eq!(max_prime_gap(&simple_sieve(200).unwrap()), Some((14, 113, 127)));
+        assert_eq!(max_prime_gap(&[2]), None);
+        assert!(prime_gap_histogram(&[]).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! Lazy iterator over the primes
+//!
+//! [`Primes`] yields 2, 3, 5, ... one at a time, sieving the next segment
+//! only when the current one runs out, so only one segment of primes is
+//! ever held in memory.
+
+use std::iter::FusedIterator;
+
+use crate::{sieve_segment, simple_sieve, MAX_SIEVE_LIMIT};
+
+/// Numbers sieved per refill (64 KiB of `bool` flags)
+const PRIMES_SEGMENT_SIZE: u64 = 1 << 16;
+
+/// Iterator over all primes in increasing order, sieved segment by segment
+///
+/// Collecting `Primes::new().take_while(|&p| p <= limit)` gives the same
+/// list as [`simple_sieve(limit)`](crate::simple_sieve). That function is
+/// not built on this iterator: the iterator needs it for its own base
+/// primes, and it stays the bit-packed reference the other sieves are
+/// checked against.
+///
+/// Iteration ends after the last prime below [`MAX_SIEVE_LIMIT`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::Primes;
+///
+/// let sum: u64 = Primes::new().take_while(|&p| p < 100).sum();
+/// assert_eq!(sum, 1060);
+/// assert_eq!(Primes::new().nth(999), Some(7919));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Primes {
+    /// Primes up to √base_bound, enough to sieve any segment ending there
+    base_primes: Vec<u64>,
+    base_bound: u64,
+    /// Primes of the current segment not yet yielded
+    segment: std::vec::IntoIter<u64>,
+    /// Start of the next segment to sieve
+    next_low: u64,
+}
+
+impl Primes {
+    pub fn new() -> Self {
+        Primes {
+            base_primes: Vec::new(),
+            base_bound: 0,
+            segment: Vec::new().into_iter(),
+            next_low: 2,
+        }
+    }
+
+    /// Sieve the next segment into `self.segment`; `false` once exhausted
+    fn refill(&mut self) -> bool {
+        if self.next_low > MAX_SIEVE_LIMIT {
+            return false;
+        }
+        let low = self.next_low;
+        let high = (low + PRIMES_SEGMENT_SIZE - 1).min(MAX_SIEVE_LIMIT);
+
+        // Grow the base primes geometrically so they are rarely recomputed
+        if high > self.base_bound {
+            self.base_bound = high.saturating_mul(2).min(MAX_SIEVE_LIMIT);
+            match simple_sieve((self.base_bound as f64).sqrt() as u64) {
+                Ok(base_primes) => self.base_primes = base_primes,
+                Err(_) => return self.finish(),
+            }
+        }
+
+        match sieve_segment(low, high, &self.base_primes) {
+            Ok(primes) => self.segment = primes.into_iter(),
+            Err(_) => return self.finish(),
+        }
+        self.next_low = high + 1;
+        true
+    }
+
+    /// Stop for good (a sieve allocation failed)
+    fn finish(&mut self) -> bool {
+        self.next_low = MAX_SIEVE_LIMIT + 1;
+        false
+    }
+}
+
+impl Default for Primes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(prime) = self.segment.next() {
+                return Some(prime);
+            }
+            if !self.refill() {
+                return None;
+            }
+        }
+    }
+}
+
+impl FusedIterator for Primes {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_ten_primes() {
+        let first: Vec<u64> = Primes::new().take(10).collect();
+        assert_eq!(first, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    }
+
+    #[test]
+    fn test_matches_simple_sieve() {
+        for limit in [10_000, 5 * PRIMES_SEGMENT_SIZE + 7] {
+            let lazy: Vec<u64> = Primes::new().take_while(|&p| p <= limit).collect();
+            assert_eq!(lazy, simple_sieve(limit).unwrap(), "limit {}", limit);
+        }
+    }
+}
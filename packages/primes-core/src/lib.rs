@@ -0,0 +1,1500 @@
+//! Shared Prime Sieve Library
+//!
+//! This crate contains the Sieve of Eratosthenes implementations used by
+//! every application in the workspace, so that a fix or optimization in
+//! one place is picked up by all of them.
+//!
+//! # Provided Algorithms
+//!
+//! - [`simple_sieve`]: sequential sieve over `[0, limit]`, bit-packed with [`BitSieve`]
+//! - [`sieve_into`]: the same, filling a caller's `Vec` so its capacity is reused
+//! - [`simple_sieve_bytes`]: the original one-byte-per-number sequential sieve
+//! - [`odd_sieve`]: sequential sieve that only stores odd candidates
+//! - [`wheel30_sieve`]: sequential sieve that only stores candidates coprime to 30
+//! - [`sieve_segment`]: sieve a window `[low, high]` using pre-computed base primes
+//! - [`sieve_range`]: the same over a half-open window `[start, end)`, for tiling
+//! - [`segmented_sieve_parallel`]: multithreaded segmented sieve over `[2, limit]`
+//! - [`segmented_sieve_rayon`]: the same, load-balanced over small chunks with rayon
+//! - [`SievePool`]: the same, on long-lived threads reused across calls
+//! - [`count_primes`] / [`segmented_count_parallel`]: π(limit) without collecting primes
+//! - [`prime_count_fast`]: π(limit) by Legendre's formula, with no sieve over the range
+//! - [`find_nth_prime`]: the n-th prime, found by sieving upward segment by segment
+//! - [`primes_from`]: the first k primes from a given value on, sieved upward the same way
+//! - [`tail_primes`]: the largest n primes of a range, sieving down from the top until it has them
+//! - [`Primes`]: lazy iterator over all primes, one segment in memory at a time
+//! - [`is_prime_mr`]: deterministic Miller-Rabin test for a single `u64`, no sieve
+//! - [`twin_primes`] / [`count_twin_primes`]: twin pairs (p, p + 2) in a prime list
+//! - [`prime_gap_histogram`] / [`max_prime_gap`]: gaps between consecutive primes
+//! - [`count_by_decade`]: primes per power-of-ten bucket `[10^k, 10^(k+1))`
+//! - [`li`]: the logarithmic integral, an estimate of π(x)
+//! - [`parse_limit`]: read a limit written as `10M`, `2G` or `1e9`
+//! - [`SplitMix64`]: seeded random numbers for repeatable draws
+//!
+//! The threaded sieves also have `_with` variants taking
+//! [`ParallelOptions`]: a cap on each thread's sieve buffer and a shared
+//! [`SieveProgress`] counter. [`segmented_sieve_parallel_cached`],
+//! [`segmented_count_parallel_cached_with`] and
+//! [`SievePool::compute_cached`] take their base primes from a
+//! [`BasePrimeCache`], kept across runs at several limits or seeded from a
+//! file with [`BasePrimeCache::from_primes`]. Capping the
+//! buffer at [`l2_cache_bytes`] keeps each window the threads sieve in L2.
+//!
+//! [`write_primes`] / [`write_primes_file`] save a prime list as text,
+//! little-endian binary or delta-encoded binary (read back with
+//! [`read_bin_delta`]), [`sample_indices`] picks every N-th prime for a
+//! readable listing, and [`peak_rss_mb`] reads the process's peak memory.
+//! [`physical_cores`] counts cores without their SMT siblings, for
+//! warning when a run asks for more threads than that.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+mod base_cache;
+mod bitsieve;
+mod cache_size;
+mod chunked;
+mod decades;
+mod error;
+mod gaps;
+mod iter;
+mod li;
+mod limit;
+mod mem;
+mod nth;
+mod output;
+mod pool;
+mod primality;
+mod prime_count;
+mod progress;
+mod rng;
+mod tail;
+mod topology;
+mod twins;
+mod wheel;
+
+pub use base_cache::BasePrimeCache;
+pub use bitsieve::BitSieve;
+pub use cache_size::{l2_cache_bytes, DEFAULT_L2_CACHE_BYTES};
+pub use chunked::{segmented_sieve_rayon, RAYON_CHUNK_SIZE};
+pub use decades::count_by_decade;
+pub use error::{SieveError, MAX_SIEVE_LIMIT};
+pub use gaps::{max_prime_gap, prime_gap_histogram};
+pub use iter::Primes;
+pub use li::li;
+pub use limit::parse_limit;
+pub use mem::{peak_rss_bytes, peak_rss_mb};
+pub use nth::{find_nth_prime, primes_from};
+pub use output::{read_bin_delta, sample_indices, write_primes, write_primes_file, OutputFormat, BIN_DELTA_VERSION};
+pub use pool::SievePool;
+pub use primality::is_prime_mr;
+pub use prime_count::{prime_count_fast, FAST_COUNT_THRESHOLD, MAX_FAST_COUNT_LIMIT};
+pub use progress::SieveProgress;
+pub use rng::{clock_seed, SplitMix64};
+pub use tail::tail_primes;
+pub use topology::physical_cores;
+pub use twins::{count_twin_primes, twin_primes};
+pub use wheel::wheel30_sieve;
+
+use error::{alloc_filled, check_limit};
+
+/// Sieve of Eratosthenes - Sequential Implementation
+///
+/// # Algorithm Overview
+/// 1. Create a bit array of size (limit + 1), initialized to true
+/// 2. Mark 0 and 1 as non-prime
+/// 3. For each number p starting from 2:
+///    - If p is still marked as prime, mark all multiples of p as non-prime
+///    - Only need to check up to sqrt(limit)
+/// 4. Collect all indices that are still marked as true
+///
+/// Flags are packed 64 per word in a [`BitSieve`], so memory use is
+/// (limit / 8) bytes instead of `limit` bytes.
+///
+/// # Arguments
+/// * `limit` - The upper bound (inclusive) to search for primes
+///
+/// # Returns
+/// A vector containing all prime numbers up to the limit
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if `limit` exceeds [`MAX_SIEVE_LIMIT`]
+/// (or, on 32-bit targets, if `limit + 1` does not fit in a `usize`), or
+/// [`SieveError::AllocationFailed`] if the sieve buffer cannot be allocated.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::simple_sieve;
+///
+/// assert_eq!(simple_sieve(30)?, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// assert_eq!(simple_sieve(1000)?.len(), 168);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn simple_sieve(limit: u64) -> Result<Vec<u64>, SieveError> {
+    #[cfg(test)]
+    tests::SIMPLE_SIEVE_CALLS.with(|calls| calls.set(calls.get() + 1));
+
+    let mut primes = Vec::new();
+    sieve_into(limit, &mut primes)?;
+    Ok(primes)
+}
+
+/// [`simple_sieve`] into a caller-provided buffer
+///
+/// Clears `out` and fills it with the primes up to `limit`, keeping the
+/// capacity it already has; a loop that sieves again and again with the
+/// same `Vec` only allocates when a larger limit needs more room. On error
+/// `out` is left empty.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::sieve_into;
+///
+/// let mut primes = Vec::new();
+/// sieve_into(1000, &mut primes)?;
+/// assert_eq!(primes.len(), 168);
+/// sieve_into(30, &mut primes)?;
+/// assert_eq!(primes, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn sieve_into(limit: u64, out: &mut Vec<u64>) -> Result<(), SieveError> {
+    out.clear();
+    if limit >= 2 {
+        out.extend(sieve_bits(limit)?.iter_ones());
+    }
+    Ok(())
+}
+
+/// Count the primes up to `limit` (π(limit)) without collecting them
+///
+/// Runs the same bit-packed sieve as [`simple_sieve`] but counts the
+/// surviving flags with a popcount instead of building a `Vec<u64>`, so
+/// peak memory is just the sieve itself (limit / 8 bytes).
+///
+/// # Example
+///
+/// ```
+/// use primes_core::count_primes;
+///
+/// assert_eq!(count_primes(1_000_000)?, 78_498);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn count_primes(limit: u64) -> Result<usize, SieveError> {
+    if limit < 2 {
+        return Ok(0);
+    }
+
+    Ok(sieve_bits(limit)?.count_ones() as usize)
+}
+
+/// Bit-packed sieve over [0, limit] shared by [`simple_sieve`] and [`count_primes`]
+fn sieve_bits(limit: u64) -> Result<BitSieve, SieveError> {
+    check_limit(limit)?;
+
+    let mut is_prime = BitSieve::new(limit + 1)?;
+    is_prime.set_false(0);
+    is_prime.set_false(1);
+
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    // `limit` is capped at MAX_SIEVE_LIMIT, so neither num * num nor
+    // multiple + num can overflow u64 here
+    for num in 2..=sqrt_limit {
+        if is_prime.get(num) {
+            let mut multiple = num * num;
+            while multiple <= limit {
+                is_prime.set_false(multiple);
+                multiple += num;
+            }
+        }
+    }
+
+    Ok(is_prime)
+}
+
+/// Sieve of Eratosthenes - Byte-per-number Implementation
+///
+/// The original sieve, storing one `bool` per number. Kept alongside the
+/// bit-packed [`simple_sieve`] so the two can be benchmarked against each other.
+///
+/// # Algorithm Overview
+/// 1. Create a boolean array of size (limit + 1), initialized to true
+/// 2. Mark 0 and 1 as non-prime
+/// 3. For each number p starting from 2:
+///    - If p is still marked as prime, mark all multiples of p as non-prime
+///    - Only need to check up to sqrt(limit)
+/// 4. Collect all indices that are still marked as true
+///
+/// # Arguments
+/// * `limit` - The upper bound (inclusive) to search for primes
+///
+/// # Returns
+/// A vector containing all prime numbers up to the limit
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if `limit` exceeds [`MAX_SIEVE_LIMIT`], or
+/// [`SieveError::AllocationFailed`] if the sieve buffer cannot be allocated.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::simple_sieve_bytes;
+///
+/// assert_eq!(simple_sieve_bytes(30)?, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// assert_eq!(simple_sieve_bytes(1000)?.len(), 168);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn simple_sieve_bytes(limit: u64) -> Result<Vec<u64>, SieveError> {
+    // Handle edge cases
+    if limit < 2 {
+        return Ok(vec![]);
+    }
+
+    check_limit(limit)?;
+
+    // Create a boolean vector where index represents the number
+    // true = potentially prime, false = composite
+    let mut is_prime = alloc_filled(limit + 1, true)?;
+
+    // 0 and 1 are not prime by definition
+    is_prime[0] = false;
+    is_prime[1] = false;
+
+    // We only need to check up to sqrt(limit)
+    // Any composite number > sqrt(limit) will have a factor <= sqrt(limit)
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    // `limit` is capped at MAX_SIEVE_LIMIT, so neither num * num nor
+    // multiple + num can overflow u64 here
+    for num in 2..=sqrt_limit {
+        if is_prime[num as usize] {
+            // Start from num^2 because smaller multiples were already marked
+            // by smaller primes
+            let mut multiple = num * num;
+            while multiple <= limit {
+                is_prime[multiple as usize] = false;
+                multiple += num;
+            }
+        }
+    }
+
+    Ok(is_prime
+        .iter()
+        .enumerate()
+        .filter(|(_, &prime)| prime)
+        .map(|(idx, _)| idx as u64)
+        .collect())
+}
+
+/// Odd-only Sieve of Eratosthenes
+///
+/// Every even number above 2 is composite, so only odd candidates are
+/// stored: index `i` represents the number `2 * i + 1`. This halves the
+/// memory of [`simple_sieve`] and skips all even multiples while marking.
+///
+/// # Algorithm Overview
+/// 1. Create a bit array with one flag per odd number in [1, limit]
+/// 2. Mark index 0 (the number 1) as non-prime
+/// 3. For each odd p up to sqrt(limit) still marked as prime, mark
+///    p², p² + 2p, p² + 4p, ... (a step of 2p is a step of p in index space)
+/// 4. Return 2 followed by every odd number still marked
+///
+/// # Example
+///
+/// ```
+/// use primes_core::odd_sieve;
+///
+/// assert_eq!(odd_sieve(30)?, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn odd_sieve(limit: u64) -> Result<Vec<u64>, SieveError> {
+    if limit < 2 {
+        return Ok(vec![]);
+    }
+
+    check_limit(limit)?;
+
+    // Number of odd values in [1, limit]
+    let len = limit.div_ceil(2);
+    let mut is_prime = BitSieve::new(len)?;
+
+    // Index 0 is the number 1, which is not prime
+    is_prime.set_false(0);
+
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    // Largest index whose odd number 2i + 1 is <= sqrt(limit)
+    let max_idx = sqrt_limit.saturating_sub(1) / 2;
+
+    for idx in 1..=max_idx {
+        if is_prime.get(idx) {
+            let prime = 2 * idx + 1;
+            // p² is odd, and its index is (p² - 1) / 2
+            let mut multiple_idx = (prime * prime) / 2;
+            while multiple_idx < len {
+                is_prime.set_false(multiple_idx);
+                multiple_idx += prime;
+            }
+        }
+    }
+
+    // 2 is the only even prime and is not represented in the sieve
+    let mut primes = vec![2];
+    primes.extend(is_prime.iter_ones().map(|i| 2 * i + 1));
+    Ok(primes)
+}
+
+/// Sieve a segment of numbers using pre-computed base primes
+///
+/// # Algorithm
+///
+/// For each base prime p, we need to mark all multiples of p in our segment.
+/// The first multiple of p in range [low, high] is:
+///   - If low <= p*p: start at p*p
+///   - Otherwise: start at the smallest multiple of p >= low
+///
+/// # Arguments
+///
+/// * `low` - Start of the segment (inclusive)
+/// * `high` - End of the segment (inclusive)
+/// * `base_primes` - Pre-computed primes up to sqrt(high), in ascending order
+///
+/// `base_primes` must hold every prime up to √high; primes beyond that are
+/// ignored. A list that stops short (say, read from a truncated file) would
+/// let composites through, so it is rejected instead.
+///
+/// # Returns
+///
+/// Vector of primes found in the segment [low, high]
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if the segment is wider than
+/// [`MAX_SIEVE_LIMIT`], [`SieveError::AllocationFailed`] if the
+/// segment buffer cannot be allocated, or
+/// [`SieveError::IncompleteBasePrimes`] if a prime up to √high is missing
+/// past the end of `base_primes`.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{simple_sieve, sieve_segment};
+///
+/// let base_primes = simple_sieve(10)?;
+/// assert_eq!(sieve_segment(10, 30, &base_primes)?, vec![11, 13, 17, 19, 23, 29]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn sieve_segment(low: u64, high: u64, base_primes: &[u64]) -> Result<Vec<u64>, SieveError> {
+    // u64::MAX is divisible by 3, so saturating cannot drop a prime
+    sieve_range(low, high.saturating_add(1), base_primes)
+}
+
+/// Sieve the half-open range [start, end) using pre-computed base primes
+///
+/// Same as [`sieve_segment`] with an exclusive upper end, so adjacent
+/// tiles `[a, b)`, `[b, c)` share their boundary without overlapping.
+/// `base_primes` must hold the primes up to √(end - 1); an empty range
+/// (`start >= end`) gives no primes.
+///
+/// # Errors
+/// Same as [`sieve_segment`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{simple_sieve, sieve_range};
+///
+/// let base_primes = simple_sieve(10)?;
+/// assert_eq!(sieve_range(10, 30, &base_primes)?, vec![11, 13, 17, 19, 23, 29]);
+/// assert_eq!(sieve_range(29, 29, &base_primes)?, vec![]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn sieve_range(start: u64, end: u64, base_primes: &[u64]) -> Result<Vec<u64>, SieveError> {
+    if start >= end {
+        return Ok(vec![]);
+    }
+
+    let is_prime = mark_segment(start, end - 1, base_primes)?;
+
+    Ok(is_prime
+        .iter()
+        .enumerate()
+        .filter(|(_, &prime)| prime)
+        .map(|(idx, _)| start + idx as u64)
+        .collect())
+}
+
+/// Count the primes in the segment [low, high] without collecting them
+///
+/// Same preconditions and errors as [`sieve_segment`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{count_segment, simple_sieve};
+///
+/// let base_primes = simple_sieve(10)?;
+/// assert_eq!(count_segment(10, 30, &base_primes)?, 6);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn count_segment(low: u64, high: u64, base_primes: &[u64]) -> Result<usize, SieveError> {
+    if low > high {
+        return Ok(0);
+    }
+
+    let is_prime = mark_segment(low, high, base_primes)?;
+    Ok(is_prime.iter().filter(|&&prime| prime).count())
+}
+
+/// The first prime up to √high that comes after the end of `base_primes`
+///
+/// Only the stretch past the last base prime is searched, and for a
+/// complete list that is shorter than one prime gap.
+fn missing_base_prime(high: u64, base_primes: &[u64]) -> Option<u64> {
+    let from = base_primes.last().map_or(2, |&p| p.saturating_add(1));
+    (from..=high.isqrt()).find(|&n| is_prime_mr(n))
+}
+
+/// Build the primality flags for [low, high]; index i represents (low + i)
+fn mark_segment(low: u64, high: u64, base_primes: &[u64]) -> Result<Vec<bool>, SieveError> {
+    check_limit(high - low)?;
+    if let Some(missing) = missing_base_prime(high, base_primes) {
+        return Err(SieveError::IncompleteBasePrimes { missing, high });
+    }
+
+    let segment_size = (high - low + 1) as usize;
+
+    // Create a local sieve for this segment
+    // Index i represents number (low + i)
+    let mut is_prime = alloc_filled(segment_size as u64, true)?;
+
+    // 0 and 1 are not prime, and only a segment starting below 2 holds
+    // them; each case names its indices outright, so nothing is subtracted
+    match low {
+        // [0, high]: 0 at index 0, and 1 at index 1 once high reaches it
+        0 => {
+            is_prime[0] = false;
+            if high >= 1 {
+                is_prime[1] = false;
+            }
+        }
+        // [1, high]: 1 at index 0
+        1 => is_prime[0] = false,
+        // From 2 up the base primes do all the marking
+        _ => {}
+    }
+
+    // For each base prime, mark its multiples in our segment
+    for &prime in base_primes {
+        // Skip if prime^2 is beyond our segment (or beyond u64 entirely,
+        // which happens for base primes above 2^32)
+        let square = match prime.checked_mul(prime) {
+            Some(square) if square <= high => square,
+            _ => continue,
+        };
+
+        // Find the first multiple of prime in our segment
+        let start = if low <= square {
+            // If our segment includes prime^2, start there
+            square
+        } else {
+            // Round low up to the nearest multiple of prime
+            let remainder = low % prime;
+            if remainder == 0 {
+                low
+            } else {
+                // No multiple left before u64::MAX means nothing to mark
+                match low.checked_add(prime - remainder) {
+                    Some(start) => start,
+                    None => continue,
+                }
+            }
+        };
+
+        // Mark all multiples of prime in our segment as composite
+        let mut multiple = start;
+        while multiple <= high {
+            // Convert global index to local segment index
+            let local_idx = (multiple - low) as usize;
+            is_prime[local_idx] = false;
+            // Stop instead of wrapping when high is close to u64::MAX
+            multiple = match multiple.checked_add(prime) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+
+    Ok(is_prime)
+}
+
+/// Thread count [`resolve_threads`] falls back to when the available
+/// parallelism cannot be determined
+pub const FALLBACK_THREADS: usize = 4;
+
+/// Thread count for a run: `requested` if given (at least 1), otherwise
+/// [`std::thread::available_parallelism`], or [`FALLBACK_THREADS`] if the
+/// platform cannot report it
+///
+/// # Example
+///
+/// ```
+/// use primes_core::resolve_threads;
+///
+/// assert_eq!(resolve_threads(Some(3)), 3);
+/// assert!(resolve_threads(None) >= 1);
+/// ```
+pub fn resolve_threads(requested: Option<usize>) -> usize {
+    match requested {
+        Some(threads) => threads.max(1),
+        None => thread::available_parallelism().map_or(FALLBACK_THREADS, |n| n.get()),
+    }
+}
+
+/// Default for [`ParallelOptions::max_segment_bytes`]
+pub const DEFAULT_MAX_SEGMENT_BYTES: u64 = 64 << 20;
+
+/// Settings for [`segmented_sieve_parallel_with`] and [`segmented_count_parallel_with`]
+#[derive(Debug, Clone)]
+pub struct ParallelOptions {
+    /// Largest sieve buffer one thread allocates at a time
+    ///
+    /// A thread whose segment needs more sieves it as consecutive
+    /// sub-segments, so memory no longer grows with `limit / threads`.
+    pub max_segment_bytes: u64,
+    /// Counter bumped after every sub-segment
+    pub progress: Option<Arc<SieveProgress>>,
+    /// Number of segments to cut (√limit, limit] into, `None` for one per thread
+    ///
+    /// The threads take segments from a shared queue until none are left,
+    /// so more segments than threads trades queue overhead for finer
+    /// load balancing.
+    pub segments: Option<usize>,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            max_segment_bytes: DEFAULT_MAX_SEGMENT_BYTES,
+            progress: None,
+            segments: None,
+        }
+    }
+}
+
+impl ParallelOptions {
+    /// Numbers per sub-segment: the segment sieve keeps one `bool` per number
+    fn sub_segment_len(&self) -> u64 {
+        (self.max_segment_bytes / std::mem::size_of::<bool>() as u64).max(1)
+    }
+
+    /// The segments to split (√limit, limit] into for `num_threads` threads
+    fn split(&self, sqrt_limit: u64, limit: u64, num_threads: usize) -> Vec<(u64, u64)> {
+        split_range(sqrt_limit + 1, limit, self.segments.unwrap_or(num_threads))
+    }
+
+    /// Metrics for a run done on the calling thread, marking its one progress chunk done
+    fn inline_metrics(&self, sqrt_limit: u64, limit: u64, prime_count: usize, started: Instant) -> ThreadMetrics {
+        if let Some(progress) = &self.progress {
+            progress.start(1);
+            progress.chunk_done();
+        }
+        let time_ms = elapsed_ms(started);
+        ThreadMetrics {
+            segments: vec![(sqrt_limit + 1, limit, prime_count, time_ms)],
+            base_ms: 0.0,
+            sieve_ms: time_ms,
+        }
+    }
+
+    /// Reset the progress counter, if any, for a run over `segments`
+    fn start_progress(&self, segments: &[(u64, u64)]) {
+        if let Some(progress) = &self.progress {
+            let len = self.sub_segment_len();
+            progress.start(segments.iter().map(|&(low, high)| (high - low + 1).div_ceil(len)).sum());
+        }
+    }
+}
+
+/// Per-segment metrics reported by [`segmented_sieve_parallel`]
+///
+/// Entries are indexed by segment, so in range order: one per thread unless
+/// [`ParallelOptions::segments`] asks for more; `time_ms` is the wall time a thread spent
+/// sieving (or counting) its segment, base primes excluded (included when a single thread
+/// runs inline, as the plain sieve finds them in the same pass).
+///
+/// `base_ms` and `sieve_ms` split the whole call's wall time in two: finding
+/// the base primes up to √limit, then sieving the segments and merging
+/// their results. An inline run counts all of it as `sieve_ms`.
+#[derive(Debug, Default, Clone)]
+pub struct ThreadMetrics {
+    pub segments: Vec<(u64, u64, usize, f64)>, // (low, high, prime_count, time_ms)
+    /// Time spent finding the base primes, in milliseconds
+    pub base_ms: f64,
+    /// Time spent on the segments after that, in milliseconds
+    pub sieve_ms: f64,
+}
+
+/// Milliseconds elapsed since `started`
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Segmented Sieve of Eratosthenes - Multithreaded Implementation
+///
+/// # Parallelization Strategy
+///
+/// ```text
+/// Range: [2, limit]
+///
+/// Step 1: Calculate base primes [2, √limit] sequentially
+///         These are needed by all threads
+///
+/// Step 2: Divide remaining range into segments
+///         Thread 0: [√limit + 1, segment_end_0]
+///         Thread 1: [segment_end_0 + 1, segment_end_1]
+///         ...
+///
+/// Step 3: Each thread sieves its segment independently, in sub-segments
+///         of at most DEFAULT_MAX_SEGMENT_BYTES of flags
+///         (No synchronization needed during sieving!)
+///
+/// Step 4: Collect and merge results
+/// ```
+///
+/// The returned primes are always sorted ascending, and the metrics list
+/// the threads in order, however the threads happen to finish: each one
+/// writes its results and metrics into its own `thread_id` slot.
+///
+/// With `num_threads == 1`, if the whole bit-packed sieve fits in
+/// [`DEFAULT_MAX_SEGMENT_BYTES`], no thread is spawned: [`simple_sieve`]
+/// runs on the calling thread and the metrics hold its one segment.
+///
+/// # Errors
+/// Returns the first [`SieveError`] raised by the base-prime sieve or by
+/// any thread's segment, or [`SieveError::WorkerPanicked`] for the first
+/// thread that panicked. Every thread is joined before returning.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{segmented_sieve_parallel, simple_sieve};
+///
+/// let (primes, _metrics) = segmented_sieve_parallel(10_000, 4)?;
+/// assert_eq!(primes, simple_sieve(10_000)?);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn segmented_sieve_parallel(
+    limit: u64,
+    num_threads: usize,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    segmented_sieve_parallel_with(limit, num_threads, &ParallelOptions::default())
+}
+
+/// [`segmented_sieve_parallel`] with a custom segment cap and progress counter
+///
+/// Each thread sieves its segment in sub-segments of at most
+/// `options.max_segment_bytes` of flags, so memory stays bounded however
+/// few threads cover however large a range. If `options.progress` is set,
+/// it is reset to the run's sub-segment count before any thread starts and
+/// bumped after each one, so another thread can poll it while this call
+/// is running. A single thread is run inline only while its whole sieve
+/// fits in `options.max_segment_bytes`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use primes_core::{segmented_sieve_parallel_with, ParallelOptions, SieveProgress};
+///
+/// let progress = Arc::new(SieveProgress::new());
+/// let options = ParallelOptions {
+///     max_segment_bytes: 1 << 20,
+///     progress: Some(Arc::clone(&progress)),
+///     ..ParallelOptions::default()
+/// };
+/// let (primes, _) = segmented_sieve_parallel_with(10_000_000, 4, &options)?;
+/// assert_eq!(primes.len(), 664_579);
+/// assert_eq!(progress.chunks_done(), progress.total_chunks());
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn segmented_sieve_parallel_with(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    sieve_parallel_from_base(limit, num_threads, options, simple_sieve)
+}
+
+/// [`segmented_sieve_parallel`] with the base primes taken from `cache`
+///
+/// The cache only sieves when `limit` needs base primes beyond any it
+/// has seen, so a sweep over several limits finds them once.
+pub fn segmented_sieve_parallel_cached(
+    limit: u64,
+    num_threads: usize,
+    cache: &mut BasePrimeCache,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    segmented_sieve_parallel_cached_with(limit, num_threads, &ParallelOptions::default(), cache)
+}
+
+/// [`segmented_sieve_parallel_with`] with the base primes taken from `cache`
+pub fn segmented_sieve_parallel_cached_with(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+    cache: &mut BasePrimeCache,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    sieve_parallel_from_base(limit, num_threads, options, |_| {
+        cache.base_primes(limit).map(<[u64]>::to_vec)
+    })
+}
+
+/// [`segmented_sieve_parallel_with`], getting the primes up to √limit from `sieve_base`
+fn sieve_parallel_from_base(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+    sieve_base: impl FnOnce(u64) -> Result<Vec<u64>, SieveError>,
+) -> Result<(Vec<u64>, ThreadMetrics), SieveError> {
+    if limit < 2 {
+        options.start_progress(&[]);
+        return Ok((vec![], ThreadMetrics::default()));
+    }
+
+    let num_threads = num_threads.max(1);
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    // One thread would only add spawn and locking overhead to the plain sieve
+    if runs_inline(limit, num_threads, options) {
+        let started = Instant::now();
+        let primes = simple_sieve(limit)?;
+        let segment_count = primes.len() - primes.partition_point(|&p| p <= sqrt_limit);
+        let metrics = options.inline_metrics(sqrt_limit, limit, segment_count, started);
+        return Ok((primes, metrics));
+    }
+
+    // Step 1: Find base primes (sequential)
+    // These are all primes up to sqrt(limit)
+    let base_started = Instant::now();
+    let base_primes = sieve_base(sqrt_limit)?;
+    let base_ms = elapsed_ms(base_started);
+    let sieve_started = Instant::now();
+
+    // If limit is small, base primes might be all we need
+    if sqrt_limit >= limit {
+        options.start_progress(&[]);
+        return Ok((base_primes, ThreadMetrics { base_ms, ..ThreadMetrics::default() }));
+    }
+
+    // Step 2: Divide the range (sqrt_limit + 1, limit] into segments,
+    // one per thread unless options.segments says otherwise
+    let segments = options.split(sqrt_limit, limit, num_threads);
+    options.start_progress(&segments);
+    let sub_segment_len = options.sub_segment_len();
+
+    // Shared storage for results from each segment
+    // Using Arc<Mutex<Vec>> for thread-safe collection
+    let results: Arc<Mutex<Vec<Vec<u64>>>> = Arc::new(Mutex::new(vec![vec![]; segments.len()]));
+
+    // Metrics for reporting
+    let metrics: Arc<Mutex<ThreadMetrics>> = Arc::new(Mutex::new(ThreadMetrics {
+        segments: vec![Default::default(); segments.len()],
+        base_ms,
+        sieve_ms: 0.0,
+    }));
+
+    // Share base_primes and the segment list among threads (read-only, so Arc is sufficient)
+    let base_primes = Arc::new(base_primes);
+    let segments = Arc::new(segments);
+
+    // The queue of segments: the index of the next one nobody has claimed
+    let next_segment = Arc::new(AtomicUsize::new(0));
+
+    // Step 3: Spawn threads (never more than there are segments)
+    let mut handles = vec![];
+
+    for thread_id in 0..num_threads.min(segments.len()) {
+        // Clone Arc references for this thread
+        let results = Arc::clone(&results);
+        let metrics = Arc::clone(&metrics);
+        let base_primes = Arc::clone(&base_primes);
+        let segments = Arc::clone(&segments);
+        let next_segment = Arc::clone(&next_segment);
+        let progress = options.progress.clone();
+
+        #[cfg(test)]
+        tests::THREADS_SPAWNED.with(|spawned| spawned.set(spawned.get() + 1));
+        #[cfg(test)]
+        let inject_panic = tests::PANIC_IN_THREAD.get() == Some(thread_id);
+        let handle = sieve_thread(thread_id).spawn(move || {
+            #[cfg(test)]
+            assert!(!inject_panic, "injected panic in thread {}", thread_id);
+
+            // Claim segments until the queue is empty; claiming one is a
+            // single lock-free fetch_add
+            loop {
+                let index = next_segment.fetch_add(1, Ordering::Relaxed);
+                let Some(&(seg_low, seg_high)) = segments.get(index) else {
+                    break;
+                };
+
+                // Each segment is sieved independently
+                // No synchronization needed during computation!
+                // (The progress counter is a lock-free fetch_add per sub-segment.)
+                let started = Instant::now();
+                let mut segment_primes = Vec::new();
+                for (low, high) in sub_segments(seg_low, seg_high, sub_segment_len) {
+                    segment_primes.extend(sieve_segment(low, high, &base_primes)?);
+                    if let Some(progress) = &progress {
+                        progress.chunk_done();
+                    }
+                }
+                let time_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+                let prime_count = segment_primes.len();
+
+                // Store results (requires lock)
+                // CRITICAL SECTION: Accessing shared data
+                {
+                    let mut results_guard = results.lock().unwrap();
+                    results_guard[index] = segment_primes;
+                } // Lock is released here
+
+                // Store metrics
+                {
+                    let mut metrics_guard = metrics.lock().unwrap();
+                    metrics_guard.segments[index] = (seg_low, seg_high, prime_count, time_ms);
+                }
+            }
+
+            Ok::<(), SieveError>(())
+        });
+
+        handles.push(handle.expect("Failed to spawn sieve thread"));
+    }
+
+    // Step 4: Wait for all threads to complete, keeping the first failure
+    let mut outcome = Ok(());
+    for (thread_id, handle) in handles.into_iter().enumerate() {
+        let result = handle.join().unwrap_or(Err(SieveError::WorkerPanicked { thread_id }));
+        if outcome.is_ok() {
+            outcome = result;
+        }
+    }
+    outcome?;
+
+    // Collect all primes in order, starting with the base primes. Every
+    // thread has been joined, so this is the last reference to the Arc.
+    let mut all_primes = Arc::try_unwrap(base_primes).unwrap_or_else(|shared| (*shared).clone());
+
+    // Add primes from each segment (already sorted within each segment)
+    let results_guard = results.lock().unwrap();
+    for segment_primes in results_guard.iter() {
+        all_primes.extend(segment_primes);
+    }
+
+    // Build metrics
+    let mut thread_metrics = metrics.lock().unwrap().clone();
+    thread_metrics.sieve_ms = elapsed_ms(sieve_started);
+
+    Ok((all_primes, thread_metrics))
+}
+
+/// Count π(limit) in parallel without building the prime vector
+///
+/// Uses the same decomposition as [`segmented_sieve_parallel`], but each
+/// thread runs [`count_segment`] and only returns a number, so peak memory
+/// is one chunk's flags per thread.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::segmented_count_parallel;
+///
+/// let (count, metrics) = segmented_count_parallel(1_000_000, 4)?;
+/// assert_eq!(count, 78_498);
+/// assert_eq!(metrics.segments.len(), 4);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn segmented_count_parallel(
+    limit: u64,
+    num_threads: usize,
+) -> Result<(usize, ThreadMetrics), SieveError> {
+    segmented_count_parallel_with(limit, num_threads, &ParallelOptions::default())
+}
+
+/// [`segmented_count_parallel`] with a custom segment cap and progress counter
+///
+/// See [`segmented_sieve_parallel_with`].
+pub fn segmented_count_parallel_with(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+) -> Result<(usize, ThreadMetrics), SieveError> {
+    count_parallel_from_base(limit, num_threads, options, simple_sieve)
+}
+
+/// [`segmented_count_parallel_with`] with the base primes taken from `cache`
+pub fn segmented_count_parallel_cached_with(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+    cache: &mut BasePrimeCache,
+) -> Result<(usize, ThreadMetrics), SieveError> {
+    count_parallel_from_base(limit, num_threads, options, |_| {
+        cache.base_primes(limit).map(<[u64]>::to_vec)
+    })
+}
+
+/// [`segmented_count_parallel_with`], getting the primes up to √limit from `sieve_base`
+fn count_parallel_from_base(
+    limit: u64,
+    num_threads: usize,
+    options: &ParallelOptions,
+    sieve_base: impl FnOnce(u64) -> Result<Vec<u64>, SieveError>,
+) -> Result<(usize, ThreadMetrics), SieveError> {
+    if limit < 2 {
+        options.start_progress(&[]);
+        return Ok((0, ThreadMetrics::default()));
+    }
+
+    let num_threads = num_threads.max(1);
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    if runs_inline(limit, num_threads, options) {
+        let started = Instant::now();
+        let total = count_primes(limit)?;
+        let segment_count = total - count_primes(sqrt_limit)?;
+        return Ok((total, options.inline_metrics(sqrt_limit, limit, segment_count, started)));
+    }
+
+    let base_started = Instant::now();
+    let base_primes = sieve_base(sqrt_limit)?;
+    let base_ms = elapsed_ms(base_started);
+    let sieve_started = Instant::now();
+
+    if sqrt_limit >= limit {
+        options.start_progress(&[]);
+        return Ok((base_primes.len(), ThreadMetrics { base_ms, ..ThreadMetrics::default() }));
+    }
+
+    let segments = options.split(sqrt_limit, limit, num_threads);
+    options.start_progress(&segments);
+    let sub_segment_len = options.sub_segment_len();
+    let next_segment = AtomicUsize::new(0);
+
+    // Scoped threads can borrow base_primes and the queue directly; each
+    // returns (segment index, count, time_ms) for the segments it claimed
+    let per_thread = thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_threads.min(segments.len()))
+            .map(|thread_id| {
+                let (base_primes, segments, next_segment) = (&base_primes, &segments, &next_segment);
+                let progress = &options.progress;
+                #[cfg(test)]
+                tests::THREADS_SPAWNED.with(|spawned| spawned.set(spawned.get() + 1));
+                #[cfg(test)]
+                let inject_panic = tests::PANIC_IN_THREAD.get() == Some(thread_id);
+                let handle = sieve_thread(thread_id).spawn_scoped(scope, move || {
+                    #[cfg(test)]
+                    assert!(!inject_panic, "injected panic in thread {}", thread_id);
+
+                    let mut counted = Vec::new();
+                    loop {
+                        let index = next_segment.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(low, high)) = segments.get(index) else {
+                            break;
+                        };
+                        let started = Instant::now();
+                        let mut count = 0;
+                        for (sub_low, sub_high) in sub_segments(low, high, sub_segment_len) {
+                            count += count_segment(sub_low, sub_high, base_primes)?;
+                            if let Some(progress) = progress {
+                                progress.chunk_done();
+                            }
+                        }
+                        counted.push((index, count, started.elapsed().as_secs_f64() * 1000.0));
+                    }
+                    Ok(counted)
+                });
+                handle.expect("Failed to spawn sieve thread")
+            })
+            .collect();
+
+        // Join them all before checking, or the scope would re-raise a
+        // panic from a thread left unjoined after an earlier error
+        let results: Vec<_> = handles
+            .into_iter()
+            .enumerate()
+            .map(|(thread_id, handle)| handle.join().unwrap_or(Err(SieveError::WorkerPanicked { thread_id })))
+            .collect();
+        results.into_iter().collect::<Result<Vec<Vec<(usize, usize, f64)>>, SieveError>>()
+    })?;
+
+    let mut counts = vec![(0, 0.0); segments.len()];
+    for (index, count, time_ms) in per_thread.into_iter().flatten() {
+        counts[index] = (count, time_ms);
+    }
+
+    let total = base_primes.len() + counts.iter().map(|&(count, _)| count).sum::<usize>();
+    let thread_metrics = ThreadMetrics {
+        segments: segments
+            .iter()
+            .zip(&counts)
+            .map(|(&(low, high), &(count, time_ms))| (low, high, count, time_ms))
+            .collect(),
+        base_ms,
+        sieve_ms: elapsed_ms(sieve_started),
+    };
+
+    Ok((total, thread_metrics))
+}
+
+/// Builder for the `thread_id`-th thread of a parallel sieve, named `sieve-<thread_id>`
+fn sieve_thread(thread_id: usize) -> thread::Builder {
+    thread::Builder::new().name(format!("sieve-{}", thread_id))
+}
+
+/// Whether a run can skip the threads and sieve `[0, limit]` in one go
+///
+/// Only for a single thread asked for at most one segment, and only if the
+/// bit-packed sieve of the whole range stays within the per-thread cap, so
+/// memory is bounded as before.
+fn runs_inline(limit: u64, num_threads: usize, options: &ParallelOptions) -> bool {
+    num_threads == 1
+        && options.segments.unwrap_or(1) <= 1
+        && (limit / 8).saturating_add(1) <= options.max_segment_bytes
+}
+
+/// Split [start, end] into at most `parts` contiguous, equally sized segments
+///
+/// `parts` is clamped to `1..=end - start + 1`, so every segment holds at
+/// least one number. The last segment may be shorter; segments that would
+/// start past `end` are omitted.
+fn split_range(start: u64, end: u64, parts: usize) -> Vec<(u64, u64)> {
+    let range_size = end - start + 1;
+    let parts = (parts as u64).clamp(1, range_size);
+    let segment_size = range_size.div_ceil(parts);
+
+    (0..parts)
+        .map(|i| start + i * segment_size)
+        .take_while(|&low| low <= end)
+        .map(|low| (low, std::cmp::min(low + segment_size - 1, end)))
+        .collect()
+}
+
+/// Split [low, high] into consecutive pieces of at most `len` numbers
+fn sub_segments(low: u64, high: u64, len: u64) -> impl Iterator<Item = (u64, u64)> {
+    let mut next = Some(low).filter(|&low| low <= high);
+    std::iter::from_fn(move || {
+        let sub_low = next?;
+        let sub_high = sub_low.saturating_add(len.max(1) - 1).min(high);
+        next = sub_high.checked_add(1).filter(|&low| low <= high);
+        Some((sub_low, sub_high))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Number of `simple_sieve` calls made on the current thread
+        pub(crate) static SIMPLE_SIEVE_CALLS: Cell<usize> = const { Cell::new(0) };
+        /// Number of sieve threads spawned by the current thread
+        pub(crate) static THREADS_SPAWNED: Cell<usize> = const { Cell::new(0) };
+        /// Sieve thread that panics instead of sieving, in runs started from the current thread
+        pub(crate) static PANIC_IN_THREAD: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    #[test]
+    fn test_small_primes() {
+        let primes = simple_sieve(30).unwrap();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    }
+
+    #[test]
+    fn test_prime_count_100() {
+        let primes = simple_sieve(100).unwrap();
+        assert_eq!(primes.len(), 25); // There are 25 primes <= 100
+    }
+
+    #[test]
+    fn test_prime_count_1000() {
+        let primes = simple_sieve(1000).unwrap();
+        assert_eq!(primes.len(), 168); // There are 168 primes <= 1000
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(simple_sieve(0).unwrap(), vec![]);
+        assert_eq!(simple_sieve(1).unwrap(), vec![]);
+        assert_eq!(simple_sieve(2).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_sieve_into_reuses_the_buffer() {
+        let mut primes = Vec::new();
+        sieve_into(100, &mut primes).unwrap();
+        assert_eq!(primes, simple_sieve(100).unwrap());
+        assert_eq!(primes.len(), 25);
+        let capacity = primes.capacity();
+
+        // A smaller limit leaves nothing of the first call behind
+        sieve_into(30, &mut primes).unwrap();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(primes.capacity(), capacity);
+
+        sieve_into(1, &mut primes).unwrap();
+        assert!(primes.is_empty());
+        primes.push(7);
+        assert!(sieve_into(MAX_SIEVE_LIMIT + 1, &mut primes).is_err());
+        assert!(primes.is_empty());
+    }
+
+    #[test]
+    fn test_segment_sieve() {
+        let base_primes = vec![2, 3, 5, 7];
+        let segment = sieve_segment(10, 20, &base_primes).unwrap();
+        assert_eq!(segment, vec![11, 13, 17, 19]);
+    }
+
+    #[test]
+    fn test_incomplete_base_primes_are_detected() {
+        // √1_000_000 = 1000, but these stop at 499 (the last prime below 500)
+        let (low, high) = (999_000, 1_000_000);
+        let half = simple_sieve(500).unwrap();
+        let error = SieveError::IncompleteBasePrimes { missing: 503, high };
+        assert_eq!(sieve_segment(low, high, &half), Err(error.clone()));
+        assert_eq!(count_segment(low, high, &half), Err(error.clone()));
+        assert_eq!(sieve_range(low, high + 1, &half), Err(error));
+        assert!(sieve_segment(10, 20, &[]).is_err());
+
+        // Complete lists pass, including ones that run past √high
+        let full = simple_sieve(1000).unwrap();
+        let expected: Vec<u64> = simple_sieve(high).unwrap().into_iter().filter(|&p| p >= low).collect();
+        assert_eq!(sieve_segment(low, high, &full).unwrap(), expected);
+        assert_eq!(sieve_segment(low, high, &simple_sieve(2000).unwrap()).unwrap(), expected);
+        assert_eq!(sieve_segment(0, 3, &[]).unwrap(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_segments_at_the_low_end() {
+        let base_primes = simple_sieve(3).unwrap();
+        for low in [0, 1, 2] {
+            assert_eq!(sieve_segment(low, 10, &base_primes).unwrap(), vec![2, 3, 5, 7], "low = {}", low);
+            assert_eq!(count_segment(low, 10, &base_primes).unwrap(), 4, "low = {}", low);
+        }
+
+        // Segments holding only 0 and 1, or one of them
+        for (low, high) in [(0, 0), (0, 1), (1, 1)] {
+            assert_eq!(sieve_segment(low, high, &[]).unwrap(), vec![], "[{}, {}]", low, high);
+            assert_eq!(count_segment(low, high, &[]).unwrap(), 0, "[{}, {}]", low, high);
+        }
+        assert_eq!(sieve_segment(1, 2, &[]).unwrap(), vec![2]);
+        assert_eq!(sieve_segment(2, 2, &[]).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_half_open_range_tiles() {
+        let base_primes = vec![2, 3, 5];
+        let whole = sieve_range(10, 30, &base_primes).unwrap();
+        assert_eq!(whole, vec![11, 13, 17, 19, 23, 29]);
+
+        let mut tiled = sieve_range(10, 20, &base_primes).unwrap();
+        tiled.extend(sieve_range(20, 30, &base_primes).unwrap());
+        assert_eq!(tiled, whole);
+
+        // The end is excluded, the start included
+        assert_eq!(sieve_range(11, 13, &base_primes).unwrap(), vec![11]);
+        assert!(sieve_range(30, 10, &base_primes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_sieve_matches_sequential() {
+        let limit = 10_000;
+        let expected = simple_sieve(limit).unwrap();
+
+        for threads in [1, 2, 4, 8] {
+            let (result, _) = segmented_sieve_parallel(limit, threads).unwrap();
+            assert_eq!(result, expected, "Mismatch with {} threads", threads);
+        }
+    }
+
+    #[test]
+    fn test_more_threads_than_numbers() {
+        let (primes, metrics) = segmented_sieve_parallel(20, 64).unwrap();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19]);
+        // Only 5..=20 is left after the base primes, one number per thread at most
+        assert_eq!(metrics.segments.len(), 16);
+        assert!(metrics.segments.iter().all(|&(low, high, ..)| low <= high && high <= 20));
+        assert_eq!(segmented_count_parallel(20, 64).unwrap().0, 8);
+
+        let (primes, metrics) = segmented_sieve_parallel(2, 8).unwrap();
+        assert_eq!(primes, vec![2]);
+        assert_eq!(metrics.segments, vec![(2, 2, 1, metrics.segments[0].3)]);
+
+        assert_eq!(split_range(5, 20, 0), vec![(5, 20)]);
+    }
+
+    #[test]
+    fn test_bit_sieve_matches_byte_sieve() {
+        let limit = 1_000_000;
+        let bits = simple_sieve(limit).unwrap();
+        let bytes = simple_sieve_bytes(limit).unwrap();
+        assert_eq!(bits.len(), 78_498);
+        assert_eq!(bits, bytes);
+    }
+
+    #[test]
+    fn test_odd_sieve_matches_classic() {
+        for limit in [100, 1000, 1_000_000] {
+            assert_eq!(
+                odd_sieve(limit).unwrap(),
+                simple_sieve(limit).unwrap(),
+                "Mismatch at limit {}",
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_odd_sieve_boundaries() {
+        assert_eq!(odd_sieve(1).unwrap(), vec![]);
+        assert_eq!(odd_sieve(2).unwrap(), vec![2]);
+        assert_eq!(odd_sieve(3).unwrap(), vec![2, 3]);
+        // Even limit: the last stored candidate is limit - 1
+        assert_eq!(odd_sieve(24).unwrap(), vec![2, 3, 5, 7, 11, 13, 17, 19, 23]);
+        // Odd prime limit is included
+        assert_eq!(odd_sieve(29).unwrap().last(), Some(&29));
+        // Odd square limit is excluded
+        assert_eq!(odd_sieve(49).unwrap().last(), Some(&47));
+    }
+
+    #[test]
+    fn test_count_primes() {
+        assert_eq!(count_primes(1).unwrap(), 0);
+        assert_eq!(count_primes(2).unwrap(), 1);
+        assert_eq!(count_primes(1_000_000).unwrap(), 78_498);
+    }
+
+    #[test]
+    fn test_parallel_count_matches_count() {
+        for threads in [1, 3, 8] {
+            let (count, _) = segmented_count_parallel(1_000_000, threads).unwrap();
+            assert_eq!(count, 78_498, "Mismatch with {} threads", threads);
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_limit_too_large() {
+        let err = simple_sieve(MAX_SIEVE_LIMIT + 1).unwrap_err();
+        assert_eq!(
+            err,
+            SieveError::LimitTooLarge {
+                requested: MAX_SIEVE_LIMIT + 1,
+                max_supported: MAX_SIEVE_LIMIT,
+            }
+        );
+        assert!(sieve_segment(0, u64::MAX, &[2]).is_err());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn test_limit_beyond_address_space() {
+        // limit + 1 flags cannot be indexed by a 32-bit usize
+        let limit = usize::MAX as u64;
+        let expected = SieveError::LimitTooLarge {
+            requested: limit,
+            max_supported: limit - 1,
+        };
+        assert_eq!(simple_sieve(limit).unwrap_err(), expected);
+        assert_eq!(simple_sieve_bytes(limit).unwrap_err(), expected);
+        assert!(segmented_sieve_parallel(limit, 2).is_err());
+    }
+
+    #[test]
+    fn test_segment_above_2_pow_40() {
+        let low = 1u64 << 40;
+        let high = low + 200;
+        let base_primes = simple_sieve((high as f64).sqrt() as u64 + 1).unwrap();
+        let segment = sieve_segment(low, high, &base_primes).unwrap();
+        assert_eq!(
+            segment,
+            vec![
+                1_099_511_627_791,
+                1_099_511_627_803,
+                1_099_511_627_831,
+                1_099_511_627_873,
+                1_099_511_627_891,
+                1_099_511_627_917,
+                1_099_511_627_933,
+                1_099_511_627_953,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segment_near_u64_max_does_not_overflow() {
+        // 4_294_967_311 is the first prime above 2^32, so its square overflows u64
+        let low = u64::MAX - 100;
+        let segment = sieve_segment(low, u64::MAX, &[2, 3, 5, 4_294_967_311]).unwrap();
+        assert!(segment.iter().all(|n| n % 2 != 0 && n % 3 != 0 && n % 5 != 0));
+        assert!(segment.contains(&18_446_744_073_709_551_557)); // largest u64 prime
+    }
+
+    #[test]
+    fn test_parallel_sieve_computes_base_primes_once() {
+        SIMPLE_SIEVE_CALLS.with(|calls| calls.set(0));
+        let (primes, _) = segmented_sieve_parallel(100_000, 4).unwrap();
+        assert_eq!(primes.len(), 9592);
+        assert_eq!(SIMPLE_SIEVE_CALLS.with(Cell::get), 1);
+    }
+
+    #[test]
+    fn test_base_and_sieve_times_add_up_to_the_run() {
+        // Whatever the call spends outside the two phases is setup: allow
+        // a tenth of the run, or a few milliseconds on a busy machine
+        let check = |metrics: &ThreadMetrics, total_ms: f64| {
+            assert!(metrics.base_ms >= 0.0 && metrics.sieve_ms >= 0.0, "{:?}", metrics);
+            let phases_ms = metrics.base_ms + metrics.sieve_ms;
+            assert!(phases_ms <= total_ms, "{} > {}", phases_ms, total_ms);
+            assert!(total_ms - phases_ms <= total_ms / 10.0 + 5.0, "{} of {}", phases_ms, total_ms);
+        };
+
+        let started = Instant::now();
+        let (_, metrics) = segmented_sieve_parallel(2_000_000, 3).unwrap();
+        check(&metrics, elapsed_ms(started));
+        assert!(metrics.base_ms > 0.0 && metrics.sieve_ms > 0.0);
+
+        let started = Instant::now();
+        let (_, metrics) = segmented_count_parallel(2_000_000, 3).unwrap();
+        check(&metrics, elapsed_ms(started));
+
+        // Inline, the plain sieve finds the base primes in the same pass
+        let started = Instant::now();
+        let (_, metrics) = segmented_sieve_parallel(100_000, 1).unwrap();
+        check(&metrics, elapsed_ms(started));
+        assert_eq!(metrics.base_ms, 0.0);
+    }
+
+    #[test]
+    fn test_single_thread_spawns_nothing() {
+        let expected = simple_sieve(1_000_000).unwrap();
+        THREADS_SPAWNED.with(|spawned| spawned.set(0));
+
+        let (primes, metrics) = segmented_sieve_parallel(1_000_000, 1).unwrap();
+        assert_eq!(primes, expected);
+        let [(low, high, prime_count, _)] = metrics.segments[..] else {
+            panic!("expected one segment: {:?}", metrics);
+        };
+        assert_eq!((low, high, prime_count), (1001, 1_000_000, 78_498 - 168));
+
+        let (count, metrics) = segmented_count_parallel(1_000_000, 1).unwrap();
+        assert_eq!(count, expected.len());
+        assert_eq!(metrics.segments[0].2, 78_498 - 168);
+        assert_eq!(THREADS_SPAWNED.with(Cell::get), 0);
+
+        // Two threads, or one whose sieve would exceed the cap, still spawn
+        segmented_sieve_parallel(1_000_000, 2).unwrap();
+        assert_eq!(THREADS_SPAWNED.with(Cell::get), 2);
+        let options = ParallelOptions {
+            max_segment_bytes: 4096,
+            ..ParallelOptions::default()
+        };
+        let (primes, _) = segmented_sieve_parallel_with(1_000_000, 1, &options).unwrap();
+        assert_eq!(primes, expected);
+        assert_eq!(THREADS_SPAWNED.with(Cell::get), 3);
+    }
+
+    #[test]
+    fn test_panicking_thread_becomes_error() {
+        PANIC_IN_THREAD.with(|thread| thread.set(Some(2)));
+        let sieved = segmented_sieve_parallel(100_000, 4);
+        let counted = segmented_count_parallel(100_000, 4);
+        PANIC_IN_THREAD.with(|thread| thread.set(None));
+
+        assert_eq!(sieved.unwrap_err(), SieveError::WorkerPanicked { thread_id: 2 });
+        assert_eq!(counted.unwrap_err(), SieveError::WorkerPanicked { thread_id: 2 });
+        assert_eq!(SieveError::WorkerPanicked { thread_id: 2 }.to_string(), "sieve thread 2 panicked");
+
+        // The process carries on, and so do later runs
+        assert_eq!(segmented_sieve_parallel(100_000, 4).unwrap().0.len(), 9592);
+    }
+
+    #[test]
+    fn test_more_segments_than_threads() {
+        let options = ParallelOptions {
+            segments: Some(16),
+            ..ParallelOptions::default()
+        };
+        let expected = simple_sieve(1_000_000).unwrap();
+        THREADS_SPAWNED.with(|spawned| spawned.set(0));
+
+        let (primes, metrics) = segmented_sieve_parallel_with(1_000_000, 4, &options).unwrap();
+        assert_eq!(primes, expected);
+        let ranges: Vec<(u64, u64)> = metrics.segments.iter().map(|&(low, high, ..)| (low, high)).collect();
+        assert_eq!(ranges, split_range(1001, 1_000_000, 16));
+        let segment_primes: usize = metrics.segments.iter().map(|&(_, _, count, _)| count).sum();
+        assert_eq!(segment_primes, expected.len() - 168);
+
+        let (count, metrics) = segmented_count_parallel_with(1_000_000, 4, &options).unwrap();
+        assert_eq!(count, expected.len());
+        assert_eq!(metrics.segments.len(), 16);
+        assert_eq!(THREADS_SPAWNED.with(Cell::get), 8);
+
+        // One thread still works through all of them
+        let (primes, metrics) = segmented_sieve_parallel_with(1_000_000, 1, &options).unwrap();
+        assert_eq!(primes, expected);
+        assert_eq!(metrics.segments.len(), 16);
+    }
+
+    #[test]
+    fn test_resolve_threads() {
+        assert!(resolve_threads(None) >= 1);
+        assert_eq!(resolve_threads(Some(6)), 6);
+        assert_eq!(resolve_threads(Some(0)), 1);
+    }
+
+    #[test]
+    fn test_parallel_output_is_deterministic() {
+        let (expected, _) = segmented_sieve_parallel(200_000, 8).unwrap();
+        assert!(expected.windows(2).all(|pair| pair[0] < pair[1]));
+
+        for _ in 0..50 {
+            let (primes, metrics) = segmented_sieve_parallel(200_000, 8).unwrap();
+            assert_eq!(primes, expected);
+            let ranges: Vec<(u64, u64)> = metrics.segments.iter().map(|&(low, high, ..)| (low, high)).collect();
+            assert_eq!(ranges, split_range(448, 200_000, 8)); // √200_000 ≈ 447.2
+        }
+    }
+
+    #[test]
+    fn test_sub_segments() {
+        let split: Vec<_> = sub_segments(10, 35, 10).collect();
+        assert_eq!(split, vec![(10, 19), (20, 29), (30, 35)]);
+        assert_eq!(sub_segments(10, 9, 10).count(), 0);
+        assert_eq!(sub_segments(u64::MAX - 1, u64::MAX, 1).count(), 2);
+    }
+
+    #[test]
+    fn test_tiny_max_segment_matches_full_sieve() {
+        let options = ParallelOptions {
+            max_segment_bytes: 4096,
+            ..ParallelOptions::default()
+        };
+        let expected = simple_sieve(1_000_000).unwrap();
+
+        let (primes, metrics) = segmented_sieve_parallel_with(1_000_000, 2, &options).unwrap();
+        assert_eq!(primes, expected);
+        // Metrics still describe each thread's whole segment
+        assert_eq!(metrics.segments.len(), 2);
+
+        let (count, _) = segmented_count_parallel_with(1_000_000, 2, &options).unwrap();
+        assert_eq!(count, expected.len());
+    }
+}
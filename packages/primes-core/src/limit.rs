@@ -0,0 +1,116 @@
+//! Parsing limits written as `10M`, `2G` or `1e9` on the command line
+
+/// Decimal exponent of each suffix [`parse_limit`] accepts (case-insensitive)
+const SUFFIXES: [(char, u32); 5] = [('k', 3), ('m', 6), ('g', 9), ('b', 9), ('t', 12)];
+
+/// Parse a limit given as digits, scientific notation or with a suffix
+///
+/// Accepts plain integers (`10000000`, `10_000_000`), an exponent
+/// (`1e9`, `2.5e6`) and the suffixes `K` (10³), `M` (10⁶), `G`/`B` (10⁹) and
+/// `T` (10¹²), which may follow a decimal (`1.5M`). The value is computed
+/// exactly, with no floating point: a form that does not land on a whole
+/// number, such as `1.5` or `1e-3`, is rejected, as is one above
+/// `u64::MAX`.
+///
+/// Meant as a clap `value_parser` for `--limit`.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::parse_limit;
+///
+/// assert_eq!(parse_limit("10M"), Ok(10_000_000));
+/// assert_eq!(parse_limit("1e9"), Ok(1_000_000_000));
+/// assert!(parse_limit("1.5").is_err());
+/// ```
+pub fn parse_limit(s: &str) -> Result<u64, String> {
+    let text: String = s.trim().chars().filter(|&c| c != '_').collect();
+
+    // Suffix, then exponent, then the mantissa that is left
+    let (text, suffix_exp) = match text.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some(last) => match SUFFIXES.iter().find(|&&(suffix, _)| suffix == last) {
+            Some(&(_, exp)) => (&text[..text.len() - 1], exp as i64),
+            None => (&text[..], 0),
+        },
+        None => return Err("empty limit".to_string()),
+    };
+    let (mantissa, exp) = match text.find(['e', 'E']) {
+        Some(at) => {
+            let exp: i64 = text[at + 1..]
+                .parse()
+                .map_err(|_| format!("invalid limit {:?}: bad exponent", s))?;
+            (&text[..at], exp)
+        }
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("invalid limit {:?}: expected a number like 10000000, 10M or 1e7", s));
+    }
+
+    // value = digits × 10^shift, with the fraction's trailing zeros dropped
+    let fraction = fraction.trim_end_matches('0');
+    let shift = exp
+        .checked_add(suffix_exp)
+        .and_then(|e| e.checked_sub(fraction.len() as i64))
+        .ok_or_else(|| format!("invalid limit {:?}: exponent out of range", s))?;
+    let digits = format!("{}{}", whole, fraction);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    if shift < 0 {
+        return Err(format!("invalid limit {:?}: not a whole number", s));
+    }
+
+    let overflow = || format!("invalid limit {:?}: larger than {}", s, u64::MAX);
+    let base: u64 = digits.parse().map_err(|_| overflow())?;
+    u32::try_from(shift)
+        .ok()
+        .and_then(|shift| 10u64.checked_pow(shift))
+        .and_then(|scale| base.checked_mul(scale))
+        .ok_or_else(overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suffixes_and_exponents() {
+        assert_eq!(parse_limit("10M"), Ok(10_000_000));
+        assert_eq!(parse_limit("1e9"), Ok(1_000_000_000));
+        assert_eq!(parse_limit("2G"), Ok(2_000_000_000));
+        assert_eq!(parse_limit("1B"), Ok(1_000_000_000));
+        assert_eq!(parse_limit("5k"), Ok(5_000));
+        assert_eq!(parse_limit("1.5M"), Ok(1_500_000));
+        assert_eq!(parse_limit("2.5e6"), Ok(2_500_000));
+        assert_eq!(parse_limit("1e1T"), Ok(10_000_000_000_000));
+        assert_eq!(parse_limit("10_000_000"), Ok(10_000_000));
+        assert_eq!(parse_limit("18446744073709551615"), Ok(u64::MAX));
+        assert_eq!(parse_limit("0"), Ok(0));
+        assert_eq!(parse_limit("0e400"), Ok(0));
+    }
+
+    #[test]
+    fn test_fractions_are_rejected() {
+        assert!(parse_limit("1.5").unwrap_err().contains("not a whole number"));
+        assert!(parse_limit("1e-3").unwrap_err().contains("not a whole number"));
+        assert!(parse_limit("1.0000001M").unwrap_err().contains("not a whole number"));
+        assert_eq!(parse_limit("1.000000M"), Ok(1_000_000));
+        assert_eq!(parse_limit("12.5e-1"), Err("invalid limit \"12.5e-1\": not a whole number".to_string()));
+    }
+
+    #[test]
+    fn test_overflow_and_garbage_error_cleanly() {
+        assert!(parse_limit("18446744073709551616").unwrap_err().contains("larger than"));
+        assert!(parse_limit("1e20").unwrap_err().contains("larger than"));
+        assert!(parse_limit("20000000T").unwrap_err().contains("larger than"));
+        assert!(parse_limit("1e99999999999999999999").unwrap_err().contains("bad exponent"));
+        for bad in ["", "M", "ten", "-5", "1e", "1.2.3", "10X"] {
+            assert!(parse_limit(bad).is_err(), "{:?}", bad);
+        }
+    }
+}
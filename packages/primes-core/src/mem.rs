@@ -0,0 +1,47 @@
+//! Peak memory of the running process, for `--report-mem`
+//!
+//! On Linux the kernel keeps the resident set's high-water mark as `VmHWM`
+//! in `/proc/self/status`. Other platforms report nothing.
+
+/// Peak resident set size of this process in bytes, or `None` where it
+/// cannot be read
+pub fn peak_rss_bytes() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| parse_vm_hwm(&status))
+    } else {
+        None
+    }
+}
+
+/// Peak resident set size in MiB, as the apps print it
+pub fn peak_rss_mb() -> Option<f64> {
+    peak_rss_bytes().map(|bytes| bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// The `VmHWM:` line of a `/proc/<pid>/status` file, in bytes
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status = "Name:\tprimes-sequential\nVmPeak:\t  131244 kB\nVmHWM:\t   12480 kB\nVmRSS:\t   12480 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(12_480 * 1024));
+        assert_eq!(parse_vm_hwm("VmRSS:\t 100 kB\n"), None);
+        assert_eq!(parse_vm_hwm("VmHWM:\t lots\n"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_peak_rss_is_read_on_linux() {
+        assert!(peak_rss_bytes().unwrap() > 0);
+    }
+}
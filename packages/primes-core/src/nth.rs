@@ -0,0 +1,177 @@
+//! Finding the n-th prime
+//!
+//! Instead of sieving up to a fixed limit, [`find_nth_prime`] walks upward
+//! in fixed-size segments, counting primes until the n-th one is reached.
+//! [`primes_from`] walks upward the same way from any starting value,
+//! collecting primes until it has as many as were asked for.
+
+use crate::error::{check_limit, SieveError};
+use crate::{count_segment, sieve_segment, simple_sieve, MAX_SIEVE_LIMIT};
+
+/// Numbers sieved per step (256 KiB of `bool` flags)
+const NTH_SEGMENT_SIZE: u64 = 1 << 18;
+
+/// Upper bound for the n-th prime from the prime number theorem
+///
+/// For n >= 6, p(n) < n (ln n + ln ln n) (Rosser's theorem); smaller n are
+/// covered by the constant 13 = p(6).
+fn nth_prime_upper_bound(n: usize) -> u64 {
+    if n < 6 {
+        return 13;
+    }
+    let n = n as f64;
+    (n * (n.ln() + n.ln().ln())).ceil() as u64
+}
+
+/// Find the n-th prime (1-based: the 1st prime is 2)
+///
+/// # Algorithm
+///
+/// 1. Estimate an upper bound for p(n) and sieve base primes up to its root
+/// 2. Count the primes in consecutive segments of [2, ∞) with
+///    [`count_segment`], which never collects them
+/// 3. Once a segment would reach the n-th prime, sieve just that segment
+///    and pick the prime out of it
+///
+/// If the estimate is ever exceeded, the bound is doubled and the base
+/// primes are recomputed, so the search keeps growing upward.
+///
+/// # Errors
+/// [`SieveError::ZeroIndex`] if `n` is 0, or [`SieveError::LimitTooLarge`]
+/// if the n-th prime lies beyond [`MAX_SIEVE_LIMIT`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::find_nth_prime;
+///
+/// assert_eq!(find_nth_prime(1)?, 2);
+/// assert_eq!(find_nth_prime(1000)?, 7919);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn find_nth_prime(n: usize) -> Result<u64, SieveError> {
+    if n == 0 {
+        return Err(SieveError::ZeroIndex);
+    }
+
+    let mut bound = nth_prime_upper_bound(n).min(MAX_SIEVE_LIMIT);
+    let mut base_primes = simple_sieve((bound as f64).sqrt() as u64)?;
+
+    let mut found = 0;
+    let mut low = 2;
+    loop {
+        // Fails once the search runs past MAX_SIEVE_LIMIT
+        check_limit(low)?;
+        let high = (low + NTH_SEGMENT_SIZE - 1).min(MAX_SIEVE_LIMIT);
+
+        // Keep the base primes covering √high as the search grows
+        if high > bound {
+            bound = bound.saturating_mul(2).clamp(high, MAX_SIEVE_LIMIT);
+            base_primes = simple_sieve((bound as f64).sqrt() as u64)?;
+        }
+
+        let count = count_segment(low, high, &base_primes)?;
+        if found + count >= n {
+            let primes = sieve_segment(low, high, &base_primes)?;
+            return Ok(primes[n - found - 1]);
+        }
+
+        found += count;
+        low = high + 1;
+    }
+}
+
+/// The first `take` primes greater than or equal to `from`, in ascending order
+///
+/// `from` itself is included when it is prime. Segments of [from, ∞) are
+/// sieved one after another until enough primes are collected; the base
+/// primes start out covering about `take` average prime gaps past `from`
+/// and double, as in [`find_nth_prime`], if the search runs further.
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if the primes run past [`MAX_SIEVE_LIMIT`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::primes_from;
+///
+/// assert_eq!(primes_from(10, 4)?, vec![11, 13, 17, 19]);
+/// assert_eq!(primes_from(13, 1)?, vec![13]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn primes_from(from: u64, take: usize) -> Result<Vec<u64>, SieveError> {
+    let mut primes = Vec::new();
+    if take == 0 {
+        return Ok(primes);
+    }
+
+    let mut low = from.max(2);
+    // Primes near `low` are about ln(low) apart; allow twice that per prime
+    let reach = take as f64 * 2.0 * (low as f64).ln().max(1.0);
+    let mut bound = low.saturating_add(reach as u64).min(MAX_SIEVE_LIMIT);
+    let mut base_primes = simple_sieve(bound.isqrt())?;
+    // A short list needs no full-size segment
+    let segment_size = (reach as u64).clamp(1 << 12, NTH_SEGMENT_SIZE);
+
+    loop {
+        // Fails once the search runs past MAX_SIEVE_LIMIT
+        check_limit(low)?;
+        let high = low.saturating_add(segment_size - 1).min(MAX_SIEVE_LIMIT);
+
+        if high > bound {
+            bound = bound.saturating_mul(2).clamp(high, MAX_SIEVE_LIMIT);
+            base_primes = simple_sieve(bound.isqrt())?;
+        }
+
+        for prime in sieve_segment(low, high, &base_primes)? {
+            primes.push(prime);
+            if primes.len() == take {
+                return Ok(primes);
+            }
+        }
+        low = high + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_nth_prime() {
+        assert_eq!(find_nth_prime(1).unwrap(), 2);
+        assert_eq!(find_nth_prime(6).unwrap(), 13);
+        assert_eq!(find_nth_prime(10_001).unwrap(), 104_743);
+        // Spans several segments
+        assert_eq!(find_nth_prime(1_000_000).unwrap(), 15_485_863);
+    }
+
+    #[test]
+    fn test_find_nth_prime_zero_is_error() {
+        assert_eq!(find_nth_prime(0), Err(SieveError::ZeroIndex));
+    }
+
+    #[test]
+    fn test_primes_from() {
+        assert_eq!(primes_from(10, 4).unwrap(), vec![11, 13, 17, 19]);
+        // A prime starting value is included
+        assert_eq!(primes_from(13, 1).unwrap(), vec![13]);
+        assert_eq!(primes_from(0, 3).unwrap(), vec![2, 3, 5]);
+        assert_eq!(primes_from(10, 0).unwrap(), vec![]);
+
+        // Spans several segments
+        let all = simple_sieve(2_000_000).unwrap();
+        let expected: Vec<u64> = all.iter().copied().filter(|&p| p >= 1_000_000).take(50_000).collect();
+        assert_eq!(expected.len(), 50_000);
+        assert_eq!(primes_from(1_000_000, 50_000).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_upper_bound_holds() {
+        let primes = simple_sieve(200_000).unwrap();
+        for n in [1, 2, 5, 6, 100, 10_000, primes.len()] {
+            assert!(nth_prime_upper_bound(n) >= primes[n - 1], "n = {}", n);
+        }
+    }
+}
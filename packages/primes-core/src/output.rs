@@ -0,0 +1,286 @@
+//! Writing prime lists to files
+//!
+//! Used by the `--output` option of the apps so large runs can be saved
+//! without printing millions of numbers to the terminal. For the terminal,
+//! [`sample_indices`] thins a list down to every N-th prime (`--sample`).
+//!
+//! The `bin-delta` format stores the gaps between consecutive primes
+//! rather than the primes themselves. Gaps are small, so as LEB128
+//! varints (7 bits per byte, high bit set on all but the last byte) most
+//! take a single byte instead of eight:
+//!
+//! ```text
+//! [magic: "PRMD"][version: u8][count: u64 LE][gap from previous: varint; count]
+//! ```
+//!
+//! The first gap is measured from 0. [`read_bin_delta`] decodes it.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// On-disk layout of a prime list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One decimal prime per line
+    #[default]
+    Lines,
+    /// A single comma-separated line
+    Comma,
+    /// Raw little-endian `u64` values, 8 bytes per prime
+    Binary,
+    /// Header, then the varint-encoded gap to each prime (see the module docs)
+    BinDelta,
+}
+
+const BIN_DELTA_MAGIC: &[u8; 4] = b"PRMD";
+
+/// Version of the `bin-delta` layout; [`read_bin_delta`] rejects any other
+pub const BIN_DELTA_VERSION: u8 = 1;
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lines" => Ok(OutputFormat::Lines),
+            "comma" => Ok(OutputFormat::Comma),
+            "binary" => Ok(OutputFormat::Binary),
+            "bin-delta" => Ok(OutputFormat::BinDelta),
+            other => Err(format!(
+                "unknown output format '{}' (expected lines, comma, binary or bin-delta)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Lines => write!(f, "lines"),
+            OutputFormat::Comma => write!(f, "comma"),
+            OutputFormat::Binary => write!(f, "binary"),
+            OutputFormat::BinDelta => write!(f, "bin-delta"),
+        }
+    }
+}
+
+/// Write `primes` to `writer` in the given format
+///
+/// The writer is not flushed; wrap unbuffered sinks in a [`BufWriter`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{write_primes, OutputFormat};
+///
+/// let mut out = Vec::new();
+/// write_primes(&mut out, &[2, 3, 5], OutputFormat::Comma)?;
+/// assert_eq!(out, b"2,3,5\n");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn write_primes<W: Write>(writer: &mut W, primes: &[u64], format: OutputFormat) -> io::Result<()> {
+    match format {
+        OutputFormat::Lines => {
+            for prime in primes {
+                writeln!(writer, "{}", prime)?;
+            }
+        }
+        OutputFormat::Comma => {
+            for (i, prime) in primes.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write!(writer, "{}", prime)?;
+            }
+            writeln!(writer)?;
+        }
+        OutputFormat::Binary => {
+            for prime in primes {
+                writer.write_all(&prime.to_le_bytes())?;
+            }
+        }
+        OutputFormat::BinDelta => {
+            writer.write_all(BIN_DELTA_MAGIC)?;
+            writer.write_all(&[BIN_DELTA_VERSION])?;
+            writer.write_all(&(primes.len() as u64).to_le_bytes())?;
+            let mut previous = 0u64;
+            for &prime in primes {
+                // Wrapping, so even an unsorted list decodes back unchanged
+                write_varint(writer, prime.wrapping_sub(previous))?;
+                previous = prime;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        writer.write_all(&[value as u8 | 0x80])?;
+        value >>= 7;
+    }
+    writer.write_all(&[value as u8])
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        let bits = u64::from(byte[0] & 0x7f);
+        if bits << shift >> shift != bits {
+            return Err(invalid_data("varint does not fit in a u64".to_string()));
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint longer than 10 bytes".to_string()))
+}
+
+/// Read a prime list written in the `bin-delta` format
+///
+/// A wrong magic or version, or a malformed varint, is an
+/// [`io::ErrorKind::InvalidData`] error; a truncated stream is `UnexpectedEof`.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{read_bin_delta, write_primes, OutputFormat};
+///
+/// let mut encoded = Vec::new();
+/// write_primes(&mut encoded, &[2, 3, 5, 7], OutputFormat::BinDelta)?;
+/// assert_eq!(encoded.len(), 13 + 4); // header, then one byte per gap
+/// assert_eq!(read_bin_delta(&mut encoded.as_slice())?, vec![2, 3, 5, 7]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_bin_delta<R: Read>(reader: &mut R) -> io::Result<Vec<u64>> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != BIN_DELTA_MAGIC {
+        return Err(invalid_data("not a bin-delta prime list".to_string()));
+    }
+    if header[4] != BIN_DELTA_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported bin-delta version {} (expected {})",
+            header[4], BIN_DELTA_VERSION
+        )));
+    }
+
+    let mut count = [0u8; 8];
+    reader.read_exact(&mut count)?;
+    let count = u64::from_le_bytes(count);
+
+    // Don't trust the count for the allocation; a corrupt file could claim anything
+    let mut primes = Vec::with_capacity(count.min(1 << 20) as usize);
+    let mut previous = 0u64;
+    for _ in 0..count {
+        previous = previous.wrapping_add(read_varint(reader)?);
+        primes.push(previous);
+    }
+    Ok(primes)
+}
+
+/// Create (or truncate) the file at `path` and write `primes` to it through a [`BufWriter`]
+pub fn write_primes_file(path: &Path, primes: &[u64], format: OutputFormat) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_primes(&mut writer, primes, format)?;
+    writer.flush()
+}
+
+/// Indices of every `every`-th element of a list of `len`, plus the last
+///
+/// Yields 0, `every`, 2·`every`, … and finally `len - 1` if that was not
+/// already among them, so the first and last prime are always shown.
+/// `every` of 0 is taken as 1 (everything).
+pub fn sample_indices(len: usize, every: usize) -> impl Iterator<Item = usize> {
+    let every = every.max(1);
+    let last = len.checked_sub(1).filter(|last| !last.is_multiple_of(every));
+    (0..len).step_by(every).chain(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_indices() {
+        let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+        let sampled: Vec<usize> = sample_indices(primes.len(), 3).collect();
+        assert_eq!(sampled, vec![0, 3, 6, 9]);
+        assert_eq!(sampled.iter().map(|&i| primes[i]).collect::<Vec<_>>(), vec![2, 7, 17, 29]);
+
+        // The last one is added when the stride steps over it
+        assert_eq!(sample_indices(8, 3).collect::<Vec<_>>(), vec![0, 3, 6, 7]);
+        assert_eq!(sample_indices(4, 1).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(sample_indices(1, 1000).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(sample_indices(0, 3).count(), 0);
+        assert_eq!(sample_indices(3, 0).count(), 3);
+    }
+
+    #[test]
+    fn test_write_lines_and_binary() {
+        let mut lines = Vec::new();
+        write_primes(&mut lines, &[2, 3, 5, 7], OutputFormat::Lines).unwrap();
+        assert_eq!(lines, b"2\n3\n5\n7\n");
+
+        let mut binary = Vec::new();
+        write_primes(&mut binary, &[2, 4_294_967_311], OutputFormat::Binary).unwrap();
+        assert_eq!(binary.len(), 16);
+        assert_eq!(&binary[..8], &2u64.to_le_bytes());
+        assert_eq!(&binary[8..], &4_294_967_311u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_bin_delta_round_trip() {
+        let primes = crate::simple_sieve(100_000).unwrap();
+        let mut encoded = Vec::new();
+        write_primes(&mut encoded, &primes, OutputFormat::BinDelta).unwrap();
+        assert_eq!(read_bin_delta(&mut encoded.as_slice()).unwrap(), primes);
+        // Every gap below 100 000 fits in one byte
+        assert_eq!(encoded.len(), 13 + primes.len());
+
+        let extremes = [0, 1 << 63, u64::MAX, 5];
+        let mut encoded = Vec::new();
+        write_primes(&mut encoded, &extremes, OutputFormat::BinDelta).unwrap();
+        assert_eq!(read_bin_delta(&mut encoded.as_slice()).unwrap(), extremes);
+    }
+
+    #[test]
+    fn test_bin_delta_rejects_bad_input() {
+        let mut encoded = Vec::new();
+        write_primes(&mut encoded, &[2, 3, 5], OutputFormat::BinDelta).unwrap();
+
+        let mut wrong_magic = encoded.clone();
+        wrong_magic[0] = b'X';
+        let err = read_bin_delta(&mut wrong_magic.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let truncated = &encoded[..encoded.len() - 1];
+        let err = read_bin_delta(&mut &truncated[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Eleven continuation bytes never end a u64
+        let mut overlong = encoded[..13].to_vec();
+        overlong.extend([0xff; 11]);
+        let err = read_bin_delta(&mut overlong.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_parse_output_format() {
+        assert_eq!("bin-delta".parse(), Ok(OutputFormat::BinDelta));
+        assert_eq!("binary".parse(), Ok(OutputFormat::Binary));
+        assert_eq!("Comma".parse(), Ok(OutputFormat::Comma));
+        assert!("json".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Reusable worker threads for repeated parallel sieves
+//!
+//! [`segmented_sieve_parallel`](crate::segmented_sieve_parallel) spawns and
+//! joins fresh OS threads on every call. When the same sieve runs many
+//! times, as in a benchmark loop, that spawn cost is paid again and again.
+//! A [`SievePool`] starts its threads once and feeds them segments through
+//! a channel for as long as it lives.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::{sieve_into, sieve_segment, split_range, BasePrimeCache, SieveError};
+
+/// A unit of work run by one of the pool's threads
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of sieve threads fed through a shared job queue
+///
+/// Dropping the pool closes the queue, lets the threads finish any jobs
+/// already queued, and joins them.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{simple_sieve, SievePool};
+///
+/// let pool = SievePool::new(4);
+/// for limit in [1_000, 100_000] {
+///     assert_eq!(pool.compute(limit)?, simple_sieve(limit)?);
+/// }
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub struct SievePool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl SievePool {
+    /// Start `num_threads` worker threads (at least one)
+    ///
+    /// # Panics
+    /// Panics if the operating system refuses to spawn a thread.
+    pub fn new(num_threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..num_threads.max(1))
+            .map(|id| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("sieve-pool-{}", id))
+                    .spawn(move || worker_loop(&receiver))
+                    .expect("Failed to spawn sieve pool thread")
+            })
+            .collect();
+
+        SievePool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Number of worker threads
+    pub fn num_threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queue a job for the next free thread
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        self.sender
+            .as_ref()
+            .expect("Sieve pool is shutting down")
+            .send(Box::new(job))
+            .expect("Sieve pool threads have exited");
+    }
+
+    /// All primes up to `limit`, sieved on the pool's threads
+    ///
+    /// Same strategy as [`segmented_sieve_parallel`](crate::segmented_sieve_parallel):
+    /// base primes up to √limit sequentially, then (√limit, limit] split
+    /// into one segment per thread.
+    ///
+    /// # Errors
+    /// Returns the first [`SieveError`] raised by the base-prime sieve or by
+    /// any segment.
+    ///
+    /// # Panics
+    /// Panics if a pool thread panicked while sieving a segment.
+    pub fn compute(&self, limit: u64) -> Result<Vec<u64>, SieveError> {
+        let mut primes = Vec::new();
+        self.compute_into(limit, &mut primes)?;
+        Ok(primes)
+    }
+
+    /// [`compute`](Self::compute) with the base primes taken from `cache`
+    pub fn compute_cached(&self, limit: u64, cache: &mut BasePrimeCache) -> Result<Vec<u64>, SieveError> {
+        let mut primes = Vec::new();
+        self.compute_cached_into(limit, cache, &mut primes)?;
+        Ok(primes)
+    }
+
+    /// [`compute`](Self::compute) into `out`, reusing its capacity as
+    /// [`sieve_into`] does
+    pub fn compute_into(&self, limit: u64, out: &mut Vec<u64>) -> Result<(), SieveError> {
+        self.compute_from_base(limit, out, sieve_into)
+    }
+
+    /// [`compute_cached`](Self::compute_cached) into `out`, reusing its capacity
+    pub fn compute_cached_into(
+        &self,
+        limit: u64,
+        cache: &mut BasePrimeCache,
+        out: &mut Vec<u64>,
+    ) -> Result<(), SieveError> {
+        self.compute_from_base(limit, out, |_, out| {
+            out.extend_from_slice(cache.base_primes(limit)?);
+            Ok(())
+        })
+    }
+
+    /// [`compute_into`](Self::compute_into), getting the primes up to
+    /// √limit from `sieve_base` (which appends them to the cleared `out`)
+    fn compute_from_base(
+        &self,
+        limit: u64,
+        out: &mut Vec<u64>,
+        sieve_base: impl FnOnce(u64, &mut Vec<u64>) -> Result<(), SieveError>,
+    ) -> Result<(), SieveError> {
+        out.clear();
+        if limit < 2 {
+            return Ok(());
+        }
+
+        let sqrt_limit = (limit as f64).sqrt() as u64;
+        sieve_base(sqrt_limit, out)?;
+        if sqrt_limit >= limit {
+            return Ok(());
+        }
+
+        let segments = split_range(sqrt_limit + 1, limit, self.num_threads());
+        let base_primes = Arc::new(out.clone());
+        let (result_sender, results) = mpsc::channel();
+
+        for (index, &(low, high)) in segments.iter().enumerate() {
+            let base_primes = Arc::clone(&base_primes);
+            let result_sender = result_sender.clone();
+            self.execute(move || {
+                // The receiver only goes away if compute() itself panicked
+                let _ = result_sender.send((index, sieve_segment(low, high, &base_primes)));
+            });
+        }
+        drop(result_sender);
+
+        // Results arrive in completion order; put them back in range order
+        let mut segment_primes = vec![Vec::new(); segments.len()];
+        for _ in 0..segments.len() {
+            let (index, primes) = results.recv().expect("Sieve pool thread panicked");
+            segment_primes[index] = primes?;
+        }
+
+        out.extend(segment_primes.into_iter().flatten());
+        Ok(())
+    }
+}
+
+/// Run jobs until the pool's sender is dropped
+fn worker_loop(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // Hold the lock only while taking a job, not while running it
+        let job = receiver.lock().unwrap().recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => break,
+        }
+    }
+}
+
+impl Drop for SievePool {
+    fn drop(&mut self) {
+        // Closing the channel makes every idle worker's recv() fail
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_sieve;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_pool_reused_across_computes() {
+        let pool = SievePool::new(3);
+        for limit in [0, 2, 30, 10_000, 1_000_000, 10_000] {
+            assert_eq!(pool.compute(limit).unwrap(), simple_sieve(limit).unwrap(), "limit {}", limit);
+        }
+        assert_eq!(pool.num_threads(), 3);
+
+        let mut cache = BasePrimeCache::new();
+        for limit in [1_000_000, 0, 10_000] {
+            assert_eq!(pool.compute_cached(limit, &mut cache).unwrap(), simple_sieve(limit).unwrap());
+        }
+        assert_eq!(cache.sieved_to(), 1000);
+
+        // One buffer across computes, shrinking back down without leftovers
+        let mut primes = Vec::new();
+        for limit in [1_000_000, 10_000, 1] {
+            pool.compute_into(limit, &mut primes).unwrap();
+            assert_eq!(primes, simple_sieve(limit).unwrap());
+            pool.compute_cached_into(limit, &mut cache, &mut primes).unwrap();
+            assert_eq!(primes, simple_sieve(limit).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_drop_joins_threads() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let pool = SievePool::new(2);
+        for _ in 0..4 {
+            let finished = Arc::clone(&finished);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(20));
+                finished.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        drop(pool);
+
+        // Queued jobs ran to completion, and the threads released every clone
+        assert_eq!(finished.load(Ordering::SeqCst), 4);
+        assert_eq!(Arc::strong_count(&finished), 1);
+    }
+}
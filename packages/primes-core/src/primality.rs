@@ -0,0 +1,105 @@
+//! Primality test for a single number
+//!
+//! Sieving is the fastest way to find every prime in a range, but to check
+//! one large number it would need a buffer up to that number. Miller-Rabin
+//! answers the question for one `u64` directly.
+
+/// Witnesses that make Miller-Rabin exact for every `u64`
+///
+/// No composite below 3.3 * 10^24 is a strong pseudoprime to all of the
+/// first twelve primes (Sorenson and Webster, 2015).
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// `a * b mod m` without overflow
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// `base^exp mod m` by square-and-multiply
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin primality test
+///
+/// Exact for every `u64`: with the fixed witness set there are no false
+/// positives, and no sieve is built.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::is_prime_mr;
+///
+/// assert!(is_prime_mr((1 << 61) - 1)); // Mersenne prime M61
+/// assert!(!is_prime_mr(561)); // Carmichael number 3 * 11 * 17
+/// ```
+pub fn is_prime_mr(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    // Small primes are witnesses themselves; their multiples are composite
+    for &p in &WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    'witness: for &a in &WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_sieve;
+
+    #[test]
+    fn test_known_values() {
+        assert!(!is_prime_mr(0));
+        assert!(!is_prime_mr(1));
+        assert!(is_prime_mr(2));
+        assert!(is_prime_mr(3));
+        assert!(is_prime_mr((1 << 61) - 1));
+        assert!(is_prime_mr(18_446_744_073_709_551_557)); // largest u64 prime
+        assert!(!is_prime_mr(u64::MAX));
+
+        // Carmichael numbers fool the Fermat test but not Miller-Rabin
+        for carmichael in [561, 1105, 1729, 2465, 2821, 6601] {
+            assert!(!is_prime_mr(carmichael), "{}", carmichael);
+        }
+        // Strong pseudoprime to every prime base up to 23
+        assert!(!is_prime_mr(3_825_123_056_546_413_051));
+    }
+
+    #[test]
+    fn test_matches_sieve() {
+        let primes = simple_sieve(100_000).unwrap();
+        let from_mr: Vec<u64> = (0..=100_000).filter(|&n| is_prime_mr(n)).collect();
+        assert_eq!(from_mr, primes);
+    }
+}
@@ -0,0 +1,104 @@
+//! π(x) without a sieve over `[0, x]`
+//!
+//! Legendre's formula counts the primes up to x by inclusion-exclusion
+//! over the primes up to √x. Evaluated naively that has exponentially many
+//! terms, but every term is π or φ of some ⌊x / n⌋, and there are only
+//! about 2√x distinct values of ⌊x / n⌋. Keeping a count S(v) for each of
+//! them and removing one prime p ≤ √x at a time,
+//!
+//! ```text
+//! S(v) -= S(⌊v / p⌋) - S(p - 1)    for every v ≥ p²
+//! ```
+//!
+//! turns S(v) from "numbers in [2, v]" into "primes in [2, v]". That takes
+//! O(x^(3/4)) steps and O(√x) memory, against O(x log log x) steps and
+//! x / 8 bytes for the bit-packed sieve.
+
+/// Limits above this are counted with [`prime_count_fast`] by the apps'
+/// `--count-only` modes; below it the sieve is fast enough and also
+/// reports per-segment metrics
+pub const FAST_COUNT_THRESHOLD: u64 = 1_000_000_000;
+
+/// Largest `x` the apps pass to [`prime_count_fast`]: about 50 MB of
+/// counts and a few seconds of work
+pub const MAX_FAST_COUNT_LIMIT: u64 = 10_000_000_000_000;
+
+/// π(x), the number of primes up to `x`, by Legendre's formula
+///
+/// Exact for every `u64`, but memory is 16·√x bytes and time grows as
+/// x^(3/4), so very large `x` (beyond [`MAX_FAST_COUNT_LIMIT`]) gets slow.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{count_primes, prime_count_fast};
+///
+/// assert_eq!(prime_count_fast(1_000_000) as usize, count_primes(1_000_000)?);
+/// assert_eq!(prime_count_fast(10_000_000_000), 455_052_511);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn prime_count_fast(x: u64) -> u64 {
+    if x < 2 {
+        return 0;
+    }
+
+    let root = x.isqrt();
+    let r = root as usize;
+    // small[v] = S(v) for v ≤ √x; large[i] = S(⌊x / i⌋) for 1 ≤ i ≤ √x.
+    // Before any prime is removed, S(v) = v - 1 (the numbers 2..=v).
+    let mut small: Vec<u64> = (0..=root).map(|v| v.saturating_sub(1)).collect();
+    let mut large: Vec<u64> = (0..=root).map(|i| x.checked_div(i).map_or(0, |q| q - 1)).collect();
+
+    for p in 2..=r {
+        if small[p] == small[p - 1] {
+            continue; // p was removed as a multiple of a smaller prime
+        }
+        let primes_below_p = small[p - 1];
+        let p_squared = (p * p) as u64;
+
+        // ⌊x / i⌋ ≥ p² exactly when i ≤ ⌊x / p²⌋
+        for i in 1..=r.min((x / p_squared) as usize) {
+            let d = i * p;
+            let quotient = if d <= r { large[d] } else { small[(x / d as u64) as usize] };
+            large[i] -= quotient - primes_below_p;
+        }
+        // Downward, so each S(v / p) read is still from before this prime
+        for v in (p * p..=r).rev() {
+            small[v] -= small[v / p] - primes_below_p;
+        }
+    }
+
+    large[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_primes, simple_sieve};
+
+    #[test]
+    fn test_matches_sieve_at_powers_of_ten() {
+        for x in [1_000_000, 10_000_000, 100_000_000] {
+            assert_eq!(prime_count_fast(x) as usize, count_primes(x).unwrap(), "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_small_values() {
+        let primes = simple_sieve(2000).unwrap();
+        for x in 0..=2000u64 {
+            let expected = primes.partition_point(|&p| p <= x) as u64;
+            assert_eq!(prime_count_fast(x), expected, "x = {}", x);
+        }
+        // Squares of primes, where p² is the first value a prime removes
+        for x in [97 * 97, 1009 * 1009, 1009 * 1009 - 1] {
+            assert_eq!(prime_count_fast(x) as usize, count_primes(x).unwrap(), "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_beyond_sieve_range() {
+        assert_eq!(prime_count_fast(10_000_000_000), 455_052_511);
+        assert_eq!(prime_count_fast(100_000_000_000), 4_118_054_813);
+    }
+}
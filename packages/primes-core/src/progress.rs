@@ -0,0 +1,71 @@
+//! Progress counters for long parallel runs
+//!
+//! The threaded sieves work through their segments in sub-segments (see
+//! [`ParallelOptions`](crate::ParallelOptions)) and bump a shared
+//! [`SieveProgress`] after each one, so another thread can report how far
+//! a run has got.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Chunks completed so far out of the total for the current run
+///
+/// Shared between the sieve threads, which only ever `fetch_add` to it,
+/// and a monitor thread that reads it.
+#[derive(Debug, Default)]
+pub struct SieveProgress {
+    done: AtomicU64,
+    total: AtomicU64,
+}
+
+impl SieveProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chunks finished so far
+    pub fn chunks_done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// Chunks in the whole run (0 until the run has been split up)
+    pub fn total_chunks(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Reset the counter for a run of `total` chunks
+    pub(crate) fn start(&self, total: u64) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Record one finished chunk
+    pub(crate) fn chunk_done(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{segmented_count_parallel_with, segmented_sieve_parallel_with, ParallelOptions};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_progress_counts_every_chunk() {
+        let progress = Arc::new(SieveProgress::new());
+        let options = ParallelOptions {
+            max_segment_bytes: 1 << 20,
+            progress: Some(Arc::clone(&progress)),
+            ..ParallelOptions::default()
+        };
+
+        // 3 threads over (2236, 5_000_000]: about 1.67M numbers, 2 chunks each
+        segmented_sieve_parallel_with(5_000_000, 3, &options).unwrap();
+        assert_eq!(progress.total_chunks(), 6);
+        assert_eq!(progress.chunks_done(), 6);
+
+        // Reusing the counter starts it over
+        segmented_count_parallel_with(5_000_000, 3, &options).unwrap();
+        assert_eq!(progress.chunks_done(), 6);
+    }
+}
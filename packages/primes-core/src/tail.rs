@@ -0,0 +1,105 @@
+//! The largest primes of a range
+//!
+//! [`tail_primes`] sieves the range downward from `limit` one segment at
+//! a time and stops as soon as it holds `n` primes. Primes are dense
+//! enough that the biggest few below 10^12 take a single segment, so the
+//! cost depends on `n` and barely on `limit`: only the base primes up to
+//! √limit grow with it.
+
+use crate::error::SieveError;
+use crate::{sieve_segment, simple_sieve, MAX_FAST_COUNT_LIMIT};
+
+/// Numbers sieved per step (256 KiB of `bool` flags)
+const TAIL_SEGMENT_SIZE: u64 = 1 << 18;
+
+/// The largest `n` primes in `[start, limit]`, in ascending order
+///
+/// Fewer than `n` come back if the range holds fewer primes. The result
+/// is the same as the last `n` entries of the full list, without ever
+/// collecting it. Since nothing near `start` is sieved unless it is
+/// needed, `limit` may go past [`MAX_SIEVE_LIMIT`](crate::MAX_SIEVE_LIMIT),
+/// up to the [`MAX_FAST_COUNT_LIMIT`] that `--count-only` can reach.
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if `limit` is beyond [`MAX_FAST_COUNT_LIMIT`].
+///
+/// # Example
+///
+/// ```
+/// use primes_core::tail_primes;
+///
+/// assert_eq!(tail_primes(2, 100, 3)?, vec![83, 89, 97]);
+/// assert_eq!(tail_primes(2, 5, 10)?, vec![2, 3, 5]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn tail_primes(start: u64, limit: u64, n: usize) -> Result<Vec<u64>, SieveError> {
+    if limit > MAX_FAST_COUNT_LIMIT {
+        return Err(SieveError::LimitTooLarge {
+            requested: limit,
+            max_supported: MAX_FAST_COUNT_LIMIT,
+        });
+    }
+    let bottom = start.max(2);
+    if n == 0 || bottom > limit {
+        return Ok(vec![]);
+    }
+
+    // Segments from the top down, until they hold n primes or reach `start`
+    let base_primes = simple_sieve(limit.isqrt())?;
+    let mut segments = Vec::new();
+    let mut found = 0;
+    let mut high = limit;
+    while found < n {
+        let low = high.saturating_sub(TAIL_SEGMENT_SIZE - 1).max(bottom);
+        let segment = sieve_segment(low, high, &base_primes)?;
+        found += segment.len();
+        segments.push(segment);
+        if low == bottom {
+            break;
+        }
+        high = low - 1;
+    }
+
+    let mut tail: Vec<u64> = segments.into_iter().rev().flatten().collect();
+    tail.drain(..found.saturating_sub(n));
+    Ok(tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_is_the_suffix_of_the_full_list() {
+        assert_eq!(tail_primes(2, 100, 3).unwrap(), vec![83, 89, 97]);
+
+        // Spans several segments
+        let limit = 3 * TAIL_SEGMENT_SIZE + 12_345;
+        let primes = simple_sieve(limit).unwrap();
+        for n in [1, 7, 1000] {
+            assert_eq!(tail_primes(2, limit, n).unwrap(), primes[primes.len() - n..]);
+        }
+        let window: Vec<u64> = primes.iter().copied().filter(|&p| p >= 500_000).collect();
+        assert_eq!(tail_primes(500_000, limit, 1_000_000).unwrap(), window);
+    }
+
+    #[test]
+    fn test_tail_edge_cases() {
+        assert_eq!(tail_primes(2, 100, 0).unwrap(), vec![]);
+        assert_eq!(tail_primes(0, 1, 5).unwrap(), vec![]);
+        assert_eq!(tail_primes(90, 96, 5).unwrap(), vec![]);
+        assert_eq!(tail_primes(0, 10, 100).unwrap(), vec![2, 3, 5, 7]);
+        assert!(tail_primes(2, u64::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_tail_beyond_the_sieve_limit() {
+        // Largest primes below 10^11 and 10^13; only the top segment is sieved
+        assert_eq!(
+            tail_primes(2, 100_000_000_000, 3).unwrap(),
+            vec![99_999_999_943, 99_999_999_947, 99_999_999_977]
+        );
+        assert_eq!(tail_primes(0, MAX_FAST_COUNT_LIMIT, 1).unwrap(), vec![9_999_999_999_971]);
+        assert!(tail_primes(2, MAX_FAST_COUNT_LIMIT + 1, 1).is_err());
+    }
+}
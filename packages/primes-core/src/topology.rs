@@ -0,0 +1,84 @@
+//! Physical CPU cores, as opposed to the logical CPUs SMT exposes
+//!
+//! With hyperthreading each core shows up as two (or more) logical CPUs
+//! that share its execution units, so a benchmark run with more threads
+//! than physical cores stops scaling for reasons that have nothing to do
+//! with the code. On Linux every logical CPU describes its core under
+//! `/sys/devices/system/cpu/cpu<N>/topology/`: `core_id` is unique within
+//! a socket and `physical_package_id` names the socket, so each distinct
+//! pair is one physical core.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+/// sysfs directory listing every logical CPU
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Physical cores on this machine, or `None` where the topology is unknown
+pub fn physical_cores() -> Option<usize> {
+    if cfg!(target_os = "linux") {
+        physical_cores_in(Path::new(CPU_DIR))
+    } else {
+        None
+    }
+}
+
+/// Distinct (`physical_package_id`, `core_id`) pairs of the `cpu<N>` entries under `dir`
+///
+/// A CPU without a `physical_package_id` is taken to be on socket 0.
+fn physical_cores_in(dir: &Path) -> Option<usize> {
+    let read = |cpu: &Path, name: &str| std::fs::read_to_string(cpu.join("topology").join(name)).ok();
+
+    let cores: HashSet<(String, String)> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu")
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter_map(|entry| {
+            let cpu = entry.path();
+            let core = read(&cpu, "core_id")?.trim().to_string();
+            let package = read(&cpu, "physical_package_id").map_or_else(|| "0".to_string(), |id| id.trim().to_string());
+            Some((package, core))
+        })
+        .collect();
+
+    (!cores.is_empty()).then_some(cores.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lay out `cpu<N>/topology/{physical_package_id,core_id}` under `dir`
+    fn write_cpus(dir: &Path, cpus: &[(u32, u32)]) {
+        for (n, &(package, core)) in cpus.iter().enumerate() {
+            let topology = dir.join(format!("cpu{}", n)).join("topology");
+            std::fs::create_dir_all(&topology).unwrap();
+            std::fs::write(topology.join("physical_package_id"), format!("{}\n", package)).unwrap();
+            std::fs::write(topology.join("core_id"), format!("{}\n", core)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_physical_cores_from_sample_sysfs() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("cpu-topology-{}", std::process::id()));
+
+        // Two sockets of two cores, each core with two SMT siblings; core
+        // ids repeat across sockets
+        let cpus = [(0, 0), (0, 1), (1, 0), (1, 1), (0, 0), (0, 1), (1, 0), (1, 1)];
+        write_cpus(&dir, &cpus);
+        // Entries that are not CPUs are skipped
+        std::fs::create_dir_all(dir.join("cpufreq")).unwrap();
+        std::fs::write(dir.join("online"), "0-7\n").unwrap();
+        let counted = physical_cores_in(&dir);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(counted, Some(4));
+        assert_eq!(physical_cores_in(Path::new("/nonexistent")), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Twin primes
+//!
+//! Twin primes are pairs (p, p + 2) that are both prime, such as (11, 13).
+//! Both members of a pair are always consecutive primes, so the pairs can
+//! be read straight off a sorted prime list.
+
+/// Twin prime pairs (p, p + 2) in a sorted list of consecutive primes
+///
+/// A list built by concatenating segment results (as the parallel sieves
+/// do) works too: a pair split across two segments sits at the end of one
+/// and the start of the next, which are adjacent once concatenated.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::{simple_sieve, twin_primes};
+///
+/// let primes = simple_sieve(20)?;
+/// let pairs: Vec<_> = twin_primes(&primes).collect();
+/// assert_eq!(pairs, vec![(3, 5), (5, 7), (11, 13), (17, 19)]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn twin_primes(primes: &[u64]) -> impl Iterator<Item = (u64, u64)> + '_ {
+    primes
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] == 2)
+        .map(|pair| (pair[0], pair[1]))
+}
+
+/// Number of twin prime pairs in a sorted list of consecutive primes
+///
+/// See [`twin_primes`].
+pub fn count_twin_primes(primes: &[u64]) -> usize {
+    twin_primes(primes).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sieve_segment, simple_sieve};
+
+    #[test]
+    fn test_count_twin_primes() {
+        assert_eq!(count_twin_primes(&simple_sieve(100).unwrap()), 8);
+        assert_eq!(count_twin_primes(&simple_sieve(1000).unwrap()), 35);
+        assert_eq!(count_twin_primes(&[]), 0);
+        assert_eq!(count_twin_primes(&[2, 3]), 0);
+    }
+
+    #[test]
+    fn test_pair_straddling_segments_is_counted() {
+        // (17, 19) is split between the two segments
+        let base_primes = simple_sieve(10).unwrap();
+        let mut primes = sieve_segment(2, 18, &base_primes).unwrap();
+        primes.extend(sieve_segment(19, 100, &base_primes).unwrap());
+
+        assert_eq!(count_twin_primes(&primes), 8);
+        assert!(twin_primes(&primes).any(|pair| pair == (17, 19)));
+    }
+}
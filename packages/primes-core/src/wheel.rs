@@ -0,0 +1,139 @@
+//! Mod-30 wheel sieve
+//!
+//! Only 8 of every 30 numbers are coprime to 2, 3 and 5, so the wheel sieve
+//! stores just those candidates: 8 flags per 30 numbers (~27% of the
+//! numbers, about half the storage of the odd-only sieve).
+
+use crate::error::{check_limit, SieveError};
+use crate::BitSieve;
+
+/// Residues modulo 30 that are coprime to 30, in ascending order
+const RESIDUES: [u64; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+
+/// Position of each residue in [`RESIDUES`] (`u64::MAX` for non-coprime residues)
+const POSITION: [u64; 30] = {
+    let mut table = [u64::MAX; 30];
+    let mut i = 0;
+    while i < RESIDUES.len() {
+        table[RESIDUES[i] as usize] = i as u64;
+        i += 1;
+    }
+    table
+};
+
+/// Number represented by sieve index `idx`
+#[inline]
+fn value_of(idx: u64) -> u64 {
+    30 * (idx / 8) + RESIDUES[(idx % 8) as usize]
+}
+
+/// Sieve index of `n`, which must be coprime to 30
+#[inline]
+fn index_of(n: u64) -> u64 {
+    (n / 30) * 8 + POSITION[(n % 30) as usize]
+}
+
+/// Wheel-factorized (mod 30) Sieve of Eratosthenes
+///
+/// Multiples of 2, 3 and 5 are never stored: index `i` represents the
+/// number `30 * (i / 8) + RESIDUES[i % 8]`. The small primes 2, 3 and 5 are
+/// added to the output explicitly.
+///
+/// # Algorithm Overview
+/// 1. Create a bit array with one flag per number in [1, limit] coprime to 30
+/// 2. Mark index 0 (the number 1) as non-prime
+/// 3. For each prime p up to sqrt(limit), mark p * q for every q >= p that is
+///    coprime to 30. For a fixed residue of q, consecutive products are
+///    30p apart and share a residue, so they are 8p apart in index space.
+/// 4. Return 2, 3, 5 followed by every number still marked
+///
+/// # Errors
+/// [`SieveError::LimitTooLarge`] if `limit` exceeds [`MAX_SIEVE_LIMIT`](crate::MAX_SIEVE_LIMIT), or
+/// [`SieveError::AllocationFailed`] if the sieve buffer cannot be allocated.
+///
+/// # Example
+///
+/// ```
+/// use primes_core::wheel30_sieve;
+///
+/// assert_eq!(wheel30_sieve(30)?, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+/// # Ok::<(), primes_core::SieveError>(())
+/// ```
+pub fn wheel30_sieve(limit: u64) -> Result<Vec<u64>, SieveError> {
+    check_limit(limit)?;
+
+    let mut primes: Vec<u64> = [2, 3, 5].into_iter().filter(|&p| p <= limit).collect();
+    if limit < 7 {
+        return Ok(primes);
+    }
+
+    // Candidates in full turns of the wheel plus those in the partial last turn
+    let len = (limit / 30) * 8 + RESIDUES.iter().filter(|&&r| r <= limit % 30).count() as u64;
+    let mut is_prime = BitSieve::new(len)?;
+
+    // Index 0 is the number 1, which is not prime
+    is_prime.set_false(0);
+
+    let sqrt_limit = (limit as f64).sqrt() as u64;
+
+    // `limit` is capped at MAX_SIEVE_LIMIT, so p * q cannot overflow u64 here
+    for idx in 1..len {
+        let prime = value_of(idx);
+        if prime > sqrt_limit {
+            break;
+        }
+        if !is_prime.get(idx) {
+            continue;
+        }
+
+        let turn_start = prime - prime % 30;
+        for &residue in &RESIDUES {
+            // Smallest q >= p with q ≡ residue (mod 30)
+            let mut q = turn_start + residue;
+            if q < prime {
+                q += 30;
+            }
+
+            let mut multiple_idx = index_of(prime * q);
+            while multiple_idx < len {
+                is_prime.set_false(multiple_idx);
+                multiple_idx += 8 * prime;
+            }
+        }
+    }
+
+    primes.extend(is_prime.iter_ones().map(value_of));
+    Ok(primes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_sieve;
+
+    #[test]
+    fn test_wheel_matches_classic() {
+        for limit in [1000, 100_000, 5_000_000] {
+            assert_eq!(wheel30_sieve(limit).unwrap(), simple_sieve(limit).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_wheel_small_limits() {
+        for limit in 0..=120 {
+            assert_eq!(
+                wheel30_sieve(limit).unwrap(),
+                simple_sieve(limit).unwrap(),
+                "limit {}",
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        for idx in 0..240 {
+            assert_eq!(index_of(value_of(idx)), idx);
+        }
+    }
+}
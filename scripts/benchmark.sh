@@ -70,7 +70,7 @@ run_sequential_benchmark() {
     echo -n "  Iteration $i/$ITERATIONS: "
 
     # Run and capture CSV output
-    result=$("$SEQUENTIAL_BIN" --limit "$LIMIT" --csv)
+    result=$("$SEQUENTIAL_BIN" --limit "$LIMIT" --csv --no-header)
 
     # Parse time from result
     time_ms=$(echo "$result" | cut -d',' -f3)
@@ -108,7 +108,7 @@ run_multithread_benchmark() {
     for i in $(seq 1 $ITERATIONS); do
       echo -n "  Iteration $i/$ITERATIONS: "
 
-      result=$("$MULTITHREAD_BIN" --limit "$LIMIT" --threads "$threads" --csv)
+      result=$("$MULTITHREAD_BIN" --limit "$LIMIT" --threads "$threads" --format csv --no-header)
 
       time_ms=$(echo "$result" | cut -d',' -f3)
       prime_count=$(echo "$result" | cut -d',' -f4)
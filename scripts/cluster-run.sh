@@ -130,7 +130,7 @@ run_benchmarks() {
   log_info "Running single-node baseline..."
   for limit in "${limits[@]}"; do
     for _ in $(seq 1 $iterations); do
-      result=$("$BINARY" --limit "$limit" --csv 2>/dev/null)
+      result=$("$BINARY" --limit "$limit" --csv --no-header 2>/dev/null)
       echo "single,1,$limit,$(echo "$result" | cut -d',' -f3),$(echo "$result" | cut -d',' -f4)" >>"$output_file"
     done
   done
@@ -173,7 +173,7 @@ run_benchmarks() {
           -np 3 \
           --hostfile /app/hostfile \
           --allow-run-as-root \
-          /app/primes-mpi --limit "$limit" --csv 2>/dev/null)
+          /app/primes-mpi --limit "$limit" --csv --no-header 2>/dev/null)
         echo "mpi,3,$limit,$(echo "$result" | cut -d',' -f3),$(echo "$result" | cut -d',' -f4)" >>"$output_file"
       done
     done
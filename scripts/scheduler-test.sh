@@ -117,7 +117,7 @@ run_basic_tests() {
     --threads "$THREADS" \
     --limit "$LIMIT" \
     --iterations "$ITERATIONS" \
-    --csv >>"$output_file"
+    --csv --no-header >>"$output_file"
 
   # Test with nice command (different priorities)
   for nice_val in 19 10 0; do
@@ -127,7 +127,7 @@ run_basic_tests() {
       --threads "$THREADS" \
       --limit "$LIMIT" \
       --iterations "$ITERATIONS" \
-      --csv 2>/dev/null >>"$output_file" ||
+      --csv --no-header 2>/dev/null >>"$output_file" ||
       log_warn "nice $nice_val requires privileges, skipping"
   done
 
@@ -167,7 +167,7 @@ run_realtime_tests() {
         --priority "$priority" \
         --limit "$LIMIT" \
         --iterations "$ITERATIONS" \
-        --csv >>"$output_file" 2>/dev/null && echo "✓" || echo "✗"
+        --csv --no-header >>"$output_file" 2>/dev/null && echo "✓" || echo "✗"
     else
       "$SCHEDULER_BIN" \
         --policy fifo \
@@ -175,7 +175,7 @@ run_realtime_tests() {
         --priority "$priority" \
         --limit "$LIMIT" \
         --iterations "$ITERATIONS" \
-        --csv >>"$output_file" 2>/dev/null && echo "✓" || echo "✗ (needs sudo)"
+        --csv --no-header >>"$output_file" 2>/dev/null && echo "✓" || echo "✗ (needs sudo)"
     fi
   done
 
@@ -193,7 +193,7 @@ run_realtime_tests() {
         --priority "$priority" \
         --limit "$LIMIT" \
         --iterations "$ITERATIONS" \
-        --csv >>"$output_file" 2>/dev/null && echo "✓" || echo "✗"
+        --csv --no-header >>"$output_file" 2>/dev/null && echo "✓" || echo "✗"
     else
       "$SCHEDULER_BIN" \
         --policy rr \
@@ -201,7 +201,7 @@ run_realtime_tests() {
         --priority "$priority" \
         --limit "$LIMIT" \
         --iterations "$ITERATIONS" \
-        --csv >>"$output_file" 2>/dev/null && echo "✓" || echo "✗ (needs sudo)"
+        --csv --no-header >>"$output_file" 2>/dev/null && echo "✓" || echo "✗ (needs sudo)"
     fi
   done
 